@@ -0,0 +1,463 @@
+use std::{hash::BuildHasher, ops::Deref};
+
+use ahash::{AHashMap, RandomState};
+
+use crate::{
+  AliasError, BatchError, CategoryChange, CategoryHandle, Entry,
+  ErrAlreadyRegistered, ErrCategoryAlreadyRegistered, ErrCategoryCycle,
+  FrozenRegistry, ImportCategoryError, InsertByNsidError, InvalidNamespace,
+  MergeError, MergePolicy, MigrationTable, NamespacedID, Registry,
+  RegistryHandle, RegistryKey, RenameNamespaceError,
+};
+
+/// The registration phase of a registry.
+///
+/// Everything that adds things or changes categories lives here,
+/// and [`RegistryBuilder::build`] hands back a [`FrozenRegistry`] that can't be changed at all.
+/// Pass `&mut RegistryBuilder` to your plugins while loading, and once they're done
+/// nothing can register anything by accident.
+///
+/// Reading from the registry while it's being built works through `Deref`.
+pub struct RegistryBuilder<T, K = NamespacedID, S = RandomState> {
+  registry: Registry<T, K, S>,
+}
+
+impl<T, K: RegistryKey> RegistryBuilder<T, K> {
+  pub fn new() -> Self {
+    Self {
+      registry: Registry::new(),
+    }
+  }
+
+  /// Start with room for this many entries and categories.
+  pub fn with_capacity(entries: usize, categories: usize) -> Self {
+    Self {
+      registry: Registry::with_capacity(entries, categories),
+    }
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> RegistryBuilder<T, K, S> {
+  /// Make room for at least this many more entries and categories. See `Registry::reserve`.
+  pub fn reserve(&mut self, entries: usize, categories: usize) {
+    self.registry.reserve(entries, categories);
+  }
+
+  /// Register something new.
+  pub fn register(
+    &mut self,
+    entry: T,
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, ErrAlreadyRegistered> {
+    self.registry.register(entry, nsid)
+  }
+
+  /// Register something new, handing the entry back if the NSID was already taken.
+  pub fn try_register(
+    &mut self,
+    entry: T,
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, (T, ErrAlreadyRegistered)> {
+    self.registry.try_register(entry, nsid)
+  }
+
+  /// Register a whole batch of things at once, or none of them. See `Registry::register_many`.
+  pub fn register_many(
+    &mut self,
+    entries: impl IntoIterator<Item = (K, T)>,
+  ) -> Result<Vec<RegistryHandle<T, K>>, BatchError<K>> {
+    self.registry.register_many(entries)
+  }
+
+  /// Get the handle of whatever's registered under this NSID, registering what `f` makes if there's nothing yet.
+  pub fn get_or_register(
+    &mut self,
+    nsid: K,
+    f: impl FnOnce() -> T,
+  ) -> RegistryHandle<T, K> {
+    self.registry.get_or_register(nsid, f)
+  }
+
+  /// Create a new category with the specified elements.
+  ///
+  /// Duplicates in the iterator are ignored.
+  pub fn register_category(
+    &mut self,
+    nsid: K,
+    entries: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    self.registry.register_category(nsid, entries)
+  }
+
+  /// Create a new empty category.
+  pub fn register_empty_category(
+    &mut self,
+    nsid: K,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    self.registry.register_empty_category(nsid)
+  }
+
+  /// Insert another element into this category.
+  ///
+  /// Duplicates are ignored.
+  pub fn insert_into_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    entry: RegistryHandle<T, K>,
+  ) {
+    self.registry.insert_into_category(category, entry)
+  }
+
+  /// Insert many elements into this category. Duplicates are ignored.
+  pub fn insert_many_into_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    entries: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) {
+    self.registry.insert_many_into_category(category, entries)
+  }
+
+  /// Remove the given element from this category. Return whether it was actually removed or not.
+  pub fn remove_from_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    entry: RegistryHandle<T, K>,
+  ) -> bool {
+    self.registry.remove_from_category(category, entry)
+  }
+
+  /// Get at whatever's registered under this NSID, to look at it or register something there.
+  /// See `Registry::entry`.
+  pub fn entry(&mut self, nsid: K) -> Entry<'_, T, K, S> {
+    self.registry.entry(nsid)
+  }
+
+  /// Unregister something. See `Registry::unregister`.
+  pub fn unregister(&mut self, handle: RegistryHandle<T, K>) -> Option<T> {
+    self.registry.unregister(handle)
+  }
+
+  /// Unregister everything `keep` returns false for, and hand back what was removed.
+  pub fn retain(&mut self, keep: impl FnMut(K, &T) -> bool) -> Vec<(K, T)> {
+    self.registry.retain(keep)
+  }
+
+  /// Bring everything in another registry into this one. See `Registry::merge`.
+  #[allow(clippy::type_complexity)]
+  pub fn merge(
+    &mut self,
+    other: Registry<T, K, S>,
+    policy: MergePolicy,
+  ) -> Result<AHashMap<RegistryHandle<T, K>, RegistryHandle<T, K>>, MergeError<K>>
+  {
+    self.registry.merge(other, policy)
+  }
+
+  /// Make `alias` another name for whatever's registered under `target`. See `Registry::register_alias`.
+  pub fn register_alias(
+    &mut self,
+    alias: K,
+    target: K,
+  ) -> Result<RegistryHandle<T, K>, AliasError<K>> {
+    self.registry.register_alias(alias, target)
+  }
+
+  /// Stop `alias` being another name for anything, handing back the NSID it pointed at.
+  pub fn remove_alias(&mut self, alias: K) -> Option<K> {
+    self.registry.remove_alias(alias)
+  }
+
+  /// Use this table to find things under their new names. See `Registry::set_migrations`.
+  pub fn set_migrations(
+    &mut self,
+    table: MigrationTable<K>,
+  ) -> Option<MigrationTable<K>> {
+    self.registry.set_migrations(table)
+  }
+
+  /// Stop using a migration table, and hand it back.
+  pub fn take_migrations(&mut self) -> Option<MigrationTable<K>> {
+    self.registry.take_migrations()
+  }
+
+  /// Call `hook` with everything registered from now on. See `Registry::on_register`.
+  ///
+  /// The hooks stay on the registry once it's built, but nothing can be registered then anyways.
+  pub fn on_register(
+    &mut self,
+    hook: impl FnMut(K, &T) + Send + Sync + 'static,
+  ) {
+    self.registry.on_register(hook)
+  }
+
+  /// Call `hook` whenever something goes into or comes out of a category. See `Registry::on_category_change`.
+  pub fn on_category_change(
+    &mut self,
+    hook: impl FnMut(K, K, CategoryChange) + Send + Sync + 'static,
+  ) {
+    self.registry.on_category_change(hook)
+  }
+
+  /// Reserve space for at least `additional` more elements in this category.
+  pub fn reserve_in_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    additional: usize,
+  ) {
+    self.registry.reserve_in_category(category, additional)
+  }
+
+  /// Replace everything in this category with the entries under these NSIDs.
+  /// See `Registry::set_category_members_by_nsid`.
+  pub fn set_category_members_by_nsid(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    nsids: impl IntoIterator<Item = K>,
+  ) -> Result<(), Vec<K>> {
+    self.registry.set_category_members_by_nsid(category, nsids)
+  }
+
+  /// Empty out this category, returning everything that used to be in it.
+  pub fn drain_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+  ) -> Vec<RegistryHandle<T, K>> {
+    self.registry.drain_category(category)
+  }
+
+  /// Put `child` inside `parent`. See `Registry::nest_category`.
+  pub fn nest_category(
+    &mut self,
+    parent: CategoryHandle<T, K>,
+    child: CategoryHandle<T, K>,
+  ) -> Result<bool, ErrCategoryCycle<K>> {
+    self.registry.nest_category(parent, child)
+  }
+
+  /// Take `child` back out of `parent`. Returns whether it was actually in there.
+  pub fn unnest_category(
+    &mut self,
+    parent: CategoryHandle<T, K>,
+    child: CategoryHandle<T, K>,
+  ) -> bool {
+    self.registry.unnest_category(parent, child)
+  }
+
+  /// Create a new category out of everything that's in either of these categories.
+  pub fn register_category_union(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    self.registry.register_category_union(nsid, a, b)
+  }
+
+  /// Create a new category out of everything that's in both of these categories.
+  pub fn register_category_intersection(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    self.registry.register_category_intersection(nsid, a, b)
+  }
+
+  /// Create a new category out of everything that's in `a` but not in `b`.
+  pub fn register_category_difference(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    self.registry.register_category_difference(nsid, a, b)
+  }
+
+  /// Look up something by a NSID, mutably.
+  pub fn lookup_by_nsid_mut(&mut self, nsid: K) -> Option<&mut T> {
+    self.registry.lookup_by_nsid_mut(nsid)
+  }
+
+  /// Look up something from its handle, mutably, while it's still allowed.
+  pub fn lookup_mut(&mut self, handle: RegistryHandle<T, K>) -> &mut T {
+    self.registry.lookup_mut(handle)
+  }
+
+  /// Swap out the value behind a handle, and hand back the old one.
+  pub fn replace(&mut self, handle: RegistryHandle<T, K>, entry: T) -> T {
+    self.registry.replace(handle, entry)
+  }
+
+  /// Change the value behind a handle in place, and return whatever `f` does.
+  pub fn update<R>(
+    &mut self,
+    handle: RegistryHandle<T, K>,
+    f: impl FnOnce(&mut T) -> R,
+  ) -> R {
+    self.registry.update(handle, f)
+  }
+
+  /// End the registration phase.
+  pub fn build(self) -> FrozenRegistry<T, K, S> {
+    self.registry.freeze()
+  }
+}
+
+impl<T, S: BuildHasher + Default> RegistryBuilder<T, NamespacedID, S> {
+  /// Set the namespace that `parse_nsid` falls back on for strings without one.
+  pub fn set_default_namespace(
+    &mut self,
+    namespace: &str,
+  ) -> Result<(), InvalidNamespace> {
+    self.registry.set_default_namespace(namespace)
+  }
+
+  /// Move every category in the `from` namespace over to `to`. See `Registry::rename_category_namespace`.
+  pub fn rename_category_namespace(
+    &mut self,
+    from: &str,
+    to: &str,
+  ) -> Result<usize, RenameNamespaceError> {
+    self.registry.rename_category_namespace(from, to)
+  }
+
+  /// Create a category out of the entries under these NSIDs. See `Registry::import_category`.
+  pub fn import_category(
+    &mut self,
+    nsid: NamespacedID,
+    member_nsids: &[NamespacedID],
+  ) -> Result<CategoryHandle<T>, ImportCategoryError> {
+    self.registry.import_category(nsid, member_nsids)
+  }
+
+  /// Insert an element into a category, looking both of them up by NSID.
+  pub fn insert_nsid_into_category(
+    &mut self,
+    category_nsid: NamespacedID,
+    entry_nsid: NamespacedID,
+  ) -> Result<bool, InsertByNsidError> {
+    self
+      .registry
+      .insert_nsid_into_category(category_nsid, entry_nsid)
+  }
+}
+
+impl<T: Default, K: RegistryKey, S: BuildHasher + Default>
+  RegistryBuilder<T, K, S>
+{
+  /// Register something new that we can auto-generate.
+  pub fn register_default(
+    &mut self,
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, ErrAlreadyRegistered> {
+    self.registry.register_default(nsid)
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Default
+  for RegistryBuilder<T, K, S>
+{
+  fn default() -> Self {
+    Self {
+      registry: Registry::default(),
+    }
+  }
+}
+
+/// Keep registering things into an existing registry.
+impl<T, K, S> From<Registry<T, K, S>> for RegistryBuilder<T, K, S> {
+  fn from(registry: Registry<T, K, S>) -> Self {
+    Self { registry }
+  }
+}
+
+impl<T, K, S> Deref for RegistryBuilder<T, K, S> {
+  type Target = Registry<T, K, S>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.registry
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  };
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn build_keeps_everything() {
+    let mut builder = RegistryBuilder::<u32>::new();
+    let stone = builder.register(1, nsid("game:stone")).unwrap();
+    let dirt = builder.entry(nsid("game:dirt")).or_register(2);
+    builder
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    let stones = builder
+      .register_category(nsid("game:stones"), [stone])
+      .unwrap();
+    let blocks = builder
+      .register_category(nsid("game:blocks"), [dirt])
+      .unwrap();
+    builder.nest_category(blocks, stones).unwrap();
+    assert!(builder.nest_category(stones, blocks).is_err());
+    builder.set_default_namespace("game").unwrap();
+    *builder.lookup_by_nsid_mut(nsid("game:dirt")).unwrap() += 10;
+
+    let registry = builder.build();
+    assert_eq!(registry[nsid("game:rock")], 1);
+    assert_eq!(registry.lookup_by_str("dirt").unwrap(), &12);
+    let blocks = registry
+      .validate_category_nsid(nsid("game:blocks"))
+      .unwrap();
+    assert_eq!(registry.lookup_category_recursive(blocks).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn hooks_run_while_building() {
+    let registered = Arc::new(AtomicUsize::new(0));
+    let changed = Arc::new(AtomicUsize::new(0));
+    let mut builder = RegistryBuilder::<u32>::new();
+    {
+      let registered = registered.clone();
+      builder.on_register(move |_, _| {
+        registered.fetch_add(1, Ordering::SeqCst);
+      });
+      let changed = changed.clone();
+      builder.on_category_change(move |_, _, _| {
+        changed.fetch_add(1, Ordering::SeqCst);
+      });
+    }
+    let stone = builder.register(1, nsid("game:stone")).unwrap();
+    let dirt = builder.register(2, nsid("game:dirt")).unwrap();
+    let blocks = builder
+      .register_empty_category(nsid("game:blocks"))
+      .unwrap();
+    builder.insert_into_category(blocks, stone);
+    builder.insert_into_category(blocks, dirt);
+    builder.unregister(dirt);
+
+    assert_eq!(registered.load(Ordering::SeqCst), 2);
+    assert_eq!(changed.load(Ordering::SeqCst), 3);
+    assert_eq!(builder.build().len(), 1);
+  }
+
+  #[test]
+  fn migrations_carry_over() {
+    let mut builder = RegistryBuilder::<u32>::new();
+    builder.register(1, nsid("game:stone")).unwrap();
+    let mut table = MigrationTable::new();
+    table.add(nsid("old:stone"), nsid("game:stone")).unwrap();
+    assert!(builder.set_migrations(table).is_none());
+
+    let registry = builder.build();
+    assert_eq!(registry.lookup_by_nsid(nsid("old:stone")), Some(&1));
+  }
+}
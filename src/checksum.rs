@@ -0,0 +1,195 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::{NamespacedID, Registry};
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Hash every NSID and category in this registry, for checking that two registries match
+  /// (like a client's and a server's) without sending the whole thing over.
+  ///
+  /// Everything is sorted first, so the order things were registered in doesn't matter.
+  /// The hash only depends on the NSIDs and categories (including nesting), and it's the same across
+  /// runs, machines, and platforms. Values aren't included; use `checksum_with_values` for that.
+  pub fn checksum(&self) -> u64 {
+    let mut hasher = StableHasher::new();
+    self.hash_contents(&mut hasher, |_, _| {});
+    hasher.finish()
+  }
+
+  /// Like `checksum`, but including the values too.
+  ///
+  /// This is only as stable as `T`'s `Hash` impl. Everything in the standard library is fine,
+  /// but pointers and the like obviously aren't.
+  pub fn checksum_with_values(&self) -> u64
+  where
+    T: Hash,
+  {
+    let mut hasher = StableHasher::new();
+    self.hash_contents(&mut hasher, |x, hasher| x.hash(hasher));
+    hasher.finish()
+  }
+
+  fn hash_contents(
+    &self,
+    hasher: &mut StableHasher,
+    mut hash_value: impl FnMut(&T, &mut StableHasher),
+  ) {
+    let mut entries: Vec<_> =
+      self.entries().map(|(_, (x, nsid))| (*nsid, x)).collect();
+    entries.sort_by_key(|(nsid, _)| *nsid);
+    hasher.write_usize(entries.len());
+    for (nsid, x) in entries {
+      hash_nsid(nsid, hasher);
+      hash_value(x, hasher);
+    }
+
+    let mut categories: Vec<_> = self.category_arena.iter().collect();
+    categories.sort_by_key(|(_, (_, nsid))| *nsid);
+    hasher.write_usize(categories.len());
+    for (id, (set, nsid)) in categories {
+      hash_nsid(*nsid, hasher);
+      let mut members: Vec<_> = set.iter().map(|id| self.slot(*id).1).collect();
+      members.sort();
+      let mut children: Vec<_> =
+        self.subcategory_nsids(id).into_iter().collect();
+      children.sort();
+      for list in [members, children] {
+        hasher.write_usize(list.len());
+        for nsid in list {
+          hash_nsid(nsid, hasher);
+        }
+      }
+    }
+  }
+}
+
+/// NSIDs hash their interner keys, which are different every run, so hash the actual strings.
+///
+/// Written out by hand instead of with `str::hash`, so the bytes can't change under us.
+/// The length goes first so `a:bc` and `ab:c` come out different.
+fn hash_nsid(nsid: NamespacedID, hasher: &mut StableHasher) {
+  let (namespace, path) = nsid.resolve();
+  for part in [namespace, path] {
+    hasher.write_usize(part.len());
+    hasher.write(part.as_bytes());
+  }
+}
+
+/// 64-bit FNV-1a, with every integer written little-endian so it comes out the same everywhere.
+struct StableHasher(u64);
+
+impl StableHasher {
+  fn new() -> Self {
+    Self(0xcbf29ce484222325)
+  }
+}
+
+impl Hasher for StableHasher {
+  fn finish(&self) -> u64 {
+    self.0
+  }
+
+  fn write(&mut self, bytes: &[u8]) {
+    for byte in bytes {
+      self.0 ^= u64::from(*byte);
+      self.0 = self.0.wrapping_mul(0x100000001b3);
+    }
+  }
+
+  fn write_u16(&mut self, i: u16) {
+    self.write(&i.to_le_bytes());
+  }
+
+  fn write_u32(&mut self, i: u32) {
+    self.write(&i.to_le_bytes());
+  }
+
+  fn write_u64(&mut self, i: u64) {
+    self.write(&i.to_le_bytes());
+  }
+
+  fn write_u128(&mut self, i: u128) {
+    self.write(&i.to_le_bytes());
+  }
+
+  fn write_usize(&mut self, i: usize) {
+    self.write_u64(i as u64);
+  }
+
+  fn write_i16(&mut self, i: i16) {
+    self.write_u16(i as u16);
+  }
+
+  fn write_i32(&mut self, i: i32) {
+    self.write_u32(i as u32);
+  }
+
+  fn write_i64(&mut self, i: i64) {
+    self.write_u64(i as u64);
+  }
+
+  fn write_i128(&mut self, i: i128) {
+    self.write_u128(i as u128);
+  }
+
+  fn write_isize(&mut self, i: isize) {
+    self.write_u64(i as i64 as u64);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn checksum_ignores_registration_order() {
+    let mut a = Registry::<u32>::new();
+    let stone = a.register(1, nsid("game:stone")).unwrap();
+    let dirt = a.register(2, nsid("game:dirt")).unwrap();
+    a.register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    a.register_category(nsid("game:empty"), []).unwrap();
+
+    let mut b = Registry::<u32>::new();
+    b.register_category(nsid("game:empty"), []).unwrap();
+    let dirt = b.register(2, nsid("game:dirt")).unwrap();
+    let stone = b.register(1, nsid("game:stone")).unwrap();
+    b.register_category(nsid("game:blocks"), [dirt, stone])
+      .unwrap();
+
+    assert_eq!(a.checksum(), b.checksum());
+    assert_eq!(a.checksum_with_values(), b.checksum_with_values());
+  }
+
+  #[test]
+  fn checksum_sees_differences() {
+    let mut a = Registry::<u32>::new();
+    a.register(1, nsid("game:stone")).unwrap();
+    let mut b = Registry::<u32>::new();
+    b.register(2, nsid("game:stone")).unwrap();
+    assert_eq!(a.checksum(), b.checksum());
+    assert_ne!(a.checksum_with_values(), b.checksum_with_values());
+
+    let mut c = Registry::<u32>::new();
+    c.register(1, nsid("gam:estone")).unwrap();
+    assert_ne!(a.checksum(), c.checksum());
+
+    let mut d = Registry::<u32>::new();
+    let stone = d.register(1, nsid("game:stone")).unwrap();
+    d.register_category(nsid("game:blocks"), [stone]).unwrap();
+    assert_ne!(a.checksum(), d.checksum());
+  }
+
+  #[test]
+  fn checksum_is_stable() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    assert_eq!(registry.checksum(), 7375268598490451558);
+  }
+}
@@ -0,0 +1,400 @@
+//! Loading categories from Minecraft-style tag files, behind the `datapack` feature.
+
+use std::{
+  fs,
+  hash::BuildHasher,
+  path::{Path, PathBuf},
+};
+
+use ahash::{AHashMap, AHashSet};
+use serde::Deserialize;
+
+use crate::{
+  find_cycle, CategoryHandle, EntryOrTag, ErrCategoryCycle,
+  LoadCategoriesError, NamespacedID, Registry,
+};
+
+#[derive(Deserialize)]
+struct TagFile {
+  #[serde(default)]
+  replace: bool,
+  values: Vec<TagValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagValue {
+  Plain(String),
+  Detailed {
+    id: String,
+    #[serde(default = "required_by_default")]
+    required: bool,
+  },
+}
+
+fn required_by_default() -> bool {
+  true
+}
+
+/// A tag file after it's been read and had all its values parsed.
+struct LoadedTag {
+  nsid: NamespacedID,
+  replace: bool,
+  values: Vec<TagReference>,
+}
+
+struct TagReference {
+  nsid: NamespacedID,
+  /// Whether this was written `#ns:path`
+  is_category: bool,
+  required: bool,
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Load categories out of a directory of Minecraft-style tag files.
+  ///
+  /// Each file at `<namespace>/<path>.json` under `dir` becomes the category `namespace:path`, and looks like
+  /// `{"replace": false, "values": ["ns:entry", "#ns:other_category"]}`.
+  /// Plain values are entries, and values starting with `#` are categories to nest inside this one
+  /// (see `nest_category`), either already registered or from another file.
+  /// A value can also be written `{"id": "ns:entry", "required": false}` to skip it if it's not there.
+  ///
+  /// If the category already exists, the file's values are added to it, unless `replace` is true.
+  ///
+  /// Everything is read and checked before anything is loaded. If any file is bad, or any required value
+  /// doesn't refer to anything, nothing is loaded and every unknown NSID is reported.
+  /// Otherwise, returns the handles of every category that was loaded.
+  pub fn load_categories_from_dir(
+    &mut self,
+    dir: impl AsRef<Path>,
+  ) -> Result<Vec<CategoryHandle<T>>, LoadCategoriesError> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    find_json_files(dir, &mut files)?;
+    files.sort();
+    let tags = files
+      .into_iter()
+      .map(|file| read_tag_file(dir, file))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let defined: AHashSet<_> = tags.iter().map(|tag| tag.nsid).collect();
+    let mut unknown_entries = Vec::new();
+    let mut unknown_categories = Vec::new();
+    for value in tags.iter().flat_map(|tag| &tag.values) {
+      if !value.required {
+        continue;
+      }
+      if value.is_category {
+        if !defined.contains(&value.nsid)
+          && self.validate_category_nsid(value.nsid).is_none()
+        {
+          unknown_categories.push(value.nsid);
+        }
+      } else if self.validate_nsid(value.nsid).is_none() {
+        unknown_entries.push(value.nsid);
+      }
+    }
+    if !unknown_entries.is_empty() || !unknown_categories.is_empty() {
+      for unknown in [&mut unknown_entries, &mut unknown_categories] {
+        unknown.sort();
+        unknown.dedup();
+      }
+      return Err(LoadCategoriesError::Unknown {
+        entries: unknown_entries,
+        categories: unknown_categories,
+      });
+    }
+
+    if let Some(cycle) = self.find_nesting_cycle(&tags, &defined) {
+      return Err(LoadCategoriesError::Cycle(ErrCategoryCycle { cycle }));
+    }
+
+    // Make all the categories first, so they can be nested in each other no matter the order
+    let mut handles = Vec::with_capacity(tags.len());
+    for tag in &tags {
+      let handle = match self.validate_category_nsid(tag.nsid) {
+        Some(handle) => {
+          if tag.replace {
+            self.clear_members(handle.id);
+            self.category_children.remove(&handle.id);
+          }
+          handle
+        }
+        None => self.register_empty_category(tag.nsid).unwrap(),
+      };
+      handles.push(handle);
+    }
+    for (tag, handle) in tags.iter().zip(&handles) {
+      for value in &tag.values {
+        if value.is_category {
+          if let Some(child) = self.validate_category_nsid(value.nsid) {
+            // Already checked for loops above
+            self.nest_category(*handle, child).unwrap();
+          }
+        } else if let Some(entry) = self.validate_nsid(value.nsid) {
+          self.insert_into_category(*handle, entry);
+        }
+      }
+    }
+    Ok(handles)
+  }
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Work out how the categories will be nested once the tags are loaded, and find a loop in that if there is one.
+  fn find_nesting_cycle(
+    &self,
+    tags: &[LoadedTag],
+    defined: &AHashSet<NamespacedID>,
+  ) -> Option<Vec<NamespacedID>> {
+    let nsid_of = |id| self.category_arena.get(id).unwrap().1;
+    let mut graph: AHashMap<NamespacedID, Vec<NamespacedID>> = self
+      .category_children
+      .iter()
+      .map(|(parent, children)| {
+        (
+          nsid_of(*parent),
+          children.iter().map(|it| nsid_of(*it)).collect(),
+        )
+      })
+      .collect();
+    for tag in tags.iter().filter(|tag| tag.replace) {
+      graph.remove(&tag.nsid);
+    }
+    for tag in tags {
+      for value in tag.values.iter().filter(|value| value.is_category) {
+        if defined.contains(&value.nsid)
+          || self.validate_category_nsid(value.nsid).is_some()
+        {
+          graph.entry(tag.nsid).or_default().push(value.nsid);
+        }
+      }
+    }
+
+    let mut parents: Vec<_> = graph.keys().copied().collect();
+    parents.sort();
+    let mut done = AHashSet::new();
+    let mut path = Vec::new();
+    parents
+      .into_iter()
+      .find_map(|nsid| find_cycle(&graph, nsid, &mut done, &mut path))
+  }
+}
+
+fn find_json_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      find_json_files(&path, out)?;
+    } else if path.extension().is_some_and(|ext| ext == "json") {
+      out.push(path);
+    }
+  }
+  Ok(())
+}
+
+fn read_tag_file(
+  dir: &Path,
+  file: PathBuf,
+) -> Result<LoadedTag, LoadCategoriesError> {
+  let nsid = match category_nsid(dir, &file) {
+    Some(it) => it,
+    None => return Err(LoadCategoriesError::BadLocation(file)),
+  };
+  let text = fs::read_to_string(&file)?;
+  let parsed: TagFile = match serde_json::from_str(&text) {
+    Ok(it) => it,
+    Err(err) => return Err(LoadCategoriesError::Json(file, err)),
+  };
+
+  let mut values = Vec::with_capacity(parsed.values.len());
+  for value in parsed.values {
+    let (id, required) = match value {
+      TagValue::Plain(id) => (id, true),
+      TagValue::Detailed { id, required } => (id, required),
+    };
+    match EntryOrTag::parse(&id) {
+      Ok(value) => values.push(TagReference {
+        nsid: value.nsid(),
+        is_category: value.is_tag(),
+        required,
+      }),
+      Err(err) => return Err(LoadCategoriesError::InvalidValue(file, id, err)),
+    }
+  }
+
+  Ok(LoadedTag {
+    nsid,
+    replace: parsed.replace,
+    values,
+  })
+}
+
+/// Turn `dir/namespace/some/path.json` into `namespace:some/path`.
+fn category_nsid(dir: &Path, file: &Path) -> Option<NamespacedID> {
+  let relative = file.strip_prefix(dir).ok()?.with_extension("");
+  let mut components = relative.components().map(|c| c.as_os_str().to_str());
+  let namespace = components.next()??;
+  let path = components.collect::<Option<Vec<_>>>()?.join("/");
+  NamespacedID::new_from_parts(namespace, path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  /// Write out some tag files into a fresh directory, as `(namespace/path, json)`.
+  fn tag_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "regigigas-{}-{}",
+      name,
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, json) in files {
+      let file = dir.join(format!("{}.json", path));
+      fs::create_dir_all(file.parent().unwrap()).unwrap();
+      fs::write(file, json).unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn loads_nested_tags() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let dir = tag_dir(
+      "nested",
+      &[
+        (
+          "game/blocks",
+          r##"{"values": ["game:dirt", "#game:stones"]}"##,
+        ),
+        (
+          "game/stones",
+          r#"{"values": ["game:stone", {"id": "game:nope", "required": false}]}"#,
+        ),
+      ],
+    );
+    let handles = registry.load_categories_from_dir(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(handles.len(), 2);
+    let blocks = registry
+      .validate_category_nsid(nsid("game:blocks"))
+      .unwrap();
+    let mut found: Vec<_> = registry
+      .lookup_category_recursive(blocks)
+      .unwrap()
+      .into_iter()
+      .map(|(_, handle)| handle)
+      .collect();
+    found.sort_by_key(|handle| handle.get_nsid());
+    assert_eq!(found, [dirt, stone]);
+  }
+
+  #[test]
+  fn reports_unknown_values() {
+    let mut registry = Registry::<u32>::new();
+    let dir = tag_dir(
+      "unknown",
+      &[(
+        "game/blocks",
+        r##"{"values": ["game:dirt", "#game:nope"]}"##,
+      )],
+    );
+    let err = registry.load_categories_from_dir(&dir).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+
+    match err {
+      LoadCategoriesError::Unknown {
+        entries,
+        categories,
+      } => {
+        assert_eq!(entries, [nsid("game:dirt")]);
+        assert_eq!(categories, [nsid("game:nope")]);
+      }
+      other => panic!("wrong error: {}", other),
+    }
+    assert_eq!(registry.category_count(), 0);
+  }
+
+  #[test]
+  fn reports_loops_before_loading_anything() {
+    let mut registry = Registry::<u32>::new();
+    let outer = registry
+      .register_empty_category(nsid("game:outer"))
+      .unwrap();
+    let inner = registry
+      .register_empty_category(nsid("game:inner"))
+      .unwrap();
+    registry.nest_category(outer, inner).unwrap();
+    let dir = tag_dir(
+      "loop",
+      &[
+        ("game/inner", r##"{"values": ["#game:extra"]}"##),
+        ("game/extra", r##"{"values": ["#game:outer"]}"##),
+      ],
+    );
+    let err = registry.load_categories_from_dir(&dir).unwrap_err();
+
+    match err {
+      LoadCategoriesError::Cycle(err) => assert_eq!(
+        err.cycle,
+        [nsid("game:extra"), nsid("game:outer"), nsid("game:inner")]
+      ),
+      other => panic!("wrong error: {}", other),
+    }
+    assert_eq!(registry.category_count(), 2);
+    assert_eq!(registry.subcategories(inner).count(), 0);
+
+    // Replacing the outer category's children breaks the loop
+    fs::write(
+      dir.join("game/outer.json"),
+      r#"{"replace": true, "values": []}"#,
+    )
+    .unwrap();
+    registry.load_categories_from_dir(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(registry.subcategories(outer).count(), 0);
+    assert_eq!(registry.subcategories(inner).count(), 1);
+  }
+
+  #[test]
+  fn replace_decides_whether_to_keep_members() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let added = registry
+      .register_category(nsid("game:added"), [stone])
+      .unwrap();
+    let replaced = registry
+      .register_category(nsid("game:replaced"), [stone])
+      .unwrap();
+    let dir = tag_dir(
+      "replace",
+      &[
+        (
+          "game/added",
+          r#"{"replace": false, "values": ["game:dirt"]}"#,
+        ),
+        (
+          "game/replaced",
+          r#"{"replace": true, "values": ["game:dirt"]}"#,
+        ),
+      ],
+    );
+    registry.load_categories_from_dir(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(registry.category_len(added), 2);
+    assert!(registry.is_in_category(dirt, added));
+    assert_eq!(registry.category_len(replaced), 1);
+    assert!(!registry.is_in_category(stone, replaced));
+  }
+}
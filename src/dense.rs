@@ -0,0 +1,251 @@
+use std::{
+  collections::HashMap,
+  fmt::Debug,
+  hash::{BuildHasher, Hash},
+  marker::PhantomData,
+  ops::Index,
+};
+
+use ahash::RandomState;
+
+use crate::{ErrAlreadyRegistered, NamespacedID, Registry, RegistryKey};
+
+/// A registry that keeps its values in one plain `Vec<T>`, for when you mostly iterate over everything.
+///
+/// A [`Registry`] keeps each value next to its NSID, so going through every value drags all the NSIDs
+/// through the cache too. Here the values and NSIDs are in separate lists, and a handle is just an index.
+///
+/// The catch is that nothing can ever be unregistered, and there are no categories.
+/// Build a normal `Registry` and turn it into one of these with `From` if you need those while loading.
+pub struct DenseRegistry<T, K = NamespacedID, S = RandomState> {
+  values: Vec<T>,
+  nsids: Vec<K>,
+  nsid_map: HashMap<K, u32, S>,
+}
+
+impl<T, K: RegistryKey> DenseRegistry<T, K> {
+  pub fn new() -> Self {
+    Self::with_capacity(0)
+  }
+
+  /// Make an empty registry with room for this many entries.
+  pub fn with_capacity(entries: usize) -> Self {
+    Self::with_capacity_and_hasher(entries, RandomState::default())
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher> DenseRegistry<T, K, S> {
+  /// Make an empty registry with room for this many entries, looking up NSIDs with the given hasher.
+  pub fn with_capacity_and_hasher(entries: usize, hasher: S) -> Self {
+    Self {
+      values: Vec::with_capacity(entries),
+      nsids: Vec::with_capacity(entries),
+      nsid_map: HashMap::with_capacity_and_hasher(entries, hasher),
+    }
+  }
+
+  /// Register something new. It goes on the end, so its handle is the number of things registered before it.
+  pub fn register(
+    &mut self,
+    x: T,
+    nsid: K,
+  ) -> Result<DenseHandle<T>, ErrAlreadyRegistered> {
+    if self.nsid_map.contains_key(&nsid) {
+      return Err(ErrAlreadyRegistered);
+    }
+    let handle = DenseHandle::new(self.values.len() as u32);
+    self.values.push(x);
+    self.nsids.push(nsid);
+    self.nsid_map.insert(nsid, handle.idx);
+    Ok(handle)
+  }
+
+  /// Look up something by its handle.
+  pub fn lookup(&self, handle: DenseHandle<T>) -> &T {
+    &self.values[handle.index()]
+  }
+
+  /// Look up something by its handle, mutably.
+  pub fn lookup_mut(&mut self, handle: DenseHandle<T>) -> &mut T {
+    &mut self.values[handle.index()]
+  }
+
+  /// Look up something by a NSID, which may or may not actually be in here.
+  pub fn lookup_by_nsid(&self, nsid: K) -> Option<&T> {
+    self.validate_nsid(nsid).map(|handle| self.lookup(handle))
+  }
+
+  /// Get the handle of whatever is registered under this NSID.
+  pub fn validate_nsid(&self, nsid: K) -> Option<DenseHandle<T>> {
+    self.nsid_map.get(&nsid).map(|idx| DenseHandle::new(*idx))
+  }
+
+  /// Get the NSID something was registered under.
+  pub fn nsid_of(&self, handle: DenseHandle<T>) -> K {
+    self.nsids[handle.index()]
+  }
+
+  /// Turn an index back into a handle, if there's something there.
+  pub fn handle_from_index(&self, idx: usize) -> Option<DenseHandle<T>> {
+    (idx < self.values.len()).then(|| DenseHandle::new(idx as u32))
+  }
+
+  /// Get every value, in the order they were registered. Index into it with `DenseHandle::index`.
+  pub fn values(&self) -> &[T] {
+    &self.values
+  }
+
+  /// Get every value mutably, in the order they were registered.
+  pub fn values_mut(&mut self) -> &mut [T] {
+    &mut self.values
+  }
+
+  /// Get every NSID, in the same order as `values`.
+  pub fn nsids(&self) -> &[K] {
+    &self.nsids
+  }
+
+  /// Iterate over everything in the order it was registered, along with its handle.
+  pub fn iter(&self) -> impl Iterator<Item = (&T, DenseHandle<T>)> {
+    self
+      .values
+      .iter()
+      .enumerate()
+      .map(|(idx, x)| (x, DenseHandle::new(idx as u32)))
+  }
+
+  /// How many things are registered.
+  pub fn len(&self) -> usize {
+    self.values.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.values.is_empty()
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Default
+  for DenseRegistry<T, K, S>
+{
+  fn default() -> Self {
+    Self::with_capacity_and_hasher(0, S::default())
+  }
+}
+
+/// Move everything out of a registry, keeping the order it was registered in.
+///
+/// Categories and aliases don't come along.
+impl<T, K: RegistryKey, S: BuildHasher + Default> From<Registry<T, K, S>>
+  for DenseRegistry<T, K, S>
+{
+  fn from(registry: Registry<T, K, S>) -> Self {
+    let iter = registry.into_iter();
+    let mut dense = Self::with_capacity_and_hasher(iter.len(), S::default());
+    for (x, nsid) in iter {
+      // NSIDs in a registry are already unique
+      dense.register(x, nsid).unwrap();
+    }
+    dense
+  }
+}
+
+/// Convenience for `lookup`
+impl<T, K: RegistryKey, S: BuildHasher> Index<DenseHandle<T>>
+  for DenseRegistry<T, K, S>
+{
+  type Output = T;
+
+  fn index(&self, index: DenseHandle<T>) -> &Self::Output {
+    self.lookup(index)
+  }
+}
+
+/// Handle to an entry in a [`DenseRegistry`]. It's only an index, so it's just 4 bytes.
+pub struct DenseHandle<T> {
+  idx: u32,
+  phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> DenseHandle<T> {
+  fn new(idx: u32) -> Self {
+    Self {
+      idx,
+      phantom: PhantomData,
+    }
+  }
+
+  /// Get where this entry is in `DenseRegistry::values`.
+  pub fn index(&self) -> usize {
+    self.idx as usize
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T> Clone for DenseHandle<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for DenseHandle<T> {}
+
+impl<T> Hash for DenseHandle<T> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.idx.hash(state);
+  }
+}
+
+impl<T> PartialEq for DenseHandle<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.idx == other.idx
+  }
+}
+
+impl<T> Eq for DenseHandle<T> {}
+
+impl<T> Debug for DenseHandle<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("DenseHandle").field(&self.idx).finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn handles_are_indices() {
+    let mut registry = DenseRegistry::<&str>::new();
+    let stone = registry.register("stone", nsid("game:stone")).unwrap();
+    let dirt = registry.register("dirt", nsid("game:dirt")).unwrap();
+    assert!(registry.register("again", nsid("game:stone")).is_err());
+
+    assert_eq!((stone.index(), dirt.index()), (0, 1));
+    assert_eq!(registry[dirt], "dirt");
+    assert_eq!(registry.values(), ["stone", "dirt"]);
+    assert_eq!(registry.nsids(), [nsid("game:stone"), nsid("game:dirt")]);
+    assert_eq!(registry.validate_nsid(nsid("game:dirt")), Some(dirt));
+    assert_eq!(registry.handle_from_index(2), None);
+  }
+
+  #[test]
+  fn from_registry_with_any_hasher() {
+    type Hasher = BuildHasherDefault<DefaultHasher>;
+    let mut registry = Registry::<u32, NamespacedID, Hasher>::default();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:dirt")).unwrap();
+    registry.unregister(stone);
+    registry.register(3, nsid("game:sand")).unwrap();
+
+    let dense: DenseRegistry<u32, NamespacedID, Hasher> = registry.into();
+    assert_eq!(dense.values(), [2, 3]);
+    assert_eq!(dense.lookup_by_nsid(nsid("game:sand")), Some(&3));
+    assert_eq!(dense.lookup_by_nsid(nsid("game:stone")), None);
+  }
+}
@@ -0,0 +1,177 @@
+use std::hash::BuildHasher;
+
+use ahash::AHashSet;
+
+use crate::{NamespacedID, Registry, RegistryKey};
+
+/// Everything that's different between two registries, from [`Registry::diff`].
+///
+/// Handles don't mean anything across registries, so it's all in terms of NSIDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryDiff<K = NamespacedID> {
+  /// Entries only in the other registry
+  pub added: Vec<K>,
+  /// Entries only in this registry
+  pub removed: Vec<K>,
+  /// Entries in both, but with different values. Always empty from `diff_nsids`.
+  pub changed: Vec<K>,
+  /// Categories only in the other registry
+  pub added_categories: Vec<K>,
+  /// Categories only in this registry
+  pub removed_categories: Vec<K>,
+  /// Categories in both, but with different members or subcategories
+  pub changed_categories: Vec<CategoryDiff<K>>,
+}
+
+/// How the members and subcategories of a category differ between two registries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryDiff<K = NamespacedID> {
+  pub category: K,
+  /// Members only in the other registry's category
+  pub added: Vec<K>,
+  /// Members only in this registry's category
+  pub removed: Vec<K>,
+  /// Subcategories only nested in the other registry's category
+  pub added_subcategories: Vec<K>,
+  /// Subcategories only nested in this registry's category
+  pub removed_subcategories: Vec<K>,
+}
+
+impl<K> RegistryDiff<K> {
+  /// Return if the registries have all the same entries and categories.
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty()
+      && self.removed.is_empty()
+      && self.changed.is_empty()
+      && self.added_categories.is_empty()
+      && self.removed_categories.is_empty()
+      && self.changed_categories.is_empty()
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Find what's different between this registry and `other`, going by NSID.
+  ///
+  /// Anything "added" is in `other` but not here, as if `other` were a newer version of this one.
+  pub fn diff(&self, other: &Registry<T, K, S>) -> RegistryDiff<K>
+  where
+    T: PartialEq,
+  {
+    let mut diff = self.diff_nsids(other);
+    for (_, (x, nsid)) in self.entries() {
+      if let Some(y) = other.nsid_map.get(nsid).map(|id| &other.slot(*id).0) {
+        if x != y {
+          diff.changed.push(*nsid);
+        }
+      }
+    }
+    diff
+  }
+
+  /// Like `diff`, but without comparing values, so `T` doesn't need to be `PartialEq`.
+  pub fn diff_nsids(&self, other: &Registry<T, K, S>) -> RegistryDiff<K> {
+    let mut diff = RegistryDiff {
+      added: Vec::new(),
+      removed: Vec::new(),
+      changed: Vec::new(),
+      added_categories: Vec::new(),
+      removed_categories: Vec::new(),
+      changed_categories: Vec::new(),
+    };
+    for (_, (_, nsid)) in self.entries() {
+      if !other.nsid_map.contains_key(nsid) {
+        diff.removed.push(*nsid);
+      }
+    }
+    for (_, (_, nsid)) in other.entries() {
+      if !self.nsid_map.contains_key(nsid) {
+        diff.added.push(*nsid);
+      }
+    }
+
+    for (id, (set, nsid)) in self.category_arena.iter() {
+      let (other_id, other_set) = match other.category_nsid_map.get(nsid) {
+        Some(other_id) => {
+          (*other_id, &other.category_arena.get(*other_id).unwrap().0)
+        }
+        None => {
+          diff.removed_categories.push(*nsid);
+          continue;
+        }
+      };
+      let mine: AHashSet<_> = set.iter().map(|id| self.slot(*id).1).collect();
+      let theirs: AHashSet<_> =
+        other_set.iter().map(|id| other.slot(*id).1).collect();
+      let my_children = self.subcategory_nsids(id);
+      let their_children = other.subcategory_nsids(other_id);
+      if mine != theirs || my_children != their_children {
+        diff.changed_categories.push(CategoryDiff {
+          category: *nsid,
+          added: theirs.difference(&mine).copied().collect(),
+          removed: mine.difference(&theirs).copied().collect(),
+          added_subcategories: their_children
+            .difference(&my_children)
+            .copied()
+            .collect(),
+          removed_subcategories: my_children
+            .difference(&their_children)
+            .copied()
+            .collect(),
+        });
+      }
+    }
+    for (_, (_, nsid)) in other.category_arena.iter() {
+      if !self.category_nsid_map.contains_key(nsid) {
+        diff.added_categories.push(*nsid);
+      }
+    }
+    diff
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn diff_finds_entry_and_category_changes() {
+    let mut old = Registry::<u32>::new();
+    let stone = old.register(1, nsid("game:stone")).unwrap();
+    old.register(2, nsid("game:dirt")).unwrap();
+    old.register(3, nsid("game:air")).unwrap();
+    old.register_category(nsid("game:blocks"), [stone]).unwrap();
+    old.register_empty_category(nsid("game:gone")).unwrap();
+
+    let mut new = Registry::<u32>::new();
+    new.register(1, nsid("game:stone")).unwrap();
+    let dirt = new.register(20, nsid("game:dirt")).unwrap();
+    new.register(4, nsid("game:sand")).unwrap();
+    let blocks = new.register_category(nsid("game:blocks"), [dirt]).unwrap();
+    let fresh = new.register_empty_category(nsid("game:fresh")).unwrap();
+    new.nest_category(blocks, fresh).unwrap();
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, [nsid("game:sand")]);
+    assert_eq!(diff.removed, [nsid("game:air")]);
+    assert_eq!(diff.changed, [nsid("game:dirt")]);
+    assert_eq!(diff.added_categories, [nsid("game:fresh")]);
+    assert_eq!(diff.removed_categories, [nsid("game:gone")]);
+    assert_eq!(
+      diff.changed_categories,
+      [CategoryDiff {
+        category: nsid("game:blocks"),
+        added: vec![nsid("game:dirt")],
+        removed: vec![nsid("game:stone")],
+        added_subcategories: vec![nsid("game:fresh")],
+        removed_subcategories: vec![],
+      }]
+    );
+
+    assert!(old.diff_nsids(&new).changed.is_empty());
+    assert!(new.diff(&new.clone()).is_empty());
+  }
+}
@@ -0,0 +1,182 @@
+use std::hash::BuildHasher;
+
+use ahash::RandomState;
+
+use crate::{ArenaID, NamespacedID, Registry, RegistryHandle, RegistryKey};
+
+/// Whatever is or isn't registered under an NSID, from [`Registry::entry`].
+pub enum Entry<'a, T, K = NamespacedID, S = RandomState> {
+  Occupied(OccupiedEntry<'a, T, K, S>),
+  Vacant(VacantEntry<'a, T, K, S>),
+}
+
+/// Something that's already registered.
+pub struct OccupiedEntry<'a, T, K = NamespacedID, S = RandomState> {
+  registry: &'a mut Registry<T, K, S>,
+  id: ArenaID<T>,
+}
+
+/// An NSID that nothing is registered under yet.
+pub struct VacantEntry<'a, T, K = NamespacedID, S = RandomState> {
+  registry: &'a mut Registry<T, K, S>,
+  nsid: K,
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> Entry<'a, T, K, S> {
+  /// Register `entry` if nothing's registered here yet, and get the handle either way.
+  pub fn or_register(self, entry: T) -> RegistryHandle<T, K> {
+    self.or_register_with(|| entry)
+  }
+
+  /// Register whatever `f` makes if nothing's registered here yet, and get the handle either way.
+  ///
+  /// `f` is only called if it's needed.
+  pub fn or_register_with(self, f: impl FnOnce() -> T) -> RegistryHandle<T, K> {
+    match self {
+      Entry::Occupied(occupied) => occupied.handle(),
+      Entry::Vacant(vacant) => vacant.register(f()),
+    }
+  }
+
+  /// Register `T::default()` if nothing's registered here yet, and get the handle either way.
+  pub fn or_default(self) -> RegistryHandle<T, K>
+  where
+    T: Default,
+  {
+    self.or_register_with(T::default)
+  }
+
+  /// Change what's registered here, if anything is.
+  pub fn and_modify(mut self, f: impl FnOnce(&mut T)) -> Self {
+    if let Entry::Occupied(occupied) = &mut self {
+      f(occupied.get_mut());
+    }
+    self
+  }
+
+  /// Get the NSID this is for.
+  ///
+  /// If this was looked up by an alias, an occupied entry has the real NSID.
+  pub fn nsid(&self) -> K {
+    match self {
+      Entry::Occupied(occupied) => occupied.handle().get_nsid(),
+      Entry::Vacant(vacant) => vacant.nsid,
+    }
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default>
+  OccupiedEntry<'a, T, K, S>
+{
+  pub(crate) fn new(
+    registry: &'a mut Registry<T, K, S>,
+    id: ArenaID<T>,
+  ) -> Self {
+    Self { registry, id }
+  }
+
+  /// Get a handle to what's registered here.
+  pub fn handle(&self) -> RegistryHandle<T, K> {
+    RegistryHandle::new(self.id, self.registry.slot(self.id).1)
+  }
+
+  pub fn get(&self) -> &T {
+    &self.registry.slot(self.id).0
+  }
+
+  pub fn get_mut(&mut self) -> &mut T {
+    &mut self.registry.slot_mut(self.id).0
+  }
+
+  /// Get a mutable reference that lives as long as the borrow of the registry.
+  pub fn into_mut(self) -> &'a mut T {
+    &mut self.registry.slot_mut(self.id).0
+  }
+
+  /// Swap out what's registered here, handing back the old value.
+  ///
+  /// The handle stays the same, and it keeps its category memberships.
+  pub fn insert(&mut self, entry: T) -> T {
+    std::mem::replace(self.get_mut(), entry)
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> VacantEntry<'a, T, K, S> {
+  pub(crate) fn new(registry: &'a mut Registry<T, K, S>, nsid: K) -> Self {
+    Self { registry, nsid }
+  }
+
+  /// Get the NSID that would be registered.
+  pub fn nsid(&self) -> K {
+    self.nsid
+  }
+
+  /// Register something under this NSID.
+  pub fn register(self, entry: T) -> RegistryHandle<T, K> {
+    self.registry.register_unchecked(entry, self.nsid)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn entries_register_once_and_modify_after() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.entry(nsid("game:stone")).or_register(1);
+    let again = registry
+      .entry(nsid("game:stone"))
+      .and_modify(|x| *x += 10)
+      .or_register_with(|| panic!("already registered"));
+    assert_eq!(again, stone);
+    assert_eq!(registry[stone], 11);
+
+    let dirt = registry
+      .entry(nsid("game:dirt"))
+      .and_modify(|_| panic!("nothing to modify"))
+      .or_default();
+    assert_eq!(registry[dirt], 0);
+
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    match registry.entry(nsid("game:rock")) {
+      Entry::Occupied(mut entry) => {
+        assert_eq!(entry.handle(), stone);
+        assert_eq!(entry.insert(5), 11);
+      }
+      Entry::Vacant(_) => panic!("aliases should find the real entry"),
+    }
+    match registry.entry(nsid("game:sand")) {
+      Entry::Vacant(entry) => {
+        assert_eq!(entry.nsid(), nsid("game:sand"));
+        entry.register(3);
+      }
+      Entry::Occupied(_) => panic!("nothing is registered there"),
+    }
+    assert_eq!(registry[stone], 5);
+    assert_eq!(registry[nsid("game:sand")], 3);
+
+    let mut migrations = crate::MigrationTable::new();
+    migrations
+      .add(nsid("game:pebble"), nsid("game:stone"))
+      .unwrap();
+    registry.set_migrations(migrations);
+    match registry.entry(nsid("game:pebble")) {
+      Entry::Occupied(entry) => {
+        assert_eq!(entry.handle(), stone);
+        assert_eq!(entry.handle().get_nsid(), nsid("game:stone"));
+      }
+      Entry::Vacant(_) => panic!("migrations should find the new name"),
+    }
+    let len = registry.len();
+    let pebble = registry.get_or_register(nsid("game:pebble"), || 7);
+    assert_eq!(pebble, stone);
+    assert_eq!(registry.len(), len);
+  }
+}
@@ -1,5 +1,6 @@
-use std::fmt::Display;
-use std::sync::TryLockError;
+use std::fmt::{Debug, Display};
+
+use crate::NamespacedID;
 
 #[derive(Debug)]
 pub struct ErrAlreadyRegistered;
@@ -27,6 +28,7 @@ pub enum NSIDParseError {
     InvalidNamespace(InvalidNamespace),
     InvalidPath(InvalidPath),
     NoSeparator,
+    /// The interner ran out of room
     InternerError(String),
 }
 
@@ -50,15 +52,19 @@ impl std::error::Error for NSIDParseError {}
 #[derive(Debug)]
 pub enum InvalidNamespace {
     Empty,
-    /// Char index of the invalid character
+    /// Byte index of the invalid character
     BadChar(usize, char),
+    /// Longer than the limit set in [`NsidRules`](crate::NsidRules), which is this many chars
+    TooLong(usize),
 }
 
 #[derive(Debug)]
 pub enum InvalidPath {
     Empty,
-    /// Char index of the invalid character
+    /// Byte index of the invalid character
     BadChar(usize, char),
+    /// Longer than the limit set in [`NsidRules`](crate::NsidRules), which is this many chars
+    TooLong(usize),
 }
 
 impl Display for InvalidNamespace {
@@ -66,6 +72,7 @@ impl Display for InvalidNamespace {
         match self {
             InvalidNamespace::Empty => writeln!(f, "cannot have an empty namespace"),
             InvalidNamespace::BadChar(idx, c) => writeln!(f, "invalid namespace char {:?} at idx {} (valid chars are a-z, 0-9, underscore, dash)", c, idx),
+            InvalidNamespace::TooLong(max) => writeln!(f, "namespace is longer than the limit of {} chars", max),
         }
     }
 }
@@ -74,6 +81,7 @@ impl Display for InvalidPath {
         match self {
             InvalidPath::Empty => writeln!(f, "cannot have an empty path"),
             InvalidPath::BadChar(idx, c) => writeln!(f, "invalid path char {:?} at idx {} (valid chars are a-z, 0-9, underscore, dash, period, slash)", c, idx),
+            InvalidPath::TooLong(max) => writeln!(f, "path is longer than the limit of {} chars", max),
         }
     }
 }
@@ -93,9 +101,463 @@ impl From<InvalidNamespace> for NSIDParseError {
     }
 }
 
-impl<T> From<TryLockError<T>> for NSIDParseError {
-    fn from(err: TryLockError<T>) -> Self {
+impl From<lasso::LassoError> for NSIDParseError {
+    fn from(err: lasso::LassoError) -> Self {
         let msg = err.to_string();
         Self::InternerError(msg)
     }
 }
+
+#[derive(Debug)]
+pub enum PreinternError {
+    /// Something went wrong reading the NSIDs in the first place
+    Io(std::io::Error),
+    InvalidNsid(NSIDParseError),
+}
+
+impl Display for PreinternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreinternError::Io(err) => {
+                write!(f, "an error happened while reading: {}", err)
+            }
+            PreinternError::InvalidNsid(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for PreinternError {}
+
+impl From<std::io::Error> for PreinternError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<NSIDParseError> for PreinternError {
+    fn from(err: NSIDParseError) -> Self {
+        Self::InvalidNsid(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportCategoryError {
+    AlreadyRegistered(ErrCategoryAlreadyRegistered),
+    /// The member NSIDs that weren't registered
+    UnknownMembers(Vec<NamespacedID>),
+}
+
+impl Display for ImportCategoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportCategoryError::AlreadyRegistered(err) => Display::fmt(err, f),
+            ImportCategoryError::UnknownMembers(nsids) => {
+                write!(f, "the category referenced unregistered elements: ")?;
+                for (idx, nsid) in nsids.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", nsid)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportCategoryError {}
+
+impl From<ErrCategoryAlreadyRegistered> for ImportCategoryError {
+    fn from(v: ErrCategoryAlreadyRegistered) -> Self {
+        Self::AlreadyRegistered(v)
+    }
+}
+
+#[derive(Debug)]
+pub enum RenameNamespaceError {
+    /// The namespace to move to isn't valid
+    InvalidNamespace(NSIDParseError),
+    AlreadyRegistered(ErrCategoryAlreadyRegistered),
+}
+
+impl Display for RenameNamespaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameNamespaceError::InvalidNamespace(err) => Display::fmt(err, f),
+            RenameNamespaceError::AlreadyRegistered(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for RenameNamespaceError {}
+
+impl From<NSIDParseError> for RenameNamespaceError {
+    fn from(err: NSIDParseError) -> Self {
+        Self::InvalidNamespace(err)
+    }
+}
+
+impl From<ErrCategoryAlreadyRegistered> for RenameNamespaceError {
+    fn from(err: ErrCategoryAlreadyRegistered) -> Self {
+        Self::AlreadyRegistered(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum InsertByNsidError {
+    UnknownCategory(NamespacedID),
+    UnknownEntry(NamespacedID),
+}
+
+impl Display for InsertByNsidError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertByNsidError::UnknownCategory(nsid) => {
+                write!(f, "there is no category registered as {}", nsid)
+            }
+            InsertByNsidError::UnknownEntry(nsid) => {
+                write!(f, "there is no element registered as {}", nsid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InsertByNsidError {}
+
+/// A category ended up containing itself, through some chain of nested categories.
+#[derive(Debug)]
+pub struct ErrCategoryCycle<K = NamespacedID> {
+    /// The categories that make up the loop, in order; the last one contains the first.
+    pub cycle: Vec<K>,
+}
+
+impl<K: Debug> Display for ErrCategoryCycle<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "categories contain each other in a loop: ")?;
+        for nsid in &self.cycle {
+            write!(f, "{:?} -> ", nsid)?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{:?}", first),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<K: Debug> std::error::Error for ErrCategoryCycle<K> {}
+
+#[derive(Debug)]
+pub enum MergeError<K = NamespacedID> {
+    /// These NSIDs are registered or aliased in both registries
+    Conflicts(Vec<K>),
+    /// The categories from both would end up nested in a loop
+    Cycle(ErrCategoryCycle<K>),
+    /// The two registries' migrations don't fit together
+    Migration(MigrationError<K>),
+}
+
+impl<K: Debug> Display for MergeError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Conflicts(conflicts) => {
+                write!(
+                    f,
+                    "nothing was merged because these NSIDs clashed: {:?}",
+                    conflicts
+                )
+            }
+            MergeError::Cycle(err) => {
+                write!(f, "nothing was merged because {}", err)
+            }
+            MergeError::Migration(err) => {
+                write!(f, "nothing was merged because {}", err)
+            }
+        }
+    }
+}
+
+impl<K: Debug> std::error::Error for MergeError<K> {}
+
+#[derive(Debug)]
+pub struct BatchError<K = NamespacedID> {
+    /// Every NSID that was already registered or showed up more than once, in the order they came up
+    pub conflicts: Vec<K>,
+}
+
+impl<K: Debug> Display for BatchError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "nothing was registered because these NSIDs clashed: {:?}",
+            self.conflicts
+        )
+    }
+}
+
+impl<K: Debug> std::error::Error for BatchError<K> {}
+
+#[derive(Debug)]
+pub struct ApplySyncMapError {
+    /// Every NSID in the map that isn't registered here
+    pub missing: Vec<NamespacedID>,
+}
+
+impl Display for ApplySyncMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the ID map has NSIDs that aren't registered here: {:?}",
+            self.missing
+        )
+    }
+}
+
+impl std::error::Error for ApplySyncMapError {}
+
+#[derive(Debug)]
+pub enum LookupError {
+    /// The string wasn't a valid NSID in the first place
+    InvalidNsid(NSIDParseError),
+    /// The NSID was fine, but nothing's registered under it
+    NotRegistered(NamespacedID),
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LookupError::InvalidNsid(err) => Display::fmt(err, f),
+            LookupError::NotRegistered(nsid) => {
+                write!(f, "nothing is registered as {}", nsid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+impl From<NSIDParseError> for LookupError {
+    fn from(err: NSIDParseError) -> Self {
+        Self::InvalidNsid(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum AliasError<K = NamespacedID> {
+    /// There's already an entry or an alias with this NSID
+    AlreadyRegistered(K),
+    /// There's nothing registered with the NSID the alias was supposed to point at
+    UnknownTarget(K),
+}
+
+impl<K: Debug> Display for AliasError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AliasError::AlreadyRegistered(alias) => {
+                write!(f, "{:?} is already registered or an alias", alias)
+            }
+            AliasError::UnknownTarget(target) => {
+                write!(
+                    f,
+                    "can't alias {:?} because nothing is registered there",
+                    target
+                )
+            }
+        }
+    }
+}
+
+impl<K: Debug> std::error::Error for AliasError<K> {}
+
+#[derive(Debug)]
+pub enum MigrationError<K = NamespacedID> {
+    /// This NSID was already renamed to something
+    AlreadyMigrated(K),
+    /// The renames would go around in a loop; these are the NSIDs in it, in order
+    Cycle(Vec<K>),
+}
+
+impl<K: Debug> Display for MigrationError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::AlreadyMigrated(old) => {
+                write!(f, "{:?} was already renamed to something else", old)
+            }
+            MigrationError::Cycle(cycle) => {
+                write!(f, "renames go around in a loop: ")?;
+                for nsid in cycle {
+                    write!(f, "{:?} -> ", nsid)?;
+                }
+                match cycle.first() {
+                    Some(first) => write!(f, "{:?}", first),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl<K: Debug> std::error::Error for MigrationError<K> {}
+
+#[derive(Debug)]
+pub enum LoadMigrationsError {
+    /// Something went wrong reading the renames
+    Io(std::io::Error),
+    /// This line (counting from 1) isn't written `old -> new`
+    BadLine(usize, String),
+    /// This line has something that isn't a valid NSID
+    InvalidNsid(usize, NSIDParseError),
+    /// This line's rename doesn't fit with the ones before it
+    Migration(usize, MigrationError),
+}
+
+impl Display for LoadMigrationsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadMigrationsError::Io(err) => {
+                write!(f, "an error happened while reading: {}", err)
+            }
+            LoadMigrationsError::BadLine(line, text) => {
+                write!(f, "line {} isn't `old -> new`: {:?}", line, text)
+            }
+            LoadMigrationsError::InvalidNsid(line, err) => {
+                write!(f, "line {}: {}", line, err)
+            }
+            LoadMigrationsError::Migration(line, err) => {
+                write!(f, "line {}: {}", line, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadMigrationsError {}
+
+impl From<std::io::Error> for LoadMigrationsError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "datapack")]
+#[derive(Debug)]
+pub enum LoadCategoriesError {
+    /// Something went wrong reading the directory or one of the files in it
+    Io(std::io::Error),
+    /// A file isn't somewhere that makes a valid category NSID
+    BadLocation(std::path::PathBuf),
+    /// A file isn't valid tag JSON
+    Json(std::path::PathBuf, serde_json::Error),
+    /// A file lists something that isn't a valid NSID
+    InvalidValue(std::path::PathBuf, String, NSIDParseError),
+    /// Some of the values didn't refer to anything, so nothing was loaded
+    Unknown {
+        entries: Vec<NamespacedID>,
+        categories: Vec<NamespacedID>,
+    },
+    /// The categories would end up nested in a loop, so nothing was loaded
+    Cycle(ErrCategoryCycle),
+}
+
+#[cfg(feature = "datapack")]
+impl Display for LoadCategoriesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadCategoriesError::Io(err) => {
+                write!(f, "an error happened while reading: {}", err)
+            }
+            LoadCategoriesError::BadLocation(path) => write!(
+                f,
+                "{} should be at <namespace>/<path>.json to make a category NSID",
+                path.display()
+            ),
+            LoadCategoriesError::Json(path, err) => {
+                write!(f, "{} isn't a valid tag file: {}", path.display(), err)
+            }
+            LoadCategoriesError::InvalidValue(path, value, err) => {
+                write!(f, "{} lists {:?}: {}", path.display(), value, err)
+            }
+            LoadCategoriesError::Unknown {
+                entries,
+                categories,
+            } => {
+                write!(f, "the tag files referenced unknown things:")?;
+                for nsid in entries {
+                    write!(f, " {}", nsid)?;
+                }
+                for nsid in categories {
+                    write!(f, " #{}", nsid)?;
+                }
+                Ok(())
+            }
+            LoadCategoriesError::Cycle(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[cfg(feature = "datapack")]
+impl std::error::Error for LoadCategoriesError {}
+
+#[cfg(feature = "datapack")]
+impl From<std::io::Error> for LoadCategoriesError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+#[derive(Debug)]
+pub enum ReloadError<E> {
+    /// Something went wrong checking the directory for changes
+    Io(std::io::Error),
+    /// The loader function failed
+    Load(E),
+}
+
+#[cfg(feature = "hot-reload")]
+impl<E: Display> Display for ReloadError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadError::Io(err) => {
+                write!(f, "an error happened while checking for changes: {}", err)
+            }
+            ReloadError::Load(err) => {
+                write!(f, "couldn't reload the registry: {}", err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+impl<E: Debug + Display> std::error::Error for ReloadError<E> {}
+
+#[cfg(feature = "hot-reload")]
+impl<E> From<std::io::Error> for ReloadError<E> {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum SmallRegisterError {
+    AlreadyRegistered,
+    /// Every 16-bit handle has been used. Unregistering things doesn't give them back.
+    Full,
+}
+
+impl Display for SmallRegisterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmallRegisterError::AlreadyRegistered => Display::fmt(&ErrAlreadyRegistered, f),
+            SmallRegisterError::Full => {
+                write!(f, "the registry has run out of 16-bit handles")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SmallRegisterError {}
+
+impl From<ErrAlreadyRegistered> for SmallRegisterError {
+    fn from(_: ErrAlreadyRegistered) -> Self {
+        SmallRegisterError::AlreadyRegistered
+    }
+}
@@ -0,0 +1,257 @@
+use std::{hash::BuildHasher, ops::Deref, ops::Index};
+
+use ahash::RandomState;
+
+use crate::{ArenaID, NamespacedID, Registry, RegistryHandle, RegistryKey};
+
+/// A registry that's done registering things.
+///
+/// Make one with [`Registry::freeze`]. It can't be mutated at all, and finding entries by NSID goes through
+/// a perfect hash table built once at freeze time, so a lookup is one hash and one comparison.
+///
+/// Everything else read-only on `Registry` is available through `Deref`.
+pub struct FrozenRegistry<T, K = NamespacedID, S = RandomState> {
+  registry: Registry<T, K, S>,
+  table: PerfectHashTable<K, ArenaID<T>>,
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Stop registering things, and build a faster NSID lookup table for everything that was registered.
+  pub fn freeze(self) -> FrozenRegistry<T, K, S> {
+    let aliases = self.aliases.iter().filter_map(|(alias, target)| {
+      let id = self.nsid_map.get(target)?;
+      (!self.nsid_map.contains_key(alias)).then_some((*alias, *id))
+    });
+    let table = PerfectHashTable::new(
+      self
+        .nsid_map
+        .iter()
+        .map(|(nsid, id)| (*nsid, *id))
+        .chain(aliases)
+        .collect(),
+    );
+    FrozenRegistry {
+      registry: self,
+      table,
+    }
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> FrozenRegistry<T, K, S> {
+  /// Look up something by a NSID, which may or may not actually be in here.
+  pub fn lookup_by_nsid(&self, nsid: K) -> Option<&T> {
+    let id = self.find(nsid)?;
+    Some(&self.registry.slot(id).0)
+  }
+
+  /// If this is a known NSID, turn it into a real `RegistryHandle`.
+  ///
+  /// Aliases and migrations work here too, and get you a handle with the real NSID.
+  pub fn validate_nsid(&self, nsid: K) -> Option<RegistryHandle<T, K>> {
+    let id = self.find(nsid)?;
+    Some(RegistryHandle::new(id, self.registry.slot(id).1))
+  }
+
+  /// Look in the table, then in the migrations for anything that isn't there.
+  fn find(&self, nsid: K) -> Option<ArenaID<T>> {
+    self.table.get(&nsid).or_else(|| {
+      let migrated = self.registry.migrate_nsid(nsid)?;
+      self.table.get(&migrated)
+    })
+  }
+
+  /// Go back to a normal, mutable registry.
+  pub fn thaw(self) -> Registry<T, K, S> {
+    self.registry
+  }
+}
+
+impl<T, K, S> Deref for FrozenRegistry<T, K, S> {
+  type Target = Registry<T, K, S>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.registry
+  }
+}
+
+/// Convenience for `lookup`
+impl<T, K: RegistryKey, S: BuildHasher + Default> Index<RegistryHandle<T, K>>
+  for FrozenRegistry<T, K, S>
+{
+  type Output = T;
+
+  fn index(&self, index: RegistryHandle<T, K>) -> &Self::Output {
+    self.registry.lookup(index)
+  }
+}
+
+/// Convenience for `lookup_by_nsid`. Unwraps if it can't find it.
+impl<T, S: BuildHasher + Default> Index<NamespacedID>
+  for FrozenRegistry<T, NamespacedID, S>
+{
+  type Output = T;
+
+  fn index(&self, index: NamespacedID) -> &Self::Output {
+    self.lookup_by_nsid(index).unwrap_or_else(|| {
+      panic!(
+        "couldn't find the NSID `{}` in the frozen {} registry",
+        &index,
+        std::any::type_name::<T>()
+      )
+    })
+  }
+}
+
+/// Hash-and-displace perfect hash table.
+///
+/// Keys are split into buckets by hash, and each bucket gets a displacement
+/// that puts all its keys into empty slots. Looking a key up only ever checks one slot.
+struct PerfectHashTable<K, V> {
+  hasher: RandomState,
+  displacements: Box<[u64]>,
+  slots: Box<[Option<(K, V)>]>,
+}
+
+/// How many displacements to try for one bucket before starting over with new seeds.
+const MAX_DISPLACEMENT_TRIES: u64 = 1 << 16;
+
+impl<K: RegistryKey, V: Copy> PerfectHashTable<K, V> {
+  /// Build a table out of these pairs. The keys all have to be different.
+  fn new(pairs: Vec<(K, V)>) -> Self {
+    // Powers of two so finding a slot is a mask instead of a division
+    let slot_count = (pairs.len() + pairs.len() / 4 + 1).next_power_of_two();
+    let bucket_count = (pairs.len() / 4 + 1).next_power_of_two();
+
+    for seed in 0.. {
+      let hasher = RandomState::with_seeds(seed, 0x5eed, seed ^ 0x2a, 0xfeed);
+      if let Some(table) =
+        Self::try_build(hasher, &pairs, slot_count, bucket_count)
+      {
+        return table;
+      }
+    }
+    unreachable!()
+  }
+
+  fn try_build(
+    hasher: RandomState,
+    pairs: &[(K, V)],
+    slot_count: usize,
+    bucket_count: usize,
+  ) -> Option<Self> {
+    let mut buckets = vec![Vec::new(); bucket_count];
+    for (idx, (key, _)) in pairs.iter().enumerate() {
+      let hash = hasher.hash_one(key);
+      buckets[bucket_of(hash, bucket_count)].push((idx, hash));
+    }
+    // Biggest buckets first, while there's the most room
+    let mut order: Vec<_> = (0..bucket_count).collect();
+    order.sort_by_key(|&b| std::cmp::Reverse(buckets[b].len()));
+
+    let mut displacements = vec![0; bucket_count];
+    let mut slots: Vec<Option<(K, V)>> = vec![None; slot_count];
+    let mut taken = Vec::new();
+    for b in order {
+      let bucket = &buckets[b];
+      if bucket.is_empty() {
+        break;
+      }
+      let displacement = (0..MAX_DISPLACEMENT_TRIES).find(|&d| {
+        taken.clear();
+        bucket.iter().all(|&(_, hash)| {
+          let slot = slot_of(hash, d, slot_count);
+          if slots[slot].is_some() || taken.contains(&slot) {
+            return false;
+          }
+          taken.push(slot);
+          true
+        })
+      })?;
+      displacements[b] = displacement;
+      for (&(idx, _), &slot) in bucket.iter().zip(taken.iter()) {
+        slots[slot] = Some(pairs[idx]);
+      }
+    }
+
+    Some(Self {
+      hasher,
+      displacements: displacements.into_boxed_slice(),
+      slots: slots.into_boxed_slice(),
+    })
+  }
+
+  fn get(&self, key: &K) -> Option<V> {
+    let hash = self.hasher.hash_one(key);
+    let displacement =
+      self.displacements[bucket_of(hash, self.displacements.len())];
+    match self.slots[slot_of(hash, displacement, self.slots.len())] {
+      Some((k, v)) if k == *key => Some(v),
+      _ => None,
+    }
+  }
+}
+
+fn bucket_of(hash: u64, bucket_count: usize) -> usize {
+  (hash.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as usize & (bucket_count - 1)
+}
+
+fn slot_of(hash: u64, displacement: u64, slot_count: usize) -> usize {
+  let f1 = hash & 0xffff_ffff;
+  // Odd, so stepping through displacements visits every slot
+  let f2 = (hash >> 32) | 1;
+  f1.wrapping_add(displacement.wrapping_mul(f2)) as usize & (slot_count - 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::MigrationTable;
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn frozen_lookups_match_the_registry() {
+    let mut registry = Registry::<u32>::new();
+    for i in 0..100 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      registry.register(i, nsid.unwrap()).unwrap();
+    }
+    registry
+      .register_alias(nsid("game:first"), nsid("game:e0"))
+      .unwrap();
+    let mut migrations = MigrationTable::new();
+    migrations.add(nsid("old:e5"), nsid("game:e5")).unwrap();
+    registry.set_migrations(migrations);
+
+    let frozen = registry.freeze();
+    for i in 0..100 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      assert_eq!(frozen.lookup_by_nsid(nsid.unwrap()), Some(&i));
+    }
+    assert_eq!(frozen[nsid("game:first")], 0);
+    let e5 = frozen.validate_nsid(nsid("old:e5")).unwrap();
+    assert_eq!(e5.get_nsid(), nsid("game:e5"));
+    assert_eq!(frozen[e5], 5);
+    assert_eq!(frozen.lookup_by_nsid(nsid("game:e100")), None);
+    assert_eq!(frozen.len(), 100);
+
+    let mut thawed = frozen.thaw();
+    thawed.register(100, nsid("game:e100")).unwrap();
+    assert_eq!(thawed.len(), 101);
+  }
+
+  #[test]
+  fn freeze_an_empty_registry() {
+    let frozen = Registry::<u32>::new().freeze();
+    assert_eq!(frozen.lookup_by_nsid(nsid("game:stone")), None);
+  }
+
+  #[test]
+  fn frozen_registries_are_sync() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<FrozenRegistry<u32>>();
+  }
+}
@@ -0,0 +1,366 @@
+use std::{fmt::Debug, hash::BuildHasher, vec};
+
+use crate::{
+  ErrCategoryCycle, NamespacedID, Registry, RegistryHandle, RegistryKey,
+};
+
+/// A reference to an entry that might not be registered yet, like one read out of a data file
+/// before the registry it points into is loaded.
+///
+/// Start with the NSID, and once the registry is ready, `resolve` it into a handle.
+/// With the `serde` feature it serializes as the NSID, and always deserializes unresolved.
+pub enum Holder<T, K = NamespacedID> {
+  Resolved(RegistryHandle<T, K>),
+  Unresolved(K),
+}
+
+impl<T, K: RegistryKey> Holder<T, K> {
+  /// Get the NSID this refers to, whether or not it's been resolved.
+  ///
+  /// Once it's resolved through an alias, this is the real NSID.
+  pub fn get_nsid(&self) -> K {
+    match self {
+      Holder::Resolved(handle) => handle.get_nsid(),
+      Holder::Unresolved(nsid) => *nsid,
+    }
+  }
+
+  /// Get the handle, if this has been resolved.
+  pub fn handle(&self) -> Option<RegistryHandle<T, K>> {
+    match self {
+      Holder::Resolved(handle) => Some(*handle),
+      Holder::Unresolved(_) => None,
+    }
+  }
+
+  pub fn is_resolved(&self) -> bool {
+    matches!(self, Holder::Resolved(_))
+  }
+
+  /// Find what this refers to in the registry, and remember its handle.
+  ///
+  /// Returns `None` and stays unresolved if nothing is registered under the NSID.
+  /// If it's already resolved, it's not looked up again.
+  pub fn resolve<S: BuildHasher + Default>(
+    &mut self,
+    registry: &Registry<T, K, S>,
+  ) -> Option<RegistryHandle<T, K>> {
+    if let Holder::Unresolved(nsid) = self {
+      *self = Holder::Resolved(registry.validate_nsid(*nsid)?);
+    }
+    self.handle()
+  }
+
+  /// Look up what this refers to, by handle if it's resolved and by NSID if it's not.
+  pub fn get<'a, S: BuildHasher + Default>(
+    &self,
+    registry: &'a Registry<T, K, S>,
+  ) -> Option<&'a T> {
+    match self {
+      Holder::Resolved(handle) => {
+        let (x, _) = registry.arena.get(handle.id)?.as_ref()?;
+        Some(x)
+      }
+      Holder::Unresolved(nsid) => registry.lookup_by_nsid(*nsid),
+    }
+  }
+}
+
+impl<T, K: RegistryKey> Holder<T, K> {
+  /// Get the handle this refers to right now, without remembering it.
+  ///
+  /// A resolved handle that's since been unregistered gives `None`.
+  fn live_handle<S: BuildHasher + Default>(
+    &self,
+    registry: &Registry<T, K, S>,
+  ) -> Option<RegistryHandle<T, K>> {
+    match self {
+      Holder::Resolved(handle) => {
+        registry.arena.get(handle.id)?.as_ref().map(|_| *handle)
+      }
+      Holder::Unresolved(nsid) => registry.validate_nsid(*nsid),
+    }
+  }
+}
+
+impl<T, K: RegistryKey> From<RegistryHandle<T, K>> for Holder<T, K> {
+  fn from(handle: RegistryHandle<T, K>) -> Self {
+    Holder::Resolved(handle)
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T, K: RegistryKey> Clone for Holder<T, K> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T, K: RegistryKey> Copy for Holder<T, K> {}
+
+/// Two holders are equal if they refer to the same NSID, resolved or not.
+impl<T, K: RegistryKey> PartialEq for Holder<T, K> {
+  fn eq(&self, other: &Self) -> bool {
+    self.get_nsid() == other.get_nsid()
+  }
+}
+
+impl<T, K: RegistryKey> Eq for Holder<T, K> {}
+
+impl<T, K: RegistryKey> Debug for Holder<T, K> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Holder::Resolved(handle) => {
+        f.debug_tuple("Resolved").field(&handle.get_nsid()).finish()
+      }
+      Holder::Unresolved(nsid) => {
+        f.debug_tuple("Unresolved").field(nsid).finish()
+      }
+    }
+  }
+}
+
+/// Either a list of entries or a whole category, like the "accepts some items or a tag" fields in data files.
+///
+/// With the `serde` feature, a category is written `"#namespace:path"` and a list is an array of NSIDs.
+/// A single NSID string deserializes as a list of just that.
+pub enum HolderSet<T, K = NamespacedID> {
+  List(Vec<Holder<T, K>>),
+  Category(K),
+}
+
+impl<T, K: RegistryKey> HolderSet<T, K> {
+  /// Resolve every entry in the list against the registry, like `Holder::resolve`.
+  ///
+  /// Returns the NSIDs of everything that isn't registered. A category is left alone.
+  pub fn resolve<S: BuildHasher + Default>(
+    &mut self,
+    registry: &Registry<T, K, S>,
+  ) -> Vec<K> {
+    match self {
+      HolderSet::List(holders) => holders
+        .iter_mut()
+        .filter_map(|holder| match holder.resolve(registry) {
+          Some(_) => None,
+          None => Some(holder.get_nsid()),
+        })
+        .collect(),
+      HolderSet::Category(_) => Vec::new(),
+    }
+  }
+
+  /// Get the handles of everything in this set.
+  ///
+  /// A category includes everything in the categories nested inside it, like `lookup_category_recursive`,
+  /// and if those nest in a loop, the loop is returned as an error.
+  /// Returns `Ok(None)` if it names a category that isn't registered, or if anything in the list isn't.
+  pub fn handles<S: BuildHasher + Default>(
+    &self,
+    registry: &Registry<T, K, S>,
+  ) -> Result<Option<HolderSetIter<T, K>>, ErrCategoryCycle<K>> {
+    let handles = match self {
+      HolderSet::List(holders) => holders
+        .iter()
+        .map(|holder| holder.live_handle(registry))
+        .collect::<Option<Vec<_>>>(),
+      HolderSet::Category(nsid) => match registry.validate_category_nsid(*nsid)
+      {
+        Some(category) => Some(
+          registry
+            .lookup_category_recursive(category)?
+            .into_iter()
+            .map(|(_, handle)| handle)
+            .collect(),
+        ),
+        None => None,
+      },
+    };
+    Ok(handles.map(|handles| HolderSetIter {
+      inner: handles.into_iter(),
+    }))
+  }
+
+  /// Return if this entry is in the set.
+  ///
+  /// Things in the list are looked up the same way as in `handles`, so an NSID matches
+  /// through an alias and a handle that's been unregistered never matches.
+  /// A category counts everything nested inside it too, like in `handles`.
+  pub fn contains<S: BuildHasher + Default>(
+    &self,
+    registry: &Registry<T, K, S>,
+    entry: RegistryHandle<T, K>,
+  ) -> Result<bool, ErrCategoryCycle<K>> {
+    match self {
+      HolderSet::List(holders) => Ok(
+        holders
+          .iter()
+          .any(|holder| holder.live_handle(registry) == Some(entry)),
+      ),
+      HolderSet::Category(nsid) => match registry.validate_category_nsid(*nsid)
+      {
+        Some(category) => Ok(
+          registry
+            .lookup_category_recursive(category)?
+            .iter()
+            .any(|(_, handle)| *handle == entry),
+        ),
+        None => Ok(false),
+      },
+    }
+  }
+}
+
+impl<T, K: RegistryKey> From<Vec<Holder<T, K>>> for HolderSet<T, K> {
+  fn from(holders: Vec<Holder<T, K>>) -> Self {
+    HolderSet::List(holders)
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T, K: RegistryKey> Clone for HolderSet<T, K> {
+  fn clone(&self) -> Self {
+    match self {
+      HolderSet::List(holders) => HolderSet::List(holders.clone()),
+      HolderSet::Category(nsid) => HolderSet::Category(*nsid),
+    }
+  }
+}
+
+impl<T, K: RegistryKey> PartialEq for HolderSet<T, K> {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (HolderSet::List(a), HolderSet::List(b)) => a == b,
+      (HolderSet::Category(a), HolderSet::Category(b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+impl<T, K: RegistryKey> Eq for HolderSet<T, K> {}
+
+impl<T, K: RegistryKey> Debug for HolderSet<T, K> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      HolderSet::List(holders) => f.debug_tuple("List").field(holders).finish(),
+      HolderSet::Category(nsid) => {
+        f.debug_tuple("Category").field(nsid).finish()
+      }
+    }
+  }
+}
+
+/// Iterator over the handles in a [`HolderSet`], from [`HolderSet::handles`].
+pub struct HolderSetIter<T, K = NamespacedID> {
+  inner: vec::IntoIter<RegistryHandle<T, K>>,
+}
+
+impl<T, K: RegistryKey> Iterator for HolderSetIter<T, K> {
+  type Item = RegistryHandle<T, K>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+}
+
+impl<T, K: RegistryKey> ExactSizeIterator for HolderSetIter<T, K> {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn resolve_follows_aliases() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+
+    let mut holder = Holder::<u32>::Unresolved(nsid("game:rock"));
+    assert_eq!(holder.get(&registry), Some(&1));
+    assert_eq!(holder.resolve(&registry), Some(stone));
+    assert_eq!(holder.get_nsid(), nsid("game:stone"));
+
+    let mut missing = Holder::<u32>::Unresolved(nsid("game:dirt"));
+    assert_eq!(missing.resolve(&registry), None);
+    assert!(!missing.is_resolved());
+  }
+
+  #[test]
+  fn list_contains_through_an_alias() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+
+    let set = HolderSet::List(vec![Holder::Unresolved(nsid("game:rock"))]);
+    assert!(set.contains(&registry, stone).unwrap());
+    assert!(!set.contains(&registry, dirt).unwrap());
+    let handles: Vec<_> = set.handles(&registry).unwrap().unwrap().collect();
+    assert_eq!(handles, [stone]);
+  }
+
+  #[test]
+  fn list_ignores_unregistered_handles() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let set = HolderSet::List(vec![Holder::Resolved(stone)]);
+    assert!(set.contains(&registry, stone).unwrap());
+
+    registry.unregister(stone);
+    let stone_again = registry.register(1, nsid("game:stone")).unwrap();
+    assert!(!set.contains(&registry, stone_again).unwrap());
+    assert!(set.handles(&registry).unwrap().is_none());
+  }
+
+  #[test]
+  fn category_contains_nested_entries() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let stones = registry
+      .register_category(nsid("game:stones"), [stone])
+      .unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [dirt])
+      .unwrap();
+    registry.nest_category(blocks, stones).unwrap();
+
+    let set = HolderSet::<u32>::Category(nsid("game:blocks"));
+    assert!(set.contains(&registry, stone).unwrap());
+    assert!(set.contains(&registry, dirt).unwrap());
+    assert_eq!(set.handles(&registry).unwrap().unwrap().len(), 2);
+
+    let missing = HolderSet::<u32>::Category(nsid("game:nope"));
+    assert!(!missing.contains(&registry, stone).unwrap());
+    assert!(missing.handles(&registry).unwrap().is_none());
+  }
+
+  #[test]
+  fn holders_resolve_once_the_entry_exists() {
+    let mut registry = Registry::<u32>::new();
+    let mut holder = Holder::<u32>::Unresolved(nsid("game:stone"));
+    assert_eq!(holder.resolve(&registry), None);
+    assert_eq!(holder.get(&registry), None);
+
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    assert_eq!(holder.resolve(&registry), Some(stone));
+    assert!(holder.is_resolved());
+    assert_eq!(holder, Holder::Unresolved(nsid("game:stone")));
+    assert_eq!(holder, Holder::from(stone));
+
+    registry.unregister(stone);
+    assert_eq!(holder.get(&registry), None);
+    assert_eq!(holder.handle(), Some(stone));
+  }
+}
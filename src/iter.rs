@@ -0,0 +1,391 @@
+use std::{
+  collections::{hash_set, HashSet},
+  hash::BuildHasher,
+};
+
+use ahash::RandomState;
+use id_arena::DefaultArenaBehavior;
+
+use crate::{
+  ArenaID, CatWrapper, CategoryHandle, CategoryID, NamespacedID, Registry,
+  RegistryHandle, RegistryKey,
+};
+
+type ArenaIter<'a, T, K> =
+  id_arena::Iter<'a, Option<(T, K)>, DefaultArenaBehavior<T>>;
+type CategorySlot<T, K, S> = (HashSet<ArenaID<T>, S>, K);
+type CategoryArenaIter<'a, T, K, S> = id_arena::Iter<
+  'a,
+  CategorySlot<T, K, S>,
+  DefaultArenaBehavior<CatWrapper<T>>,
+>;
+type ArenaIntoIter<T, K> =
+  id_arena::IntoIter<Option<(T, K)>, DefaultArenaBehavior<T>>;
+
+/// Iterator over everything in a registry, from [`Registry::iter`].
+pub struct Iter<'a, T, K = NamespacedID> {
+  inner: ArenaIter<'a, T, K>,
+  /// How many live entries are left; the arena also has the holes left by unregistered ones
+  remaining: usize,
+}
+
+/// Iterator that takes a registry apart into its values and their NSIDs, from `Registry::into_iter`.
+///
+/// Categories are thrown away.
+pub struct IntoIter<T, K = NamespacedID> {
+  inner: ArenaIntoIter<T, K>,
+  remaining: usize,
+}
+
+/// Iterator over the members of a category, from [`Registry::lookup_category`] and friends.
+pub struct CategoryIter<'a, T, K = NamespacedID, S = RandomState> {
+  registry: &'a Registry<T, K, S>,
+  inner: hash_set::Iter<'a, ArenaID<T>>,
+}
+
+/// Iterator over the values of the members of a category, from [`Registry::category_values_by_nsid`].
+pub struct CategoryValues<'a, T, K = NamespacedID, S = RandomState> {
+  inner: CategoryIter<'a, T, K, S>,
+}
+
+/// Iterator over some categories, from [`Registry::categories_of`] and [`Registry::subcategories`].
+pub struct CategoryHandles<'a, T, K = NamespacedID, S = RandomState> {
+  registry: &'a Registry<T, K, S>,
+  inner: Option<hash_set::Iter<'a, CategoryID<T>>>,
+}
+
+/// Iterator over every category in a registry, with its NSID and how many members it has,
+/// from [`Registry::iter_categories`].
+pub struct Categories<'a, T, K = NamespacedID, S = RandomState> {
+  inner: CategoryArenaIter<'a, T, K, S>,
+}
+
+impl<'a, T, K> Iter<'a, T, K> {
+  pub(crate) fn new<S>(registry: &'a Registry<T, K, S>) -> Self {
+    Self {
+      inner: registry.arena.iter(),
+      remaining: registry.nsid_map.len(),
+    }
+  }
+}
+
+impl<'a, T, K: RegistryKey> Iterator for Iter<'a, T, K> {
+  type Item = (&'a T, RegistryHandle<T, K>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let out = self.inner.find_map(live_entry)?;
+    self.remaining -= 1;
+    Some(out)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<'a, T, K: RegistryKey> DoubleEndedIterator for Iter<'a, T, K> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    let out = self.inner.by_ref().rev().find_map(live_entry)?;
+    self.remaining -= 1;
+    Some(out)
+  }
+}
+
+impl<'a, T, K: RegistryKey> ExactSizeIterator for Iter<'a, T, K> {}
+
+fn live_entry<T, K: RegistryKey>(
+  (id, slot): (ArenaID<T>, &Option<(T, K)>),
+) -> Option<(&T, RegistryHandle<T, K>)> {
+  let (x, nsid) = slot.as_ref()?;
+  Some((x, RegistryHandle::new(id, *nsid)))
+}
+
+impl<T, K> Iterator for IntoIter<T, K> {
+  type Item = (T, K);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let out = self.inner.find_map(|(_, slot)| slot)?;
+    self.remaining -= 1;
+    Some(out)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.remaining, Some(self.remaining))
+  }
+}
+
+impl<T, K> DoubleEndedIterator for IntoIter<T, K> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    let out = self.inner.by_ref().rev().find_map(|(_, slot)| slot)?;
+    self.remaining -= 1;
+    Some(out)
+  }
+}
+
+impl<T, K> ExactSizeIterator for IntoIter<T, K> {}
+
+impl<'a, T, K, S> CategoryIter<'a, T, K, S> {
+  pub(crate) fn new(
+    registry: &'a Registry<T, K, S>,
+    id: CategoryID<T>,
+  ) -> Self {
+    Self {
+      registry,
+      inner: registry.category_arena.get(id).unwrap().0.iter(),
+    }
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> Iterator
+  for CategoryIter<'a, T, K, S>
+{
+  type Item = (&'a T, RegistryHandle<T, K>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let id = *self.inner.next()?;
+    let (out, nsid) = self.registry.slot(id);
+    Some((out, RegistryHandle::new(id, *nsid)))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> ExactSizeIterator
+  for CategoryIter<'a, T, K, S>
+{
+}
+
+impl<'a, T, K, S> CategoryValues<'a, T, K, S> {
+  pub(crate) fn new(inner: CategoryIter<'a, T, K, S>) -> Self {
+    Self { inner }
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> Iterator
+  for CategoryValues<'a, T, K, S>
+{
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.next().map(|(x, _)| x)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> ExactSizeIterator
+  for CategoryValues<'a, T, K, S>
+{
+}
+
+impl<'a, T, K, S> CategoryHandles<'a, T, K, S> {
+  pub(crate) fn new(
+    registry: &'a Registry<T, K, S>,
+    categories: Option<&'a HashSet<CategoryID<T>, S>>,
+  ) -> Self {
+    Self {
+      registry,
+      inner: categories.map(|set| set.iter()),
+    }
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> Iterator
+  for CategoryHandles<'a, T, K, S>
+{
+  type Item = CategoryHandle<T, K>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let id = *self.inner.as_mut()?.next()?;
+    Some(self.registry.category_handle(id))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = self.inner.as_ref().map_or(0, |inner| inner.len());
+    (len, Some(len))
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> ExactSizeIterator
+  for CategoryHandles<'a, T, K, S>
+{
+}
+
+impl<'a, T, K, S> Categories<'a, T, K, S> {
+  pub(crate) fn new(registry: &'a Registry<T, K, S>) -> Self {
+    Self {
+      inner: registry.category_arena.iter(),
+    }
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> Iterator
+  for Categories<'a, T, K, S>
+{
+  type Item = (CategoryHandle<T, K>, K, usize);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.next().map(category_info)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> DoubleEndedIterator
+  for Categories<'a, T, K, S>
+{
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.inner.next_back().map(category_info)
+  }
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> ExactSizeIterator
+  for Categories<'a, T, K, S>
+{
+}
+
+fn category_info<T, K: RegistryKey, S>(
+  (id, (set, nsid)): (CategoryID<T>, &CategorySlot<T, K, S>),
+) -> (CategoryHandle<T, K>, K, usize) {
+  (CategoryHandle::new(id, *nsid), *nsid, set.len())
+}
+
+impl<'a, T, K: RegistryKey, S: BuildHasher + Default> IntoIterator
+  for &'a Registry<T, K, S>
+{
+  type Item = (&'a T, RegistryHandle<T, K>);
+  type IntoIter = Iter<'a, T, K>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl<T, K, S> IntoIterator for Registry<T, K, S> {
+  type Item = (T, K);
+  type IntoIter = IntoIter<T, K>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    IntoIter {
+      remaining: self.nsid_map.len(),
+      inner: self.arena.into_iter(),
+    }
+  }
+}
+
+/// Register everything from the iterator.
+///
+/// Panics if any NSID is already taken, naming it; use `register_many` to handle clashes instead.
+impl<T, K: RegistryKey, S: BuildHasher + Default> Extend<(K, T)>
+  for Registry<T, K, S>
+{
+  fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+    let iter = iter.into_iter();
+    self.reserve(iter.size_hint().0, 0);
+    for (nsid, entry) in iter {
+      if self.try_register(entry, nsid).is_err() {
+        panic!("{:?} was registered more than once", nsid);
+      }
+    }
+  }
+}
+
+/// Make a registry out of everything from the iterator.
+///
+/// Panics if any NSID shows up more than once, like `extend`.
+impl<T, K: RegistryKey, S: BuildHasher + Default> FromIterator<(K, T)>
+  for Registry<T, K, S>
+{
+  fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+    let mut registry = Registry::default();
+    registry.extend(iter);
+    registry
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn registries_work_in_for_loops() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry.register(3, nsid("game:sand")).unwrap();
+    registry.unregister(dirt);
+
+    let mut total = 0;
+    for (x, handle) in &registry {
+      assert_eq!(registry.get_nsid(handle), handle.get_nsid());
+      total += x;
+    }
+    assert_eq!(total, 4);
+
+    let parts: Vec<_> = registry.into_iter().collect();
+    assert_eq!(parts, [(1, nsid("game:stone")), (3, nsid("game:sand"))]);
+  }
+
+  #[test]
+  fn iterators_know_their_length() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    registry.unregister(dirt);
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, sand])
+      .unwrap();
+    registry
+      .register_empty_category(nsid("game:empty"))
+      .unwrap();
+
+    let mut iter = registry.iter();
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next_back().map(|(x, _)| *x), Some(3));
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next().map(|(x, _)| *x), Some(1));
+    assert_eq!(iter.len(), 0);
+    assert!(iter.next().is_none());
+
+    assert_eq!(registry.lookup_category(blocks).len(), 2);
+    assert_eq!(registry.categories_of(stone).len(), 1);
+    let last = registry.iter_categories().next_back().unwrap();
+    assert_eq!((last.1, last.2), (nsid("game:empty"), 0));
+    assert_eq!(registry.iter_categories().len(), 2);
+
+    let mut owned = registry.into_iter();
+    assert_eq!(owned.len(), 2);
+    assert_eq!(owned.next_back(), Some((3, nsid("game:sand"))));
+    assert_eq!(owned.len(), 1);
+  }
+
+  #[test]
+  fn collect_and_extend_registries() {
+    let mut registry: Registry<u32> =
+      [(nsid("game:stone"), 1), (nsid("game:dirt"), 2)]
+        .into_iter()
+        .collect();
+    registry.extend([(nsid("game:sand"), 3)]);
+    assert_eq!(registry.len(), 3);
+    assert_eq!(registry[nsid("game:sand")], 3);
+  }
+
+  #[test]
+  #[should_panic(expected = "game:stone was registered more than once")]
+  fn collecting_a_duplicate_panics() {
+    let _: Registry<u32> = [(nsid("game:stone"), 1), (nsid("game:stone"), 2)]
+      .into_iter()
+      .collect();
+  }
+}
@@ -0,0 +1,64 @@
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{NamespacedID, NsidInterner};
+
+/// Something that can name the entries and categories in a [`Registry`](crate::Registry).
+///
+/// This is [`NamespacedID`] by default, but you can implement it for your own ID types
+/// (like a plain number) if you don't want minecraft-style names.
+pub trait RegistryKey: Copy + Eq + Hash + Debug {
+  /// The namespace and path of this key, if it has them, which is what `Registry::iter_namespace` indexes by.
+  ///
+  /// Only [`NamespacedID`]s have them by default.
+  fn namespace_and_path(&self) -> Option<(&'static str, &'static str)> {
+    None
+  }
+
+  /// Point a key made by `from` at `to`, a clone of it. Only [`LocalNamespacedID`](crate::LocalNamespacedID)s care.
+  #[doc(hidden)]
+  fn rebase_interner(self, _from: &NsidInterner, _to: &NsidInterner) -> Self {
+    self
+  }
+}
+
+impl RegistryKey for NamespacedID {
+  fn namespace_and_path(&self) -> Option<(&'static str, &'static str)> {
+    Some(self.resolve())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Registry;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+  struct BlockId(u32);
+
+  impl RegistryKey for BlockId {}
+
+  #[test]
+  fn custom_keys_work_next_to_nsids() {
+    let mut numbered = Registry::<&str, BlockId>::new();
+    let stone = numbered.register("stone", BlockId(1)).unwrap();
+    numbered.register("dirt", BlockId(2)).unwrap();
+    assert!(numbered.register("again", BlockId(1)).is_err());
+    let solid = numbered.register_category(BlockId(100), [stone]).unwrap();
+
+    assert_eq!(numbered.lookup_by_nsid(BlockId(2)), Some(&"dirt"));
+    assert_eq!(stone.get_nsid(), BlockId(1));
+    assert!(numbered.is_in_category(stone, solid));
+    assert_eq!(BlockId(1).namespace_and_path(), None);
+
+    let mut named = Registry::<&str>::new();
+    let stone = named.register("stone", crate::nsid("game:stone")).unwrap();
+    assert_eq!(
+      named[stone],
+      numbered[numbered.validate_nsid(BlockId(1)).unwrap()]
+    );
+    assert_eq!(
+      stone.get_nsid().namespace_and_path(),
+      Some(("game", "stone"))
+    );
+  }
+}
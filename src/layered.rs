@@ -0,0 +1,204 @@
+use std::hash::BuildHasher;
+
+use ahash::{AHashSet, RandomState};
+
+use crate::{NamespacedID, Registry, RegistryKey};
+
+/// A base registry with any number of overlay registries stacked on top, like vanilla content plus datapacks.
+///
+/// Looking something up by NSID checks the topmost layer first and works its way down,
+/// so a layer can override anything below it. Pop a layer off to undo it.
+///
+/// Handles only make sense for the registry they came from, so everything here goes by NSID.
+/// Use `layer` to get at an individual registry if you need handles.
+pub struct LayeredRegistry<T, K = NamespacedID, S = RandomState> {
+  /// The base is always at index 0 and can't be popped.
+  layers: Vec<Registry<T, K, S>>,
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> LayeredRegistry<T, K, S> {
+  pub fn new(base: Registry<T, K, S>) -> Self {
+    Self { layers: vec![base] }
+  }
+
+  /// Put a new layer on top of everything else.
+  pub fn push_layer(&mut self, layer: Registry<T, K, S>) {
+    self.layers.push(layer);
+  }
+
+  /// Take the topmost layer off and return it.
+  ///
+  /// Returns `None` if only the base is left; it can't be popped.
+  pub fn pop_layer(&mut self) -> Option<Registry<T, K, S>> {
+    if self.layers.len() > 1 {
+      self.layers.pop()
+    } else {
+      None
+    }
+  }
+
+  /// How many layers there are, counting the base.
+  pub fn layer_count(&self) -> usize {
+    self.layers.len()
+  }
+
+  /// Get a layer by index. The base is `0`, and the top is `layer_count() - 1`.
+  pub fn layer(&self, idx: usize) -> Option<&Registry<T, K, S>> {
+    self.layers.get(idx)
+  }
+
+  /// Get the base registry.
+  pub fn base(&self) -> &Registry<T, K, S> {
+    &self.layers[0]
+  }
+
+  /// Get the topmost layer mutably, to register more things into it.
+  pub fn top_mut(&mut self) -> &mut Registry<T, K, S> {
+    self.layers.last_mut().unwrap()
+  }
+
+  /// Look up something by a NSID in the topmost layer that has it.
+  pub fn lookup_by_nsid(&self, nsid: K) -> Option<&T> {
+    self
+      .layers_top_down()
+      .find_map(|layer| layer.lookup_by_nsid(nsid))
+  }
+
+  /// Find the index of the topmost layer that has something registered under this NSID.
+  pub fn layer_of(&self, nsid: K) -> Option<usize> {
+    self
+      .layers
+      .iter()
+      .rposition(|layer| layer.validate_nsid(nsid).is_some())
+  }
+
+  /// Return if any layer has something registered under this NSID.
+  pub fn contains_nsid(&self, nsid: K) -> bool {
+    self.layer_of(nsid).is_some()
+  }
+
+  /// Iterate over every NSID registered in any layer, along with the value from the topmost layer that has it.
+  ///
+  /// Each NSID shows up once.
+  pub fn iter(&self) -> impl Iterator<Item = (K, &T)> {
+    let mut seen = AHashSet::new();
+    self
+      .layers_top_down()
+      .flat_map(|layer| layer.iter())
+      .map(|(x, handle)| (handle.get_nsid(), x))
+      .filter(move |(nsid, _)| seen.insert(*nsid))
+  }
+
+  /// Get the NSIDs of everything in this category, across every layer that has a category with this NSID.
+  ///
+  /// Layers add to categories instead of replacing them, so this is everything from all of them.
+  /// Returns `None` if no layer has this category.
+  pub fn category_member_nsids(&self, nsid: K) -> Option<AHashSet<K>> {
+    let mut found = false;
+    let mut members = AHashSet::new();
+    for layer in &self.layers {
+      if let Some(category) = layer.lookup_category_by_nsid(nsid) {
+        found = true;
+        members.extend(category.map(|(_, handle)| handle.get_nsid()));
+      }
+    }
+    found.then_some(members)
+  }
+
+  fn layers_top_down(&self) -> impl Iterator<Item = &Registry<T, K, S>> {
+    self.layers.iter().rev()
+  }
+}
+
+/// Start a stack with this registry as the base.
+impl<T, K: RegistryKey, S: BuildHasher + Default> From<Registry<T, K, S>>
+  for LayeredRegistry<T, K, S>
+{
+  fn from(base: Registry<T, K, S>) -> Self {
+    Self::new(base)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  fn base() -> Registry<u32> {
+    let mut base = Registry::new();
+    let stone = base.register(1, nsid("game:stone")).unwrap();
+    base.register(2, nsid("game:dirt")).unwrap();
+    base
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    base
+  }
+
+  #[test]
+  fn top_layer_wins() {
+    let mut layered = LayeredRegistry::new(base());
+    let mut overlay = Registry::new();
+    overlay.register(10, nsid("game:stone")).unwrap();
+    overlay.register(3, nsid("pack:gem")).unwrap();
+    layered.push_layer(overlay);
+
+    assert_eq!(layered.lookup_by_nsid(nsid("game:stone")), Some(&10));
+    assert_eq!(layered.lookup_by_nsid(nsid("game:dirt")), Some(&2));
+    assert_eq!(layered.layer_of(nsid("pack:gem")), Some(1));
+    let mut all: Vec<_> = layered.iter().collect();
+    all.sort();
+    assert_eq!(
+      all,
+      [
+        (nsid("game:dirt"), &2),
+        (nsid("game:stone"), &10),
+        (nsid("pack:gem"), &3)
+      ]
+    );
+
+    assert!(layered.pop_layer().is_some());
+    assert!(layered.pop_layer().is_none());
+    assert_eq!(layered.lookup_by_nsid(nsid("game:stone")), Some(&1));
+    assert!(!layered.contains_nsid(nsid("pack:gem")));
+  }
+
+  #[test]
+  fn categories_merge_across_layers() {
+    let mut layered = LayeredRegistry::new(base());
+    let mut overlay = Registry::new();
+    let gem = overlay.register(3, nsid("pack:gem")).unwrap();
+    overlay
+      .register_category(nsid("game:blocks"), [gem])
+      .unwrap();
+    layered.push_layer(overlay);
+
+    let members = layered.category_member_nsids(nsid("game:blocks")).unwrap();
+    assert_eq!(members.len(), 2);
+    assert!(members.contains(&nsid("game:stone")));
+    assert!(members.contains(&nsid("pack:gem")));
+    assert!(layered.category_member_nsids(nsid("game:nope")).is_none());
+  }
+
+  #[test]
+  fn popping_reverts_one_layer_at_a_time() {
+    let mut layered = LayeredRegistry::new(base());
+    for value in [10, 20] {
+      let mut overlay = Registry::new();
+      overlay.register(value, nsid("game:stone")).unwrap();
+      layered.push_layer(overlay);
+    }
+    layered.top_mut().register(30, nsid("pack:gem")).unwrap();
+    assert_eq!(layered.layer_count(), 3);
+    assert_eq!(layered.lookup_by_nsid(nsid("game:stone")), Some(&20));
+    assert_eq!(layered.layer_of(nsid("pack:gem")), Some(2));
+
+    let popped = layered.pop_layer().unwrap();
+    assert_eq!(popped.len(), 2);
+    assert_eq!(layered.lookup_by_nsid(nsid("game:stone")), Some(&10));
+    assert!(!layered.contains_nsid(nsid("pack:gem")));
+    assert_eq!(layered.base().len(), 2);
+  }
+}
@@ -1,54 +1,269 @@
 #![doc = include_str!("../README.md")]
 
+mod builder;
+mod checksum;
+#[cfg(feature = "datapack")]
+mod datapack;
+mod dense;
+mod diff;
+mod entry;
 mod err;
+mod frozen;
+mod holder;
+mod iter;
+mod key;
+mod layered;
+mod local;
+mod manager;
+mod migration;
 mod nsid;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pattern;
+#[cfg(feature = "hot-reload")]
+mod reload;
+mod rules;
+mod secondary;
+#[cfg(feature = "serde")]
+mod serialization;
+mod small;
+mod suggest;
+mod sync;
+pub use builder::*;
+pub use dense::*;
+pub use diff::*;
+pub use entry::*;
 pub use err::*;
+pub use frozen::*;
+pub use holder::*;
+pub use iter::*;
+pub use key::*;
+pub use layered::*;
+pub use local::*;
+pub use manager::*;
+pub use migration::*;
 pub use nsid::*;
+pub use pattern::*;
+#[cfg(feature = "hot-reload")]
+pub use reload::*;
+pub use rules::*;
+pub use secondary::*;
+#[cfg(feature = "serde")]
+pub use serialization::*;
+pub use small::*;
+pub use sync::*;
 
-use ahash::{AHashMap, AHashSet};
+/// Re-exports for macros to use. Not public API.
+#[doc(hidden)]
+pub mod __private {
+  pub use once_cell::sync::Lazy;
+}
+
+use ahash::{AHashMap, AHashSet, RandomState};
 use id_arena::{Arena, ArenaBehavior, DefaultArenaBehavior};
-use std::{fmt::Debug, hash::Hash, marker::PhantomData, ops::Index};
+use std::{
+  collections::{BTreeMap, HashMap, HashSet},
+  fmt::Debug,
+  hash::{BuildHasher, Hash},
+  marker::PhantomData,
+  ops::Index,
+};
 
 type ArenaID<T> = <DefaultArenaBehavior<T> as ArenaBehavior>::Id;
+/// Unregistered entries leave a `None` behind. Arena slots are never reused,
+/// so a stale handle can always tell its entry is gone instead of finding some other entry.
+type EntryArena<T, K> = Arena<Option<(T, K)>, DefaultArenaBehavior<T>>;
+type CategoryID<T> = ArenaID<CatWrapper<T>>;
+type RegisterHook<T, K> = Box<dyn FnMut(K, &T) + Send + Sync>;
+type CategoryHook<K> = Box<dyn FnMut(K, K, CategoryChange) + Send + Sync>;
+type CategoryArena<T, K, S> =
+  Arena<(HashSet<ArenaID<T>, S>, K), DefaultArenaBehavior<CatWrapper<T>>>;
 
-pub struct Registry<T> {
-  arena: Arena<(T, NamespacedID), DefaultArenaBehavior<T>>,
-  nsid_map: AHashMap<NamespacedID, ArenaID<T>>,
+/// A collection of `T`s, each one named by a unique key.
+///
+/// The key is almost always a [`NamespacedID`], but anything implementing [`RegistryKey`] works.
+///
+/// `S` is the hasher for all the maps inside. The default is seeded randomly, so if you need
+/// the same iteration order of internal maps every run (for replays or fuzzing),
+/// use something like `BuildHasherDefault<SomeHasher>` instead.
+pub struct Registry<T, K = NamespacedID, S = RandomState> {
+  arena: EntryArena<T, K>,
+  nsid_map: HashMap<K, ArenaID<T>, S>,
+  /// Every entry in each namespace by path, for keys that have namespaces.
+  /// Sorted so everything under a path prefix is next to each other.
+  namespace_index: HashMap<&'static str, BTreeMap<&'static str, ArenaID<T>>, S>,
 
   /// We LIE and tell it this can accept a thing called a "CatWrapper"
   /// this is to prevent needing horrible ArenaId<AHashSet< ... >>
-  category_arena: Arena<
-    (AHashSet<ArenaID<T>>, NamespacedID),
-    DefaultArenaBehavior<CatWrapper<T>>,
-  >,
-  category_nsid_map: AHashMap<NamespacedID, ArenaID<CatWrapper<T>>>,
+  category_arena: CategoryArena<T, K, S>,
+  category_nsid_map: HashMap<K, ArenaID<CatWrapper<T>>, S>,
+  /// Categories nested directly inside each category. Most categories don't have any,
+  /// so this lives off to the side instead of in the category arena.
+  category_children: HashMap<CategoryID<T>, HashSet<CategoryID<T>, S>, S>,
+  /// Reverse index of which categories each entry is in.
+  /// Only touch category membership through `add_member` and friends so this stays right.
+  entry_categories: HashMap<ArenaID<T>, HashSet<CategoryID<T>, S>, S>,
+  /// Other names for entries, mapped to their real NSIDs. Never points at another alias.
+  aliases: HashMap<K, K, S>,
+  /// Where to look for unregistered NSIDs that were renamed.
+  migrations: Option<MigrationTable<K>>,
+
+  register_hooks: Vec<RegisterHook<T, K>>,
+  category_hooks: Vec<CategoryHook<K>>,
+
+  /// Namespace for `parse_nsid` to use when there isn't one. Only used by NSID registries.
+  default_namespace: Option<String>,
+  /// Numbers for entries, from `apply_id_map`. Only used by NSID registries.
+  sync_ids: Option<sync::SyncIds<T>>,
+  /// Where the keys came from. Only used by `LocalNamespacedID` registries.
+  interner: Option<NsidInterner>,
 }
 
-impl<T> Registry<T> {
+impl<T, K: RegistryKey> Registry<T, K> {
   pub fn new() -> Self {
+    Self::with_capacity(0, 0)
+  }
+
+  /// Make an empty registry with room for this many entries and categories,
+  /// so registering a lot of things up front doesn't keep reallocating.
+  pub fn with_capacity(entries: usize, categories: usize) -> Self {
+    Self::with_capacities(entries, categories)
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Like `with_capacity`, but for a registry with any hasher, which is made with `S::default()`.
+  /// Use `Registry::default()` for an empty one.
+  pub fn with_capacities(entries: usize, categories: usize) -> Self {
     Self {
-      arena: Arena::new(),
-      nsid_map: AHashMap::new(),
+      arena: Arena::with_capacity(entries),
+      nsid_map: HashMap::with_capacity_and_hasher(entries, S::default()),
+      namespace_index: HashMap::default(),
+
+      category_arena: Arena::with_capacity(categories),
+      category_nsid_map: HashMap::with_capacity_and_hasher(
+        categories,
+        S::default(),
+      ),
+      category_children: HashMap::default(),
+      entry_categories: HashMap::default(),
+      aliases: HashMap::default(),
+      migrations: None,
 
-      category_arena: Arena::new(),
-      category_nsid_map: AHashMap::new(),
+      register_hooks: Vec::new(),
+      category_hooks: Vec::new(),
+
+      default_namespace: None,
+      sync_ids: None,
+      interner: None,
     }
   }
 
+  /// Make room in the NSID lookup maps for at least this many more entries and categories.
+  ///
+  /// The arenas the entries themselves are kept in can't reserve more room,
+  /// so use `with_capacity` to size those up front.
+  pub fn reserve(&mut self, entries: usize, categories: usize) {
+    self.nsid_map.reserve(entries);
+    self.category_nsid_map.reserve(categories);
+  }
+
   /// Register something new with this registry.
   pub fn register(
     &mut self,
     entry: T,
-    nsid: NamespacedID,
-  ) -> Result<RegistryHandle<T>, ErrAlreadyRegistered> {
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, ErrAlreadyRegistered> {
+    self.try_register(entry, nsid).map_err(|(_, err)| err)
+  }
+
+  /// Register something new with this registry, handing the entry back if the NSID was already taken.
+  pub fn try_register(
+    &mut self,
+    entry: T,
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, (T, ErrAlreadyRegistered)> {
     if self.nsid_map.contains_key(&nsid) {
-      return Err(ErrAlreadyRegistered);
+      return Err((entry, ErrAlreadyRegistered));
+    }
+    Ok(self.register_unchecked(entry, nsid))
+  }
+
+  /// Register a whole batch of things at once, or none of them.
+  ///
+  /// If any NSID is already taken, or shows up more than once in the batch, nothing is registered
+  /// and every NSID that clashed is reported. Otherwise, returns the handles in the same order.
+  pub fn register_many(
+    &mut self,
+    entries: impl IntoIterator<Item = (K, T)>,
+  ) -> Result<Vec<RegistryHandle<T, K>>, BatchError<K>> {
+    let entries: Vec<_> = entries.into_iter().collect();
+    let mut seen = AHashSet::with_capacity(entries.len());
+    let mut reported = AHashSet::new();
+    let mut conflicts = Vec::new();
+    for (nsid, _) in &entries {
+      if (self.nsid_map.contains_key(nsid) || !seen.insert(*nsid))
+        && reported.insert(*nsid)
+      {
+        conflicts.push(*nsid);
+      }
+    }
+    if !conflicts.is_empty() {
+      return Err(BatchError { conflicts });
+    }
+
+    self.reserve(entries.len(), 0);
+    Ok(
+      entries
+        .into_iter()
+        .map(|(nsid, entry)| self.register_unchecked(entry, nsid))
+        .collect(),
+    )
+  }
+
+  /// Get the handle of whatever's registered under this NSID, registering what `f` makes if there's nothing yet.
+  ///
+  /// Handy for plugin setup code that might run more than once. `f` is only called if it's needed.
+  pub fn get_or_register(
+    &mut self,
+    nsid: K,
+    f: impl FnOnce() -> T,
+  ) -> RegistryHandle<T, K> {
+    self.entry(nsid).or_register_with(f)
+  }
+
+  /// Get at whatever's registered under this NSID (or an alias or old name of it), to look at it or register something there.
+  ///
+  /// Like `lookup_by_nsid`, finding something under an old name tells the migration table's `on_migrate` callback.
+  pub fn entry(&mut self, nsid: K) -> Entry<'_, T, K, S> {
+    match self.resolve_nsid(nsid) {
+      Some((id, _)) => Entry::Occupied(OccupiedEntry::new(self, id)),
+      None => Entry::Vacant(VacantEntry::new(self, nsid)),
     }
-    let id = self.arena.alloc((entry, nsid));
+  }
+
+  /// Register something under an NSID that's already known to be free.
+  fn register_unchecked(&mut self, entry: T, nsid: K) -> RegistryHandle<T, K> {
+    let id = self.arena.alloc(Some((entry, nsid)));
     let handle = RegistryHandle::new(id, nsid);
     self.nsid_map.insert(nsid, handle.id);
+    if let Some((namespace, path)) = nsid.namespace_and_path() {
+      self
+        .namespace_index
+        .entry(namespace)
+        .or_default()
+        .insert(path, id);
+    }
 
-    Ok(handle)
+    if !self.register_hooks.is_empty() {
+      // Not `self.slot`, so this only borrows the arena
+      let entry = &self.arena.get(id).unwrap().as_ref().unwrap().0;
+      for hook in self.register_hooks.iter_mut() {
+        hook(nsid, entry);
+      }
+    }
+
+    handle
   }
 
   /// Create a new category with the specified elements.
@@ -56,25 +271,201 @@ impl<T> Registry<T> {
   /// Duplicates in the iterator are ignored.
   pub fn register_category(
     &mut self,
-    nsid: NamespacedID,
-    entries: impl IntoIterator<Item = RegistryHandle<T>>,
-  ) -> Result<CategoryHandle<T>, ErrCategoryAlreadyRegistered> {
+    nsid: K,
+    entries: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
     if self.category_nsid_map.contains_key(&nsid) {
       return Err(ErrCategoryAlreadyRegistered);
     }
-    let set = entries.into_iter().map(|handle| handle.id).collect();
-    let id = self.category_arena.alloc((set, nsid));
+    let members: Vec<_> = entries
+      .into_iter()
+      .map(|handle| self.live_id(handle))
+      .collect();
+    let id = self.category_arena.alloc((
+      HashSet::with_capacity_and_hasher(members.len(), S::default()),
+      nsid,
+    ));
+    for member in members {
+      self.add_member(id, member);
+    }
     let handle = CategoryHandle::new(id, nsid);
     self.category_nsid_map.insert(nsid, handle.id);
 
     Ok(handle)
   }
 
+  /// Move everything in `other` into this registry.
+  ///
+  /// If any of `other`'s NSIDs or aliases are already taken here, nothing is merged
+  /// and every one of the colliding NSIDs is returned.
+  /// Otherwise, returns the NSID and new handle of every moved entry.
+  ///
+  /// Categories in `other` come along too; if this registry already has a category with the same NSID,
+  /// the members are added to it. Nothing is merged if that would nest categories in a loop.
+  #[allow(clippy::type_complexity)]
+  pub fn try_merge(
+    &mut self,
+    other: Registry<T, K, S>,
+  ) -> Result<Vec<(K, RegistryHandle<T, K>)>, MergeError<K>> {
+    let remap = self.merge(other, MergePolicy::ErrorOnConflict)?;
+    let mut moved: Vec<_> = remap
+      .into_iter()
+      .map(|(_, handle)| (handle.nsid, handle))
+      .collect();
+    // New entries get allocated in order, so this is the order they were in `other`
+    moved.sort_by_key(|(_, handle)| handle.id);
+    Ok(moved)
+  }
+
+  /// Move everything in `other` into this registry, deciding what to do about NSIDs registered in both with `policy`.
+  ///
+  /// Returns a table from each of `other`'s handles to the handle of wherever its entry ended up here.
+  /// With [`MergePolicy::ErrorOnConflict`], nothing is merged if there are any collisions,
+  /// and every one of the colliding NSIDs is returned.
+  ///
+  /// Categories in `other` come along too; if this registry already has a category with the same NSID,
+  /// the members are added to it. Whatever the policy, nothing is merged if the categories
+  /// from both would end up nested in a loop.
+  ///
+  /// So do aliases, which collide like entries do, except that an alias never replaces an entry.
+  /// `other`'s migrations are added to this registry's; if the two disagree about where an NSID
+  /// went, or the renames would go in a loop, nothing is merged whatever the policy.
+  #[allow(clippy::type_complexity)]
+  pub fn merge(
+    &mut self,
+    other: Registry<T, K, S>,
+    policy: MergePolicy,
+  ) -> Result<AHashMap<RegistryHandle<T, K>, RegistryHandle<T, K>>, MergeError<K>>
+  {
+    if policy == MergePolicy::ErrorOnConflict {
+      let taken = |nsid: &K| {
+        self.nsid_map.contains_key(nsid) || self.aliases.contains_key(nsid)
+      };
+      let mut conflicts: Vec<_> = other
+        .entries()
+        .map(|(_, (_, nsid))| *nsid)
+        .filter(|nsid| taken(nsid))
+        .collect();
+      // The same alias on both sides is fine
+      conflicts.extend(
+        other
+          .aliases
+          .iter()
+          .filter(|(alias, target)| {
+            taken(alias) && self.aliases.get(alias) != Some(target)
+          })
+          .map(|(alias, _)| *alias),
+      );
+      if !conflicts.is_empty() {
+        return Err(MergeError::Conflicts(conflicts));
+      }
+    }
+    if let Some(cycle) = self.find_merged_nesting_cycle(&other) {
+      return Err(MergeError::Cycle(ErrCategoryCycle { cycle }));
+    }
+    let migrations = match (self.migrations.clone(), other.migrations) {
+      (Some(mut merged), Some(theirs)) => {
+        let existing: AHashMap<K, K> = merged.iter().collect();
+        for (old, new) in theirs.iter() {
+          if existing.get(&old) != Some(&new) {
+            merged.add(old, new).map_err(MergeError::Migration)?;
+          }
+        }
+        Some(merged)
+      }
+      (mine, theirs) => mine.or(theirs),
+    };
+
+    let mut remap = AHashMap::with_capacity(other.nsid_map.len());
+    let mut handles = AHashMap::with_capacity(other.nsid_map.len());
+    let live = other
+      .arena
+      .into_iter()
+      .filter_map(|(id, slot)| Some((id, slot?)));
+    for (old_id, (entry, nsid)) in live {
+      let id = match self.nsid_map.get(&nsid) {
+        Some(&id) => {
+          if policy == MergePolicy::Overwrite {
+            self.slot_mut(id).0 = entry;
+          }
+          id
+        }
+        None => self.register(entry, nsid).unwrap().id,
+      };
+      remap.insert(old_id, id);
+      handles.insert(
+        RegistryHandle::new(old_id, nsid),
+        RegistryHandle::new(id, nsid),
+      );
+    }
+
+    let mut category_remap =
+      AHashMap::with_capacity(other.category_arena.len());
+    for (old_id, (set, nsid)) in other.category_arena {
+      let id = match self.category_nsid_map.get(&nsid) {
+        Some(id) => *id,
+        None => {
+          let id = self.category_arena.alloc((
+            HashSet::with_capacity_and_hasher(set.len(), S::default()),
+            nsid,
+          ));
+          self.category_nsid_map.insert(nsid, id);
+          id
+        }
+      };
+      for old_member in set {
+        self.add_member(id, remap[&old_member]);
+      }
+      category_remap.insert(old_id, id);
+    }
+    for (old_parent, old_children) in other.category_children {
+      let children = old_children.iter().map(|old| category_remap[old]);
+      self
+        .category_children
+        .entry(category_remap[&old_parent])
+        .or_default()
+        .extend(children);
+    }
+    for (alias, target) in other.aliases {
+      // Whatever's registered under an alias wins anyway
+      if self.nsid_map.contains_key(&alias) {
+        continue;
+      }
+      if policy == MergePolicy::Overwrite || !self.aliases.contains_key(&alias)
+      {
+        self.aliases.insert(alias, target);
+      }
+    }
+    self.migrations = migrations;
+
+    Ok(handles)
+  }
+
+  /// Look for a loop in the nesting both registries' categories would have together, going by NSID.
+  fn find_merged_nesting_cycle(&self, other: &Self) -> Option<Vec<K>> {
+    let mut graph: AHashMap<K, Vec<K>> = AHashMap::new();
+    for registry in [self, other] {
+      let nsid_of = |id| registry.category_arena.get(id).unwrap().1;
+      for (parent, children) in &registry.category_children {
+        graph
+          .entry(nsid_of(*parent))
+          .or_default()
+          .extend(children.iter().map(|child| nsid_of(*child)));
+      }
+    }
+    // Either side was fine on its own, so any loop goes through one of `other`'s categories
+    let mut done = AHashSet::new();
+    let mut path = Vec::new();
+    other.category_arena.iter().find_map(|(_, (_, nsid))| {
+      find_cycle(&graph, *nsid, &mut done, &mut path)
+    })
+  }
+
   /// Create a new empty category.
   pub fn register_empty_category(
     &mut self,
-    nsid: NamespacedID,
-  ) -> Result<CategoryHandle<T>, ErrCategoryAlreadyRegistered> {
+    nsid: K,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
     self.register_category(nsid, std::iter::empty())
   }
 
@@ -83,260 +474,2987 @@ impl<T> Registry<T> {
   /// Duplicates are ignored.
   pub fn insert_into_category(
     &mut self,
-    category: CategoryHandle<T>,
-    entry: RegistryHandle<T>,
+    category: CategoryHandle<T, K>,
+    entry: RegistryHandle<T, K>,
   ) {
-    let set = &mut self.category_arena.get_mut(category.id).unwrap().0;
-    set.insert(entry.id);
+    let id = self.live_id(entry);
+    self.add_member(category.id, id);
   }
 
   /// Insert many elements into this category. Duplicates are ignored.
   pub fn insert_many_into_category(
     &mut self,
-    category: CategoryHandle<T>,
-    entries: impl IntoIterator<Item = RegistryHandle<T>>,
+    category: CategoryHandle<T, K>,
+    entries: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) {
+    let ids: Vec<_> = entries.into_iter().map(|e| self.live_id(e)).collect();
+    for id in ids {
+      self.add_member(category.id, id);
+    }
+  }
+
+  /// Reserve space for at least `additional` more elements in this category.
+  pub fn reserve_in_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    additional: usize,
   ) {
     let set = &mut self.category_arena.get_mut(category.id).unwrap().0;
-    set.extend(entries.into_iter().map(|e| e.id));
+    set.reserve(additional);
   }
 
   /// Remove the given element from this category. Return whether it was actually removed or not.
   pub fn remove_from_category(
     &mut self,
-    category: CategoryHandle<T>,
-    entry: RegistryHandle<T>,
+    category: CategoryHandle<T, K>,
+    entry: RegistryHandle<T, K>,
   ) -> bool {
-    let set = &mut self.category_arena.get_mut(category.id).unwrap().0;
-    set.remove(&entry.id)
+    self.remove_member(category.id, entry.id)
   }
 
-  /// Look up something from its handle.
+  /// Replace everything in this category with the elements with the given NSIDs,
+  /// like when restoring a `snapshot_category`.
   ///
-  /// Because we have its handle, we know that we will always be able to get whatever it is out of the registry,
-  /// so we return `&T` directly instead of an `Option<&T>`.
-  pub fn lookup(&self, handle: RegistryHandle<T>) -> &T {
-    &self.arena.get(handle.id).unwrap().0
+  /// NSIDs are looked up like `validate_nsid`, so aliases and migrations are followed.
+  /// If any of the NSIDs aren't registered, the category is left alone and all the unknown ones are returned.
+  pub fn set_category_members_by_nsid(
+    &mut self,
+    category: CategoryHandle<T, K>,
+    nsids: impl IntoIterator<Item = K>,
+  ) -> Result<(), Vec<K>> {
+    let mut members = Vec::new();
+    let mut unknown = Vec::new();
+    for nsid in nsids {
+      match self.validate_nsid(nsid) {
+        Some(handle) => members.push(handle.id),
+        None => unknown.push(nsid),
+      }
+    }
+    if !unknown.is_empty() {
+      return Err(unknown);
+    }
+    self.clear_members(category.id);
+    for member in members {
+      self.add_member(category.id, member);
+    }
+    Ok(())
   }
 
-  /// Look up whatever NSID is associated with the handle..
+  /// Empty out this category, returning everything that used to be in it.
   ///
-  /// Because we have its handle, we know that we will always be able to get whatever it is out of the registry,
-  /// so we return `&T` directly instead of an `Option<&T>`.
-  pub fn get_nsid(&self, handle: RegistryHandle<T>) -> NamespacedID {
-    self.arena.get(handle.id).unwrap().1
+  /// The category itself stays registered, and so do all the elements.
+  pub fn drain_category(
+    &mut self,
+    category: CategoryHandle<T, K>,
+  ) -> Vec<RegistryHandle<T, K>> {
+    let set = self.clear_members(category.id);
+    set
+      .into_iter()
+      .map(|id| RegistryHandle::new(id, self.slot(id).1))
+      .collect()
   }
 
-  /// Look up something by a NSID, which may or may not actually be in here.
-  pub fn lookup_by_nsid(&self, nsid: NamespacedID) -> Option<&T> {
-    let id = self.nsid_map.get(&nsid)?;
-    Some(&self.arena.get(*id).unwrap().0)
+  /// Remove an entry from this registry, along with all of its category memberships, and hand it back.
+  ///
+  /// Its NSID is free to be registered again afterwards.
+  /// Every handle to the old entry goes stale: `is_live` returns false for it,
+  /// and `lookup` panics instead of finding whatever got registered next.
+  ///
+  /// Returns `None` if it was already unregistered.
+  pub fn unregister(&mut self, handle: RegistryHandle<T, K>) -> Option<T> {
+    let (entry, nsid) = self.arena.get_mut(handle.id)?.take()?;
+    self.nsid_map.remove(&nsid);
+    if let Some(sync_ids) = &mut self.sync_ids {
+      sync_ids.forget(handle.id);
+    }
+    if let Some((namespace, path)) = nsid.namespace_and_path() {
+      let ids = self.namespace_index.get_mut(namespace).unwrap();
+      ids.remove(path);
+      if ids.is_empty() {
+        self.namespace_index.remove(namespace);
+      }
+    }
+    let categories = self.entry_categories.remove(&handle.id);
+    for category in categories.into_iter().flatten() {
+      let (set, category_nsid) = self.category_arena.get_mut(category).unwrap();
+      set.remove(&handle.id);
+      let category_nsid = *category_nsid;
+      self.notify_category(category_nsid, nsid, CategoryChange::Removed);
+    }
+    Some(entry)
   }
 
-  /// If this is a known NSID, turn it into a real `RegistryHandle`.
-  pub fn validate_nsid(&self, nsid: NamespacedID) -> Option<RegistryHandle<T>> {
-    let id = self.nsid_map.get(&nsid)?;
-    Some(RegistryHandle::new(*id, nsid))
+  /// Unregister everything `keep` returns false for, and hand back what was removed.
+  ///
+  /// Everything removed is taken out of its categories, just like with `unregister`.
+  pub fn retain(&mut self, mut keep: impl FnMut(K, &T) -> bool) -> Vec<(K, T)> {
+    let doomed: Vec<_> = self
+      .iter()
+      .filter(|(x, handle)| !keep(handle.get_nsid(), x))
+      .map(|(_, handle)| handle)
+      .collect();
+    doomed
+      .into_iter()
+      .map(|handle| (handle.get_nsid(), self.unregister(handle).unwrap()))
+      .collect()
   }
 
-  /// Iterate over everything in this registry.
-  pub fn iter(&self) -> impl Iterator<Item = (&T, RegistryHandle<T>)> {
-    self
-      .arena
-      .iter()
-      .map(|(id, (x, nsid))| (x, RegistryHandle::new(id, *nsid)))
+  /// Unregister everything, and hand it all back.
+  ///
+  /// The categories are still there afterwards, but empty.
+  pub fn drain(&mut self) -> impl Iterator<Item = (K, T)> {
+    self.retain(|_, _| false).into_iter()
   }
 
-  /// Look up all the elements in the given category.
-  pub fn lookup_category(
-    &self,
-    category: CategoryHandle<T>,
-  ) -> impl Iterator<Item = (&T, RegistryHandle<T>)> {
-    let set = &self.category_arena.get(category.id).unwrap().0;
-    set.iter().map(|id| {
-      let (out, nsid) = self.arena.get(*id).unwrap();
-      (out, RegistryHandle::new(*id, *nsid))
-    })
+  /// Call `hook` with the NSID and value of everything registered from now on, right after it's registered.
+  ///
+  /// Handy for keeping some other index in sync with the registry.
+  pub fn on_register(
+    &mut self,
+    hook: impl FnMut(K, &T) + Send + Sync + 'static,
+  ) {
+    self.register_hooks.push(Box::new(hook));
   }
 
-  /// Look up all the elements in the given category by its NSID.
+  /// Call `hook` every time something is added to or removed from a category from now on,
+  /// with the NSID of the category, the NSID of the entry, and which way it went.
   ///
-  /// Returns `None` if that wasn't a recognized NSID.
-  pub fn lookup_category_by_nsid(
-    &self,
-    nsid: NamespacedID,
-  ) -> Option<impl Iterator<Item = (&T, RegistryHandle<T>)>> {
-    let id = self.category_nsid_map.get(&nsid)?;
-    let set = &self.category_arena.get(*id).unwrap().0;
-    Some(set.iter().map(move |id| {
-      let (out, _) = self.arena.get(*id).unwrap();
-      (out, RegistryHandle::new(*id, nsid))
-    }))
+  /// This includes entries being taken out of their categories when they're unregistered.
+  pub fn on_category_change(
+    &mut self,
+    hook: impl FnMut(K, K, CategoryChange) + Send + Sync + 'static,
+  ) {
+    self.category_hooks.push(Box::new(hook));
   }
 
-  /// If this is a known NSID for a category, turn it into a real `CategoryHandle`.
-  pub fn validate_category_nsid(
-    &self,
-    nsid: NamespacedID,
-  ) -> Option<CategoryHandle<T>> {
-    let id = self.category_nsid_map.get(&nsid)?;
-    Some(CategoryHandle::new(*id, nsid))
+  /// Return if the entry this handle points to is still registered.
+  pub fn is_live(&self, handle: RegistryHandle<T, K>) -> bool {
+    matches!(self.arena.get(handle.id), Some(Some(_)))
   }
 
-  /// Return if this entry is of the given category.
-  pub fn is_in_category(
-    &self,
-    entry: RegistryHandle<T>,
-    category: CategoryHandle<T>,
-  ) -> bool {
-    let set = &self.category_arena.get(category.id).unwrap().0;
-    set.contains(&entry.id)
+  /// Look up something from its handle.
+  ///
+  /// Because we have its handle, we know that we will always be able to get whatever it is out of the registry,
+  /// so we return `&T` directly instead of an `Option<&T>`.
+  ///
+  /// Panics if the entry has been unregistered since; use `is_live` to check first.
+  pub fn lookup(&self, handle: RegistryHandle<T, K>) -> &T {
+    &self.slot(handle.id).0
   }
-}
 
-impl<T: Default> Registry<T> {
-  /// Register something new from this registry that we can auto-generate.
-  /// This is handy for things that have no interesting info other than their identity.
-  pub fn register_default(
-    &mut self,
-    nsid: NamespacedID,
-  ) -> Result<RegistryHandle<T>, ErrAlreadyRegistered> {
-    self.register(Default::default(), nsid)
+  /// Turn an index from `RegistryHandle::to_raw` back into a handle.
+  ///
+  /// Returns `None` if nothing was ever registered at that index, or if it's been unregistered.
+  pub fn handle_from_raw(&self, raw: u32) -> Option<RegistryHandle<T, K>> {
+    let id = self.id_at(raw as usize);
+    let (_, nsid) = self.arena.get(id)?.as_ref()?;
+    Some(RegistryHandle::new(id, *nsid))
   }
-}
 
-impl<T> Default for Registry<T> {
-  fn default() -> Self {
-    Self::new()
+  /// Look up something from its handle, mutably.
+  ///
+  /// Panics if the entry has been unregistered, just like `lookup`.
+  pub fn lookup_mut(&mut self, handle: RegistryHandle<T, K>) -> &mut T {
+    &mut self.slot_mut(handle.id).0
   }
-}
 
-/// Convenience for `lookup`
-impl<T> Index<RegistryHandle<T>> for Registry<T> {
-  type Output = T;
+  /// Swap out the value behind a handle, and hand back the old one.
+  ///
+  /// The NSID, the handle, and its category memberships all stay the same.
+  pub fn replace(&mut self, handle: RegistryHandle<T, K>, entry: T) -> T {
+    std::mem::replace(self.lookup_mut(handle), entry)
+  }
 
-  fn index(&self, index: RegistryHandle<T>) -> &Self::Output {
-    self.lookup(index)
+  /// Change the value behind a handle in place, and return whatever `f` does.
+  ///
+  /// Like `replace`, this doesn't touch the NSID or category memberships.
+  pub fn update<R>(
+    &mut self,
+    handle: RegistryHandle<T, K>,
+    f: impl FnOnce(&mut T) -> R,
+  ) -> R {
+    f(self.lookup_mut(handle))
   }
-}
 
-/// Convenience for `lookup_by_nsid`. Unwraps if it can't find it.
-impl<T> Index<NamespacedID> for Registry<T> {
-  type Output = T;
+  /// Look up whatever NSID is associated with the handle..
+  ///
+  /// Because we have its handle, we know that we will always be able to get whatever it is out of the registry,
+  /// so we return `&T` directly instead of an `Option<&T>`.
+  pub fn get_nsid(&self, handle: RegistryHandle<T, K>) -> K {
+    self.slot(handle.id).1
+  }
 
-  fn index(&self, index: NamespacedID) -> &Self::Output {
-    self.lookup_by_nsid(index).unwrap_or_else(|| {
-      panic!(
-        "couldn't find the NSID `{}` in the {} registry",
-        &index,
-        std::any::type_name::<T>()
-      )
-    })
+  /// Return if this handle points at an entry that's still registered, under the NSID the handle has.
+  ///
+  /// Entries can't be renamed, so for a handle from this registry this is the same as `is_live`.
+  /// It doesn't panic on stale handles, and it also catches most handles from some other registry.
+  pub fn handle_nsid_is_current(&self, handle: RegistryHandle<T, K>) -> bool {
+    match self.arena.get(handle.id) {
+      Some(Some((_, nsid))) => *nsid == handle.nsid,
+      _ => false,
+    }
   }
-}
 
-/// Super super convenience impl for `lookup_by_nsid`, turning the str into an NSID
-impl<'a, T> Index<&'a str> for Registry<T> {
-  type Output = T;
+  /// Look up something by a NSID, which may or may not actually be in here.
+  pub fn lookup_by_nsid(&self, nsid: K) -> Option<&T> {
+    let (id, _) = self.resolve_nsid(nsid)?;
+    Some(&self.slot(id).0)
+  }
 
-  fn index(&self, index: &'a str) -> &Self::Output {
-    let nsid = NamespacedID::new(index).unwrap();
-    &self[nsid]
+  /// Look up something by a NSID, mutably.
+  pub fn lookup_by_nsid_mut(&mut self, nsid: K) -> Option<&mut T> {
+    let (id, _) = self.resolve_nsid(nsid)?;
+    Some(&mut self.slot_mut(id).0)
   }
-}
 
-/// Lightweight handle to an entry in a registry.
-pub struct RegistryHandle<T> {
-  id: ArenaID<T>,
-  nsid: NamespacedID,
-}
+  /// If this is a known NSID, turn it into a real `RegistryHandle`.
+  ///
+  /// If it's an alias, the handle has the real NSID.
+  pub fn validate_nsid(&self, nsid: K) -> Option<RegistryHandle<T, K>> {
+    let (id, nsid) = self.resolve_nsid(nsid)?;
+    Some(RegistryHandle::new(id, nsid))
+  }
 
-impl<T> RegistryHandle<T> {
-  fn new(handle: ArenaID<T>, nsid: NamespacedID) -> Self {
-    Self { id: handle, nsid }
+  /// Let `alias` be used as another name for the entry registered as `target`,
+  /// like for something that got renamed but still shows up under its old name in saves.
+  ///
+  /// `lookup_by_nsid` and `validate_nsid` follow aliases, but `iter` and friends only show the real NSIDs.
+  /// If `target` is an alias itself, the new alias points at what that one does.
+  ///
+  /// The alias goes by NSID, so if the target is unregistered, the alias finds nothing until something
+  /// is registered under that NSID again. If something is ever registered under the alias itself, that wins.
+  pub fn register_alias(
+    &mut self,
+    alias: K,
+    target: K,
+  ) -> Result<RegistryHandle<T, K>, AliasError<K>> {
+    if self.nsid_map.contains_key(&alias) || self.aliases.contains_key(&alias) {
+      return Err(AliasError::AlreadyRegistered(alias));
+    }
+    let handle = self
+      .validate_nsid(target)
+      .ok_or(AliasError::UnknownTarget(target))?;
+    self.aliases.insert(alias, handle.nsid);
+    Ok(handle)
   }
 
-  pub fn get_nsid(&self) -> NamespacedID {
-    self.nsid
+  /// Stop `alias` being another name for anything, handing back the NSID it pointed at.
+  pub fn remove_alias(&mut self, alias: K) -> Option<K> {
+    self.aliases.remove(&alias)
   }
-}
 
-// Manual impls cause it doesn't believe me it doesn't actually own a T
-impl<T> Clone for RegistryHandle<T> {
-  fn clone(&self) -> Self {
-    Self {
-      id: self.id,
-      nsid: self.nsid,
+  /// Iterate over every alias, along with the NSID it points at.
+  pub fn aliases(&self) -> impl Iterator<Item = (K, K)> + '_ {
+    self.aliases.iter().map(|(alias, target)| (*alias, *target))
+  }
+
+  /// Find the entry an NSID refers to, following an alias or migration if there is one.
+  fn resolve_nsid(&self, nsid: K) -> Option<(ArenaID<T>, K)> {
+    self.resolve_alias(nsid).or_else(|| {
+      let migrated = self.migrate_nsid(nsid)?;
+      self.resolve_alias(migrated)
+    })
+  }
+
+  fn resolve_alias(&self, nsid: K) -> Option<(ArenaID<T>, K)> {
+    match self.nsid_map.get(&nsid) {
+      Some(id) => Some((*id, nsid)),
+      None => {
+        let target = *self.aliases.get(&nsid)?;
+        Some((*self.nsid_map.get(&target)?, target))
+      }
     }
   }
-}
 
-impl<T> Copy for RegistryHandle<T> {}
+  /// How many entries are registered, not counting aliases.
+  pub fn len(&self) -> usize {
+    self.nsid_map.len()
+  }
 
-impl<T> Hash for RegistryHandle<T> {
-  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-    self.id.hash(state);
+  pub fn is_empty(&self) -> bool {
+    self.nsid_map.is_empty()
   }
-}
 
-impl<T> PartialEq for RegistryHandle<T> {
-  fn eq(&self, other: &Self) -> bool {
-    self.id == other.id
+  /// How many categories are registered.
+  pub fn category_count(&self) -> usize {
+    self.category_nsid_map.len()
   }
-}
 
-impl<T> Eq for RegistryHandle<T> {}
+  /// Return if something is registered under exactly this NSID.
+  ///
+  /// Unlike `validate_nsid`, this doesn't follow aliases or migrations.
+  pub fn contains_nsid(&self, nsid: K) -> bool {
+    self.nsid_map.contains_key(&nsid)
+  }
 
-impl<T> Debug for RegistryHandle<T> {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.debug_tuple("RegistryHandle").field(&self.nsid).finish()
+  /// Return if a category is registered under this NSID.
+  pub fn contains_category_nsid(&self, nsid: K) -> bool {
+    self.category_nsid_map.contains_key(&nsid)
   }
-}
 
-/// Lightweight handle to a *category* of entries in a registry.
-pub struct CategoryHandle<T> {
-  id: ArenaID<CatWrapper<T>>,
-  nsid: NamespacedID,
-}
+  /// Iterate over everything in this registry.
+  pub fn iter(&self) -> Iter<'_, T, K> {
+    Iter::new(self)
+  }
 
-impl<T> CategoryHandle<T> {
-  fn new(handle: ArenaID<CatWrapper<T>>, nsid: NamespacedID) -> Self {
-    Self { id: handle, nsid }
+  /// Iterate over everything whose NSID is in this namespace, sorted by path.
+  ///
+  /// This goes through an index, so it only visits the entries in that namespace.
+  /// Keys without namespaces (see `RegistryKey::namespace_and_path`) are never in any namespace.
+  pub fn iter_namespace<'a>(
+    &'a self,
+    namespace: &str,
+  ) -> impl Iterator<Item = (&'a T, RegistryHandle<T, K>)> + 'a {
+    self.iter_path_prefix(namespace, "")
   }
 
-  pub fn get_nsid(&self) -> NamespacedID {
-    self.nsid
+  /// Iterate over everything in this namespace whose path starts with `prefix`, sorted by path.
+  ///
+  /// This is a plain string prefix, so to get everything in the `ores` directory, ask for `ores/`
+  /// (`ores` would also find `ores_deepslate/...`).
+  /// Like `iter_namespace`, this goes through a sorted index and only visits what matches.
+  pub fn iter_path_prefix<'a>(
+    &'a self,
+    namespace: &str,
+    prefix: &'a str,
+  ) -> impl Iterator<Item = (&'a T, RegistryHandle<T, K>)> + 'a {
+    self
+      .namespace_index
+      .get(namespace)
+      .into_iter()
+      .flat_map(move |paths| paths.range(prefix..))
+      .take_while(move |(path, _)| path.starts_with(prefix))
+      .map(|(_, id)| {
+        let (x, nsid) = self.slot(*id);
+        (x, RegistryHandle::new(*id, *nsid))
+      })
   }
-}
-impl<T> Clone for CategoryHandle<T> {
-  fn clone(&self) -> Self {
-    Self {
-      id: self.id,
-      nsid: self.nsid,
-    }
+
+  /// Iterate over every namespace that has anything registered in it.
+  pub fn namespaces(&self) -> impl Iterator<Item = &'static str> + '_ {
+    self.namespace_index.keys().copied()
   }
-}
 
-impl<T> Copy for CategoryHandle<T> {}
+  /// Count how many things in this registry match the predicate.
+  pub fn count<F: Fn(&T) -> bool>(&self, pred: F) -> usize {
+    self.entries().filter(|(_, (x, _))| pred(x)).count()
+  }
 
-impl<T> Hash for CategoryHandle<T> {
-  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-    self.id.hash(state);
+  /// Run `f` over everything in this registry and collect whatever it returns `Some` for.
+  pub fn filter_map_values<U, F: Fn(&T, RegistryHandle<T, K>) -> Option<U>>(
+    &self,
+    f: F,
+  ) -> Vec<U> {
+    self.iter().filter_map(|(x, handle)| f(x, handle)).collect()
   }
-}
 
-impl<T> PartialEq for CategoryHandle<T> {
-  fn eq(&self, other: &Self) -> bool {
-    self.id == other.id
+  /// Collect the handle and NSID of everything in this registry, without borrowing any of the values.
+  pub fn handle_nsid_pairs(&self) -> Vec<(RegistryHandle<T, K>, K)> {
+    self
+      .entries()
+      .map(|(id, (_, nsid))| (RegistryHandle::new(id, *nsid), *nsid))
+      .collect()
   }
-}
 
-impl<T> Eq for CategoryHandle<T> {}
+  /// Look up all the elements in the given category.
+  pub fn lookup_category(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> CategoryIter<'_, T, K, S> {
+    CategoryIter::new(self, category.id)
+  }
 
-impl<T> Debug for CategoryHandle<T> {
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.debug_tuple("CategoryHandle").field(&self.nsid).finish()
+  /// Iterate over every category, along with its NSID and how many members it has (not counting subcategories).
+  pub fn iter_categories(&self) -> Categories<'_, T, K, S> {
+    Categories::new(self)
   }
-}
 
-/// Internal struct to help differentiate handles to the arena itself and to the category arena.
-struct CatWrapper<T>(PhantomData<T>);
+  /// Iterate over the NSIDs of every category.
+  pub fn category_nsids(&self) -> impl Iterator<Item = K> + '_ {
+    self.category_arena.iter().map(|(_, (_, nsid))| *nsid)
+  }
+
+  /// How many elements are in the given category.
+  pub fn category_len(&self, category: CategoryHandle<T, K>) -> usize {
+    self.category_arena.get(category.id).unwrap().0.len()
+  }
+
+  /// Look up all the elements in the given category by its NSID.
+  ///
+  /// Returns `None` if that wasn't a recognized NSID.
+  pub fn lookup_category_by_nsid(
+    &self,
+    nsid: K,
+  ) -> Option<CategoryIter<'_, T, K, S>> {
+    let id = self.category_nsid_map.get(&nsid)?;
+    Some(CategoryIter::new(self, *id))
+  }
+
+  /// Look up the values of all the elements in the given category by its NSID.
+  ///
+  /// Returns `None` if that wasn't a recognized NSID.
+  pub fn category_values_by_nsid(
+    &self,
+    nsid: K,
+  ) -> Option<CategoryValues<'_, T, K, S>> {
+    let id = self.category_nsid_map.get(&nsid)?;
+    Some(CategoryValues::new(CategoryIter::new(self, *id)))
+  }
+
+  /// If this is a known NSID for a category, turn it into a real `CategoryHandle`.
+  pub fn validate_category_nsid(
+    &self,
+    nsid: K,
+  ) -> Option<CategoryHandle<T, K>> {
+    let id = self.category_nsid_map.get(&nsid)?;
+    Some(CategoryHandle::new(*id, nsid))
+  }
+
+  /// Return if this entry is of the given category.
+  pub fn is_in_category(
+    &self,
+    entry: RegistryHandle<T, K>,
+    category: CategoryHandle<T, K>,
+  ) -> bool {
+    let set = &self.category_arena.get(category.id).unwrap().0;
+    set.contains(&entry.id)
+  }
+
+  /// Return if this category contains exactly the given entries, no more and no fewer.
+  ///
+  /// Duplicates in the iterator are ignored.
+  pub fn category_equals(
+    &self,
+    category: CategoryHandle<T, K>,
+    expected: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) -> bool {
+    let set = &self.category_arena.get(category.id).unwrap().0;
+    let expected: HashSet<_, S> = expected.into_iter().map(|e| e.id).collect();
+    *set == expected
+  }
+
+  /// Count how many of the given entries are in this category.
+  ///
+  /// Duplicates in the iterator are counted each time they show up.
+  pub fn category_overlap_count(
+    &self,
+    category: CategoryHandle<T, K>,
+    entries: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) -> usize {
+    let set = &self.category_arena.get(category.id).unwrap().0;
+    entries.into_iter().filter(|e| set.contains(&e.id)).count()
+  }
+
+  /// Count how many categories this entry is in.
+  pub fn category_count_containing(
+    &self,
+    entry: RegistryHandle<T, K>,
+  ) -> usize {
+    self
+      .entry_categories
+      .get(&entry.id)
+      .map_or(0, |it| it.len())
+  }
+
+  /// Iterate over every category this entry is in.
+  pub fn categories_of(
+    &self,
+    entry: RegistryHandle<T, K>,
+  ) -> CategoryHandles<'_, T, K, S> {
+    CategoryHandles::new(self, self.entry_categories.get(&entry.id))
+  }
+
+  /// Get the NSIDs of every category each of the given entries is in, like `categories_of` for each one.
+  ///
+  /// Every requested entry gets a key in the returned map, even if it isn't in any categories.
+  pub fn tags_of_many(
+    &self,
+    handles: impl IntoIterator<Item = RegistryHandle<T, K>>,
+  ) -> AHashMap<RegistryHandle<T, K>, Vec<K>> {
+    handles
+      .into_iter()
+      .map(|handle| {
+        let tags = self
+          .categories_of(handle)
+          .map(|category| category.get_nsid())
+          .collect();
+        (handle, tags)
+      })
+      .collect()
+  }
+
+  /// Work out how many entries are in how many categories.
+  ///
+  /// The returned map goes from "number of categories" to "how many entries are in that many categories",
+  /// so the count for `0` is how many entries aren't in any category at all.
+  pub fn member_count_distribution(&self) -> AHashMap<usize, usize> {
+    let mut distribution = AHashMap::new();
+    for (id, _) in self.entries() {
+      let count = self.entry_categories.get(&id).map_or(0, |it| it.len());
+      *distribution.entry(count).or_default() += 1;
+    }
+    distribution
+  }
+
+  /// Count how many distinct elements are in at least one of the given categories,
+  /// without making a new category out of them.
+  pub fn category_union_len(
+    &self,
+    categories: impl IntoIterator<Item = CategoryHandle<T, K>>,
+  ) -> usize {
+    let mut seen = AHashSet::new();
+    for category in categories {
+      let set = &self.category_arena.get(category.id).unwrap().0;
+      seen.extend(set.iter().copied());
+    }
+    seen.len()
+  }
+
+  /// Find the elements that are in every one of the given categories.
+  ///
+  /// Returns an empty set if there aren't any categories.
+  pub fn category_intersection_all(
+    &self,
+    categories: impl IntoIterator<Item = CategoryHandle<T, K>>,
+  ) -> AHashSet<RegistryHandle<T, K>> {
+    let mut sets: Vec<_> = categories
+      .into_iter()
+      .map(|category| &self.category_arena.get(category.id).unwrap().0)
+      .collect();
+    sets.sort_by_key(|set| set.len());
+    let (smallest, rest) = match sets.split_first() {
+      Some(it) => it,
+      None => return AHashSet::new(),
+    };
+    smallest
+      .iter()
+      .filter(|id| rest.iter().all(|set| set.contains(id)))
+      .map(|id| RegistryHandle::new(*id, self.slot(*id).1))
+      .collect()
+  }
+
+  /// Put `child` inside `parent`, so everything in `child` counts as being in `parent`
+  /// when looked up with `lookup_category_recursive`.
+  ///
+  /// Returns whether it was newly inserted. If `parent` is already somewhere inside `child`
+  /// (or they're the same category), nothing changes and the loop it would make is returned as an error.
+  pub fn nest_category(
+    &mut self,
+    parent: CategoryHandle<T, K>,
+    child: CategoryHandle<T, K>,
+  ) -> Result<bool, ErrCategoryCycle<K>> {
+    if let Some(mut path) = self.nesting_path(child.id, parent.id) {
+      path.pop();
+      let cycle = std::iter::once(parent.id)
+        .chain(path)
+        .map(|id| self.category_arena.get(id).unwrap().1)
+        .collect();
+      return Err(ErrCategoryCycle { cycle });
+    }
+    Ok(
+      self
+        .category_children
+        .entry(parent.id)
+        .or_default()
+        .insert(child.id),
+    )
+  }
+
+  /// Find a chain of nested categories going from `from` down to `to`, with both ends included.
+  fn nesting_path(
+    &self,
+    from: CategoryID<T>,
+    to: CategoryID<T>,
+  ) -> Option<Vec<CategoryID<T>>> {
+    let mut path = vec![from];
+    let mut seen = AHashSet::new();
+    self
+      .find_nesting_path(to, &mut path, &mut seen)
+      .then_some(path)
+  }
+
+  fn find_nesting_path(
+    &self,
+    to: CategoryID<T>,
+    path: &mut Vec<CategoryID<T>>,
+    seen: &mut AHashSet<CategoryID<T>>,
+  ) -> bool {
+    let current = *path.last().unwrap();
+    if current == to {
+      return true;
+    }
+    if !seen.insert(current) {
+      return false;
+    }
+    for child in self.category_children.get(&current).into_iter().flatten() {
+      path.push(*child);
+      if self.find_nesting_path(to, path, seen) {
+        return true;
+      }
+      path.pop();
+    }
+    false
+  }
+
+  /// Take `child` back out of `parent`. Returns whether it was actually in there.
+  pub fn unnest_category(
+    &mut self,
+    parent: CategoryHandle<T, K>,
+    child: CategoryHandle<T, K>,
+  ) -> bool {
+    match self.category_children.get_mut(&parent.id) {
+      Some(children) => children.remove(&child.id),
+      None => false,
+    }
+  }
+
+  /// Iterate over the categories nested directly inside this one.
+  pub fn subcategories(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> CategoryHandles<'_, T, K, S> {
+    CategoryHandles::new(self, self.category_children.get(&category.id))
+  }
+
+  /// Look up all the elements in the given category and in every category nested inside it, however deep.
+  ///
+  /// Everything shows up once, even if it's reachable through several categories.
+  /// `nest_category` won't make a loop, but `merge` can, so if the categories nest inside each other
+  /// in a loop, the loop is returned as an error.
+  #[allow(clippy::type_complexity)]
+  pub fn lookup_category_recursive(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> Result<Vec<(&T, RegistryHandle<T, K>)>, ErrCategoryCycle<K>> {
+    let mut members = AHashSet::new();
+    let mut done = AHashSet::new();
+    let mut path = Vec::new();
+    self.collect_nested(category.id, &mut members, &mut done, &mut path)?;
+    Ok(members.into_iter().map(|id| self.member(id)).collect())
+  }
+
+  /// Depth-first walk for `lookup_category_recursive`.
+  ///
+  /// `path` is the chain of categories we're currently inside, to spot loops;
+  /// `done` is every category that's been fully walked, so shared children are only walked once.
+  fn collect_nested(
+    &self,
+    id: CategoryID<T>,
+    members: &mut AHashSet<ArenaID<T>>,
+    done: &mut AHashSet<CategoryID<T>>,
+    path: &mut Vec<CategoryID<T>>,
+  ) -> Result<(), ErrCategoryCycle<K>> {
+    if let Some(start) = path.iter().position(|it| *it == id) {
+      let cycle = path[start..]
+        .iter()
+        .map(|id| self.category_arena.get(*id).unwrap().1)
+        .collect();
+      return Err(ErrCategoryCycle { cycle });
+    }
+    if !done.insert(id) {
+      return Ok(());
+    }
+    members.extend(self.category_arena.get(id).unwrap().0.iter().copied());
+
+    path.push(id);
+    for child in self.category_children.get(&id).into_iter().flatten() {
+      self.collect_nested(*child, members, done, path)?;
+    }
+    path.pop();
+    Ok(())
+  }
+
+  /// Iterate over everything that's in either of these categories, without repeats.
+  pub fn category_union(
+    &self,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> impl Iterator<Item = (&T, RegistryHandle<T, K>)> {
+    let a = &self.category_arena.get(a.id).unwrap().0;
+    let b = &self.category_arena.get(b.id).unwrap().0;
+    let ids = a.iter().chain(b.iter().filter(move |id| !a.contains(id)));
+    ids.map(|id| self.member(*id))
+  }
+
+  /// Iterate over everything that's in both of these categories.
+  pub fn category_intersection(
+    &self,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> impl Iterator<Item = (&T, RegistryHandle<T, K>)> {
+    let a = &self.category_arena.get(a.id).unwrap().0;
+    let b = &self.category_arena.get(b.id).unwrap().0;
+    let (smaller, bigger) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let ids = smaller.iter().filter(move |id| bigger.contains(id));
+    ids.map(|id| self.member(*id))
+  }
+
+  /// Iterate over everything that's in `a` but not in `b`.
+  pub fn category_difference(
+    &self,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> impl Iterator<Item = (&T, RegistryHandle<T, K>)> {
+    let a = &self.category_arena.get(a.id).unwrap().0;
+    let b = &self.category_arena.get(b.id).unwrap().0;
+    let ids = a.iter().filter(move |id| !b.contains(id));
+    ids.map(|id| self.member(*id))
+  }
+
+  /// Create a new category out of everything that's in either of these categories.
+  pub fn register_category_union(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    let members: Vec<_> = self.category_union(a, b).map(|(_, h)| h).collect();
+    self.register_category(nsid, members)
+  }
+
+  /// Create a new category out of everything that's in both of these categories.
+  pub fn register_category_intersection(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    let members: Vec<_> =
+      self.category_intersection(a, b).map(|(_, h)| h).collect();
+    self.register_category(nsid, members)
+  }
+
+  /// Create a new category out of everything that's in `a` but not in `b`.
+  pub fn register_category_difference(
+    &mut self,
+    nsid: K,
+    a: CategoryHandle<T, K>,
+    b: CategoryHandle<T, K>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    let members: Vec<_> =
+      self.category_difference(a, b).map(|(_, h)| h).collect();
+    self.register_category(nsid, members)
+  }
+
+  /// Get the NSIDs of everything in this category.
+  ///
+  /// Unlike handles, NSIDs stay the same across reloads, so this is good for saving a category's contents for later.
+  pub fn snapshot_category(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> AHashSet<K> {
+    self
+      .lookup_category(category)
+      .map(|(_, handle)| handle.nsid)
+      .collect()
+  }
+
+  /// Compare the current members of a category against an earlier snapshot of member NSIDs.
+  ///
+  /// Returns `(added, removed)`: the NSIDs that are in the category now but weren't in `before`,
+  /// and the ones that were in `before` but aren't in the category anymore.
+  pub fn category_member_diff(
+    &self,
+    before: &AHashSet<K>,
+    category: CategoryHandle<T, K>,
+  ) -> (Vec<K>, Vec<K>) {
+    let now: AHashSet<K> = self
+      .lookup_category(category)
+      .map(|(_, handle)| handle.nsid)
+      .collect();
+    let added = now.difference(before).copied().collect();
+    let removed = before.difference(&now).copied().collect();
+    (added, removed)
+  }
+
+  /// Make the ID for this index in the arena, whether or not there's anything there.
+  fn id_at(&self, idx: usize) -> ArenaID<T> {
+    let arena_id = DefaultArenaBehavior::arena_id(self.arena.next_id());
+    DefaultArenaBehavior::new_id(arena_id, idx)
+  }
+
+  /// Get a live entry out of the arena.
+  fn slot(&self, id: ArenaID<T>) -> &(T, K) {
+    self
+      .arena
+      .get(id)
+      .unwrap()
+      .as_ref()
+      .expect("used a handle to an entry that was unregistered")
+  }
+
+  fn slot_mut(&mut self, id: ArenaID<T>) -> &mut (T, K) {
+    self
+      .arena
+      .get_mut(id)
+      .unwrap()
+      .as_mut()
+      .expect("used a handle to an entry that was unregistered")
+  }
+
+  /// Put an entry in a category. Returns whether it was newly added.
+  fn add_member(&mut self, category: CategoryID<T>, id: ArenaID<T>) -> bool {
+    let added = self.category_arena.get_mut(category).unwrap().0.insert(id);
+    if added {
+      self
+        .entry_categories
+        .entry(id)
+        .or_default()
+        .insert(category);
+      self.notify_member(category, id, CategoryChange::Added);
+    }
+    added
+  }
+
+  /// Take an entry out of a category. Returns whether it was actually in there.
+  fn remove_member(&mut self, category: CategoryID<T>, id: ArenaID<T>) -> bool {
+    let removed = self.category_arena.get_mut(category).unwrap().0.remove(&id);
+    if removed {
+      if let Some(categories) = self.entry_categories.get_mut(&id) {
+        categories.remove(&category);
+      }
+      self.notify_member(category, id, CategoryChange::Removed);
+    }
+    removed
+  }
+
+  /// Empty out a category, returning what used to be in it.
+  fn clear_members(
+    &mut self,
+    category: CategoryID<T>,
+  ) -> HashSet<ArenaID<T>, S> {
+    let set =
+      std::mem::take(&mut self.category_arena.get_mut(category).unwrap().0);
+    for id in &set {
+      if let Some(categories) = self.entry_categories.get_mut(id) {
+        categories.remove(&category);
+      }
+      self.notify_member(category, *id, CategoryChange::Removed);
+    }
+    set
+  }
+
+  fn notify_member(
+    &mut self,
+    category: CategoryID<T>,
+    id: ArenaID<T>,
+    change: CategoryChange,
+  ) {
+    if !self.category_hooks.is_empty() {
+      let category_nsid = self.category_arena.get(category).unwrap().1;
+      let entry_nsid = self.slot(id).1;
+      self.notify_category(category_nsid, entry_nsid, change);
+    }
+  }
+
+  fn notify_category(
+    &mut self,
+    category_nsid: K,
+    entry_nsid: K,
+    change: CategoryChange,
+  ) {
+    for hook in self.category_hooks.iter_mut() {
+      hook(category_nsid, entry_nsid, change);
+    }
+  }
+
+  fn category_handle(&self, id: CategoryID<T>) -> CategoryHandle<T, K> {
+    CategoryHandle::new(id, self.category_arena.get(id).unwrap().1)
+  }
+
+  fn subcategory_nsids(&self, id: CategoryID<T>) -> AHashSet<K> {
+    let children = self.category_children.get(&id).into_iter().flatten();
+    children
+      .map(|child| self.category_arena.get(*child).unwrap().1)
+      .collect()
+  }
+
+  /// Get a category member's value and handle.
+  fn member(&self, id: ArenaID<T>) -> (&T, RegistryHandle<T, K>) {
+    let (out, nsid) = self.slot(id);
+    (out, RegistryHandle::new(id, *nsid))
+  }
+
+  /// Iterate over the live entries in the arena, skipping unregistered ones.
+  fn entries(&self) -> impl Iterator<Item = (ArenaID<T>, &(T, K))> {
+    self
+      .arena
+      .iter()
+      .filter_map(|(id, slot)| Some((id, slot.as_ref()?)))
+  }
+
+  /// Get a handle's ID, panicking if it's stale, so dead entries don't sneak into categories.
+  fn live_id(&self, handle: RegistryHandle<T, K>) -> ArenaID<T> {
+    assert!(
+      self.is_live(handle),
+      "{:?} was unregistered and can't be put in a category",
+      handle
+    );
+    handle.id
+  }
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Set the namespace that `parse_nsid` (and indexing with a `&str`) falls back on for strings without one.
+  pub fn set_default_namespace(
+    &mut self,
+    namespace: &str,
+  ) -> Result<(), InvalidNamespace> {
+    NamespacedID::check_namespace(namespace)?;
+    self.default_namespace = Some(namespace.to_owned());
+    Ok(())
+  }
+
+  /// Get the namespace that `parse_nsid` falls back on, if there is one.
+  pub fn default_namespace(&self) -> Option<&str> {
+    self.default_namespace.as_deref()
+  }
+
+  /// Parse an NSID, using this registry's default namespace if the string doesn't have one.
+  ///
+  /// If there's no default namespace, this is just like `NamespacedID::new`.
+  pub fn parse_nsid(&self, s: &str) -> Result<NamespacedID, NSIDParseError> {
+    match &self.default_namespace {
+      Some(namespace) => {
+        NamespacedID::parse_with_default_namespace(s, namespace)
+      }
+      None => s.parse(),
+    }
+  }
+
+  /// Parse a string with `parse_nsid` and look it up.
+  pub fn lookup_by_str(&self, s: &str) -> Result<&T, LookupError> {
+    let nsid = self.parse_nsid(s)?;
+    self
+      .lookup_by_nsid(nsid)
+      .ok_or(LookupError::NotRegistered(nsid))
+  }
+
+  /// Write out a Graphviz DOT graph of this registry, with an edge from each category to each of its members.
+  ///
+  /// Categories are drawn as boxes and named like `#namespace:path` so they can't clash with entries.
+  /// Categories nested inside another one get a dashed edge from the outer one.
+  /// Everything is written in NSID order, so the same registry makes the same graph.
+  pub fn export_dot<W: std::io::Write>(
+    &self,
+    w: &mut W,
+  ) -> std::io::Result<()> {
+    let (entries, categories) = self.export_all();
+    let entry = |nsid: &NamespacedID| dot_escape(&nsid.to_string());
+    let category = |nsid: &NamespacedID| dot_escape(&format!("#{}", nsid));
+    writeln!(w, "digraph registry {{")?;
+    for (nsid, _) in &entries {
+      writeln!(w, "  \"{}\";", entry(nsid))?;
+    }
+    for (nsid, members) in &categories {
+      writeln!(w, "  \"{}\" [shape=box];", category(nsid))?;
+      for member in members {
+        writeln!(w, "  \"{}\" -> \"{}\";", category(nsid), entry(member))?;
+      }
+    }
+    for (nsid, children) in &self.export_subcategories() {
+      for child in children {
+        writeln!(
+          w,
+          "  \"{}\" -> \"{}\" [style=dashed];",
+          category(nsid),
+          category(child)
+        )?;
+      }
+    }
+    writeln!(w, "}}")
+  }
+
+  /// Move every category in the namespace `from` over to the namespace `to`, keeping their paths and members.
+  ///
+  /// If any of the new NSIDs are already taken by another category, nothing is moved.
+  /// Otherwise, returns how many categories were moved.
+  /// Existing `CategoryHandle`s to the moved categories still work, but `get_nsid` on them returns the old NSID.
+  ///
+  /// Nothing is moved if `to` isn't a valid namespace, either.
+  pub fn rename_category_namespace(
+    &mut self,
+    from: &str,
+    to: &str,
+  ) -> Result<usize, RenameNamespaceError> {
+    NamespacedID::check_namespace(to).map_err(NSIDParseError::from)?;
+
+    let moving: Vec<_> = self
+      .category_nsid_map
+      .iter()
+      .filter(|(nsid, _)| nsid.resolve().0 == from)
+      .map(|(nsid, id)| {
+        let renamed =
+          NamespacedID::new_from_parts(to, nsid.resolve().1).unwrap();
+        (*nsid, renamed, *id)
+      })
+      .collect();
+    if from == to {
+      return Ok(moving.len());
+    }
+    if moving
+      .iter()
+      .any(|(_, renamed, _)| self.category_nsid_map.contains_key(renamed))
+    {
+      return Err(ErrCategoryAlreadyRegistered.into());
+    }
+
+    for (old, renamed, id) in &moving {
+      self.category_nsid_map.remove(old);
+      self.category_nsid_map.insert(*renamed, *id);
+      self.category_arena.get_mut(*id).unwrap().1 = *renamed;
+    }
+    Ok(moving.len())
+  }
+
+  /// Turn this NSID into a handle if it's registered; otherwise, try to find what it was a typo of.
+  ///
+  /// Returns `Err(Some(closest))` with the best match from `suggest`,
+  /// or `Err(None)` if nothing registered is close enough to be a likely typo.
+  pub fn validate_or_suggest(
+    &self,
+    nsid: NamespacedID,
+  ) -> Result<RegistryHandle<T>, Option<NamespacedID>> {
+    match self.validate_nsid(nsid) {
+      Some(handle) => Ok(handle),
+      None => Err(self.suggest(nsid, 1).into_iter().next()),
+    }
+  }
+
+  /// Find up to `limit` registered NSIDs that look the most like `query`, closest first, for "did you mean" messages.
+  ///
+  /// `query` can be an NSID or any string, like a typo'd one that didn't parse.
+  /// Something counts as close if it's only a couple of edits away, or if it starts with `query`.
+  /// If `query` has no `:`, it's also compared against just the paths, so `stone` finds `minecraft:stone`.
+  pub fn suggest(
+    &self,
+    query: impl ToString,
+    limit: usize,
+  ) -> Vec<NamespacedID> {
+    let query = query.to_string();
+    let bare = !query.contains(':');
+    let max_distance = suggest::max_typo_distance(&query);
+    let mut found: Vec<_> = self
+      .nsid_map
+      .keys()
+      .filter_map(|candidate| {
+        let candidate_str = candidate.to_string();
+        let mut distance = suggest::edit_distance(&query, &candidate_str);
+        let mut is_prefix = candidate_str.starts_with(&query);
+        if bare {
+          let path = candidate.resolve().1;
+          distance = distance.min(suggest::edit_distance(&query, path));
+          is_prefix |= path.starts_with(&query);
+        }
+        (distance <= max_distance || is_prefix).then_some((
+          distance,
+          candidate_str,
+          *candidate,
+        ))
+      })
+      .collect();
+    found
+      .sort_by(|(d1, s1, _), (d2, s2, _)| d1.cmp(d2).then_with(|| s1.cmp(s2)));
+    found
+      .into_iter()
+      .take(limit)
+      .map(|(_, _, candidate)| candidate)
+      .collect()
+  }
+
+  /// Create a new category out of the NSIDs of its members, like when bringing it over from another registry.
+  ///
+  /// Every member has to already be registered in this registry;
+  /// if any aren't, nothing is created and all the unknown ones are reported.
+  pub fn import_category(
+    &mut self,
+    nsid: NamespacedID,
+    member_nsids: &[NamespacedID],
+  ) -> Result<CategoryHandle<T>, ImportCategoryError> {
+    if self.category_nsid_map.contains_key(&nsid) {
+      return Err(ErrCategoryAlreadyRegistered.into());
+    }
+    let mut members = Vec::with_capacity(member_nsids.len());
+    let mut unknown = Vec::new();
+    for &member in member_nsids {
+      match self.validate_nsid(member) {
+        Some(handle) => members.push(handle),
+        None => unknown.push(member),
+      }
+    }
+    if !unknown.is_empty() {
+      return Err(ImportCategoryError::UnknownMembers(unknown));
+    }
+    Ok(self.register_category(nsid, members)?)
+  }
+
+  /// Insert an element into a category, looking both of them up by NSID.
+  ///
+  /// The element is looked up like `validate_nsid`, so aliases are followed.
+  /// Returns whether the element was newly inserted.
+  pub fn insert_nsid_into_category(
+    &mut self,
+    category_nsid: NamespacedID,
+    entry_nsid: NamespacedID,
+  ) -> Result<bool, InsertByNsidError> {
+    let category = *self
+      .category_nsid_map
+      .get(&category_nsid)
+      .ok_or(InsertByNsidError::UnknownCategory(category_nsid))?;
+    let entry = self
+      .validate_nsid(entry_nsid)
+      .ok_or(InsertByNsidError::UnknownEntry(entry_nsid))?;
+    Ok(self.add_member(category, entry.id))
+  }
+
+  /// Dump everything in this registry: every entry with its NSID, and every category with the NSIDs of its members.
+  ///
+  /// Everything is sorted by NSID (namespace, then path),
+  /// so the output doesn't depend on what order things were registered in.
+  /// Nested categories aren't included; get those from `export_subcategories`.
+  #[allow(clippy::type_complexity)]
+  pub fn export_all(
+    &self,
+  ) -> (
+    Vec<(NamespacedID, &T)>,
+    Vec<(NamespacedID, Vec<NamespacedID>)>,
+  ) {
+    let mut entries: Vec<_> =
+      self.entries().map(|(_, (x, nsid))| (*nsid, x)).collect();
+    entries.sort_by_key(|(nsid, _)| *nsid);
+
+    let mut categories: Vec<_> = self
+      .category_arena
+      .iter()
+      .map(|(_, (set, nsid))| {
+        let mut members: Vec<_> =
+          set.iter().map(|id| self.slot(*id).1).collect();
+        members.sort();
+        (*nsid, members)
+      })
+      .collect();
+    categories.sort_by_key(|(nsid, _)| *nsid);
+
+    (entries, categories)
+  }
+
+  /// Dump how the categories are nested: every category with anything nested directly inside it,
+  /// along with the NSIDs of those categories.
+  ///
+  /// Sorted like `export_all`, so it goes alongside it for a full dump.
+  pub fn export_subcategories(&self) -> Vec<(NamespacedID, Vec<NamespacedID>)> {
+    let mut nesting: Vec<_> = self
+      .category_children
+      .iter()
+      .filter(|(_, children)| !children.is_empty())
+      .map(|(parent, _)| {
+        let mut children: Vec<_> =
+          self.subcategory_nsids(*parent).into_iter().collect();
+        children.sort();
+        (self.category_arena.get(*parent).unwrap().1, children)
+      })
+      .collect();
+    nesting.sort_by_key(|(nsid, _)| *nsid);
+    nesting
+  }
+
+  /// Get up to `n` elements of the given category, sorted by NSID so it's the same every time.
+  ///
+  /// Use `category_len` for the total count.
+  pub fn category_sample(
+    &self,
+    category: CategoryHandle<T>,
+    n: usize,
+  ) -> Vec<(&T, RegistryHandle<T>)> {
+    let mut members: Vec<_> = self.lookup_category(category).collect();
+    members.sort_by_key(|(_, handle)| handle.nsid);
+    members.truncate(n);
+    members
+  }
+}
+
+impl<T: Default, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Register something new from this registry that we can auto-generate.
+  /// This is handy for things that have no interesting info other than their identity.
+  pub fn register_default(
+    &mut self,
+    nsid: K,
+  ) -> Result<RegistryHandle<T, K>, ErrAlreadyRegistered> {
+    self.register(Default::default(), nsid)
+  }
+}
+
+impl<T: Clone, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Clone out the values of everything in the given category.
+  pub fn category_values_cloned(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> Vec<T> {
+    self
+      .lookup_category(category)
+      .map(|(x, _)| x.clone())
+      .collect()
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Default
+  for Registry<T, K, S>
+{
+  fn default() -> Self {
+    Self::with_capacities(0, 0)
+  }
+}
+
+/// Make an independent copy of the registry, with all the same entries, categories, aliases, migrations,
+/// `SyncMap` numbering, and interner.
+///
+/// The copy has its own handles, just like a registry built from scratch would, so handles from the original
+/// don't work in it; look things up again with `validate_nsid`. The same goes for the other direction.
+/// A cloned interner gets its own ID too, so `LocalNamespacedID`s have to be looked up again through it.
+/// Hooks from `on_register` and `on_category_change` can't be cloned, so the copy doesn't have any.
+impl<T: Clone, K: RegistryKey, S: BuildHasher + Default> Clone
+  for Registry<T, K, S>
+{
+  fn clone(&self) -> Self {
+    let mut out = Registry::with_capacities(self.len(), self.category_count());
+    // A cloned interner has its own ID, so keys from it need moving over
+    let interner = self.interner.clone();
+    let rekey = |nsid: K| match (&self.interner, &interner) {
+      (Some(from), Some(to)) => nsid.rebase_interner(from, to),
+      _ => nsid,
+    };
+    let mut remap = AHashMap::with_capacity(self.len());
+    for (id, (x, nsid)) in self.entries() {
+      remap.insert(id, out.register_unchecked(x.clone(), rekey(*nsid)).id);
+    }
+    let mut category_remap = AHashMap::with_capacity(self.category_count());
+    for (id, (set, nsid)) in self.category_arena.iter() {
+      let members = set.iter().map(|member| remap[member]);
+      let new_id = out.category_arena.alloc((
+        HashSet::with_capacity_and_hasher(set.len(), S::default()),
+        rekey(*nsid),
+      ));
+      for member in members {
+        out.add_member(new_id, member);
+      }
+      out.category_nsid_map.insert(rekey(*nsid), new_id);
+      category_remap.insert(id, new_id);
+    }
+    for (parent, children) in &self.category_children {
+      out.category_children.insert(
+        category_remap[parent],
+        children.iter().map(|child| category_remap[child]).collect(),
+      );
+    }
+    out
+      .aliases
+      .extend(self.aliases.iter().map(|(&a, &b)| (rekey(a), rekey(b))));
+    out.migrations = self.migrations.clone();
+    if let Some(migrations) = &mut out.migrations {
+      migrations.map_nsids(rekey);
+    }
+    out.default_namespace = self.default_namespace.clone();
+    out.sync_ids = self.sync_ids.as_ref().map(|ids| ids.remap(&remap));
+    out.interner = interner;
+    out
+  }
+}
+
+/// Two registries are equal if they have the same values under the same NSIDs,
+/// and the same categories with the same members nested the same way.
+///
+/// Handles and registration order don't matter.
+impl<T: PartialEq, K: RegistryKey, S: BuildHasher + Default> PartialEq
+  for Registry<T, K, S>
+{
+  fn eq(&self, other: &Self) -> bool {
+    if self.nsid_map.len() != other.nsid_map.len()
+      || self.category_nsid_map.len() != other.category_nsid_map.len()
+    {
+      return false;
+    }
+
+    // Straight to the maps, so aliases and migrations don't count (or fire hooks)
+    let entries_match = self.entries().all(|(_, (x, nsid))| {
+      other
+        .nsid_map
+        .get(nsid)
+        .is_some_and(|id| &other.slot(*id).0 == x)
+    });
+    entries_match
+      && self.category_arena.iter().all(|(_, (set, nsid))| {
+        let other_set = match other.category_nsid_map.get(nsid) {
+          Some(id) => &other.category_arena.get(*id).unwrap().0,
+          None => return false,
+        };
+        set.len() == other_set.len()
+          && set.iter().all(|id| {
+            let member = self.slot(*id).1;
+            other
+              .nsid_map
+              .get(&member)
+              .is_some_and(|other_id| other_set.contains(other_id))
+          })
+      })
+      && self.category_arena.iter().all(|(id, (_, nsid))| {
+        let other_id = other.category_nsid_map[nsid];
+        self.subcategory_nsids(id) == other.subcategory_nsids(other_id)
+      })
+  }
+}
+
+impl<T: Eq, K: RegistryKey, S: BuildHasher + Default> Eq for Registry<T, K, S> {}
+
+/// Convenience for `lookup`
+impl<T, K: RegistryKey, S: BuildHasher + Default> Index<RegistryHandle<T, K>>
+  for Registry<T, K, S>
+{
+  type Output = T;
+
+  fn index(&self, index: RegistryHandle<T, K>) -> &Self::Output {
+    self.lookup(index)
+  }
+}
+
+/// Convenience for `lookup_by_nsid`. Unwraps if it can't find it.
+impl<T, S: BuildHasher + Default> Index<NamespacedID>
+  for Registry<T, NamespacedID, S>
+{
+  type Output = T;
+
+  fn index(&self, index: NamespacedID) -> &Self::Output {
+    self.lookup_by_nsid(index).unwrap_or_else(|| {
+      panic!(
+        "couldn't find the NSID `{}` in the {} registry",
+        &index,
+        std::any::type_name::<T>()
+      )
+    })
+  }
+}
+
+/// Super super convenience impl for `lookup_by_nsid`, turning the str into an NSID with `parse_nsid`
+impl<'a, T, S: BuildHasher + Default> Index<&'a str>
+  for Registry<T, NamespacedID, S>
+{
+  type Output = T;
+
+  fn index(&self, index: &'a str) -> &Self::Output {
+    let nsid = self.parse_nsid(index).unwrap();
+    &self[nsid]
+  }
+}
+
+/// Depth-first walk like `Registry::collect_nested`, but over NSIDs.
+///
+/// Returns the first loop found going down from `nsid`, in the order of [`ErrCategoryCycle::cycle`].
+pub(crate) fn find_cycle<K: RegistryKey>(
+  graph: &AHashMap<K, Vec<K>>,
+  nsid: K,
+  done: &mut AHashSet<K>,
+  path: &mut Vec<K>,
+) -> Option<Vec<K>> {
+  if let Some(start) = path.iter().position(|it| *it == nsid) {
+    return Some(path[start..].to_vec());
+  }
+  if !done.insert(nsid) {
+    return None;
+  }
+  path.push(nsid);
+  for child in graph.get(&nsid).into_iter().flatten() {
+    if let Some(cycle) = find_cycle(graph, *child, done, path) {
+      return Some(cycle);
+    }
+  }
+  path.pop();
+  None
+}
+
+/// Escape a string to go inside a double-quoted DOT ID.
+///
+/// NSIDs made under custom `NsidRules` can have quotes and backslashes in them.
+fn dot_escape(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for c in s.chars() {
+    if c == '"' || c == '\\' {
+      out.push('\\');
+    }
+    out.push(c);
+  }
+  out
+}
+
+/// How a category's membership changed, for `Registry::on_category_change`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CategoryChange {
+  Added,
+  Removed,
+}
+
+/// What `Registry::merge` should do when both registries have something under the same NSID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+  /// Don't merge anything, and report all the collisions.
+  ErrorOnConflict,
+  /// Keep the entry that was already registered and throw away the incoming one.
+  KeepExisting,
+  /// Replace the value of the entry that was already registered with the incoming one.
+  /// Existing handles to it keep working.
+  Overwrite,
+}
+
+/// Lightweight handle to an entry in a registry.
+pub struct RegistryHandle<T, K = NamespacedID> {
+  id: ArenaID<T>,
+  nsid: K,
+}
+
+impl<T, K: RegistryKey> RegistryHandle<T, K> {
+  fn new(handle: ArenaID<T>, nsid: K) -> Self {
+    Self { id: handle, nsid }
+  }
+
+  pub fn get_nsid(&self) -> K {
+    self.nsid
+  }
+
+  /// Get this handle's index in its registry, for packing into network packets or GPU buffers.
+  ///
+  /// Turn it back into a handle with `Registry::handle_from_raw`.
+  /// The index only means anything to the registry the handle came from (not even a clone of it).
+  pub fn to_raw(&self) -> u32 {
+    self.id.index() as u32
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T, K: RegistryKey> Clone for RegistryHandle<T, K> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T, K: RegistryKey> Copy for RegistryHandle<T, K> {}
+
+impl<T, K: RegistryKey> Hash for RegistryHandle<T, K> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.id.hash(state);
+  }
+}
+
+impl<T, K: RegistryKey> PartialEq for RegistryHandle<T, K> {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl<T, K: RegistryKey> Eq for RegistryHandle<T, K> {}
+
+impl<T, K: RegistryKey> Debug for RegistryHandle<T, K> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("RegistryHandle").field(&self.nsid).finish()
+  }
+}
+
+/// Lightweight handle to a *category* of entries in a registry.
+pub struct CategoryHandle<T, K = NamespacedID> {
+  id: ArenaID<CatWrapper<T>>,
+  nsid: K,
+}
+
+impl<T, K: RegistryKey> CategoryHandle<T, K> {
+  fn new(handle: ArenaID<CatWrapper<T>>, nsid: K) -> Self {
+    Self { id: handle, nsid }
+  }
+
+  pub fn get_nsid(&self) -> K {
+    self.nsid
+  }
+}
+impl<T, K: RegistryKey> Clone for CategoryHandle<T, K> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T, K: RegistryKey> Copy for CategoryHandle<T, K> {}
+
+impl<T, K: RegistryKey> Hash for CategoryHandle<T, K> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.id.hash(state);
+  }
+}
+
+impl<T, K: RegistryKey> PartialEq for CategoryHandle<T, K> {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl<T, K: RegistryKey> Eq for CategoryHandle<T, K> {}
+
+impl<T, K: RegistryKey> Debug for CategoryHandle<T, K> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("CategoryHandle").field(&self.nsid).finish()
+  }
+}
+
+/// Internal struct to help differentiate handles to the arena itself and to the category arena.
+struct CatWrapper<T>(PhantomData<T>);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn index_by_handle() {
+    let mut registry = Registry::<&str>::new();
+    let stone = registry.register("stone", nsid("game:stone")).unwrap();
+    let dirt = registry.register("dirt", nsid("game:dirt")).unwrap();
+    assert_eq!(registry[stone], "stone");
+    assert_eq!(registry[dirt], *registry.lookup(dirt));
+  }
+
+  #[test]
+  fn index_by_nsid_and_str() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(7, nsid("game:stone")).unwrap();
+    assert_eq!(registry[nsid("game:stone")], 7);
+    assert_eq!(registry["game:stone"], 7);
+  }
+
+  #[test]
+  fn nest_category_refuses_loops() {
+    let mut registry = Registry::<u32>::new();
+    let a = registry.register_empty_category(nsid("game:a")).unwrap();
+    let b = registry.register_empty_category(nsid("game:b")).unwrap();
+    let c = registry.register_empty_category(nsid("game:c")).unwrap();
+
+    assert!(registry.nest_category(a, b).unwrap());
+    assert!(!registry.nest_category(a, b).unwrap());
+    assert!(registry.nest_category(b, c).unwrap());
+
+    let err = registry.nest_category(c, a).unwrap_err();
+    assert_eq!(err.cycle, [nsid("game:c"), nsid("game:a"), nsid("game:b")]);
+    let err = registry.nest_category(a, a).unwrap_err();
+    assert_eq!(err.cycle, [nsid("game:a")]);
+    assert_eq!(registry.subcategories(c).count(), 0);
+    assert_eq!(registry.subcategories(a).count(), 1);
+  }
+
+  #[test]
+  fn nest_category_allows_diamonds() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let top = registry.register_empty_category(nsid("game:top")).unwrap();
+    let left = registry.register_empty_category(nsid("game:left")).unwrap();
+    let right = registry
+      .register_empty_category(nsid("game:right"))
+      .unwrap();
+    let bottom = registry
+      .register_category(nsid("game:bottom"), [stone])
+      .unwrap();
+    registry.nest_category(top, left).unwrap();
+    registry.nest_category(top, right).unwrap();
+    registry.nest_category(left, bottom).unwrap();
+    registry.nest_category(right, bottom).unwrap();
+
+    let found = registry.lookup_category_recursive(top).unwrap();
+    assert_eq!(found, [(&1, stone)]);
+  }
+
+  #[test]
+  fn category_members_by_nsid_follow_aliases() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [dirt])
+      .unwrap();
+
+    let err = registry
+      .set_category_members_by_nsid(
+        blocks,
+        [nsid("game:rock"), nsid("game:nope")],
+      )
+      .unwrap_err();
+    assert_eq!(err, [nsid("game:nope")]);
+    assert!(registry.is_in_category(dirt, blocks));
+
+    registry
+      .set_category_members_by_nsid(blocks, [nsid("game:rock")])
+      .unwrap();
+    assert!(registry.is_in_category(stone, blocks));
+    assert!(!registry.is_in_category(dirt, blocks));
+  }
+
+  #[test]
+  fn insert_nsid_into_category_follows_aliases() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    let blocks = registry
+      .register_empty_category(nsid("game:blocks"))
+      .unwrap();
+
+    assert!(registry
+      .insert_nsid_into_category(nsid("game:blocks"), nsid("game:rock"))
+      .unwrap());
+    assert!(!registry
+      .insert_nsid_into_category(nsid("game:blocks"), nsid("game:stone"))
+      .unwrap());
+    assert!(registry.is_in_category(stone, blocks));
+    assert!(matches!(
+      registry.insert_nsid_into_category(nsid("game:nope"), nsid("game:stone")),
+      Err(InsertByNsidError::UnknownCategory(c)) if c == nsid("game:nope")
+    ));
+    assert!(matches!(
+      registry
+        .insert_nsid_into_category(nsid("game:blocks"), nsid("game:dirt")),
+      Err(InsertByNsidError::UnknownEntry(e)) if e == nsid("game:dirt")
+    ));
+    assert_eq!(registry.category_len(blocks), 1);
+  }
+
+  #[test]
+  fn validate_or_suggest_picks_the_closest() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:stones")).unwrap();
+    registry.register(3, nsid("game:dirt")).unwrap();
+
+    assert_eq!(registry.validate_or_suggest(nsid("game:stone")), Ok(stone));
+    assert_eq!(
+      registry.validate_or_suggest(nsid("game:stine")),
+      Err(Some(nsid("game:stone")))
+    );
+    assert_eq!(
+      registry.validate_or_suggest(nsid("game:durt")),
+      Err(Some(nsid("game:dirt")))
+    );
+    assert_eq!(registry.validate_or_suggest(nsid("other:lava")), Err(None));
+    assert_eq!(
+      registry.suggest(nsid("game:stine"), 1),
+      [nsid("game:stone")]
+    );
+  }
+
+  #[test]
+  fn suggest_matches_bare_paths_and_prefixes() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:stone_bricks")).unwrap();
+    registry.register(3, nsid("game:dirt")).unwrap();
+
+    assert_eq!(
+      registry.suggest("ston", 5),
+      [nsid("game:stone"), nsid("game:stone_bricks")]
+    );
+    assert_eq!(registry.suggest("dirt", 5), [nsid("game:dirt")]);
+    assert!(registry.suggest("lava", 5).is_empty());
+  }
+
+  #[test]
+  fn member_counts_and_tags() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let lava = registry.register(3, nsid("game:lava")).unwrap();
+    let air = registry.register(4, nsid("game:air")).unwrap();
+    registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    registry
+      .register_category(nsid("game:solid"), [stone])
+      .unwrap();
+    registry
+      .register_category(nsid("game:hot"), [lava, air])
+      .unwrap();
+    registry.unregister(air);
+
+    let distribution = registry.member_count_distribution();
+    assert_eq!(distribution.len(), 2);
+    assert_eq!(distribution[&2], 1);
+    assert_eq!(distribution[&1], 2);
+
+    let mut tags = registry.tags_of_many([stone, lava]);
+    tags.get_mut(&stone).unwrap().sort();
+    assert_eq!(tags[&stone], [nsid("game:blocks"), nsid("game:solid")]);
+    assert_eq!(tags[&lava], [nsid("game:hot")]);
+  }
+
+  #[test]
+  fn export_all_is_sorted() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let apple = registry.register(3, nsid("food:apple")).unwrap();
+    registry
+      .register_category(nsid("game:things"), [stone, apple, dirt])
+      .unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    let all = registry.register_empty_category(nsid("all:all")).unwrap();
+    registry.nest_category(all, blocks).unwrap();
+
+    let (entries, categories) = registry.export_all();
+    assert_eq!(
+      entries,
+      [
+        (nsid("food:apple"), &3),
+        (nsid("game:dirt"), &2),
+        (nsid("game:stone"), &1)
+      ]
+    );
+    assert_eq!(
+      categories,
+      [
+        (nsid("all:all"), vec![]),
+        (
+          nsid("game:blocks"),
+          vec![nsid("game:dirt"), nsid("game:stone")]
+        ),
+        (
+          nsid("game:things"),
+          vec![nsid("food:apple"), nsid("game:dirt"), nsid("game:stone")]
+        ),
+      ]
+    );
+    assert_eq!(
+      registry.export_subcategories(),
+      [(nsid("all:all"), vec![nsid("game:blocks")])]
+    );
+  }
+
+  #[test]
+  fn export_dot_draws_categories() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let stones = registry
+      .register_category(nsid("game:stones"), [stone])
+      .unwrap();
+    let blocks = registry
+      .register_empty_category(nsid("game:blocks"))
+      .unwrap();
+    registry.nest_category(blocks, stones).unwrap();
+
+    let mut out = Vec::new();
+    registry.export_dot(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+      out,
+      "digraph registry {\n  \"game:stone\";\n  \"#game:blocks\" [shape=box];\n  \
+       \"#game:stones\" [shape=box];\n  \"#game:stones\" -> \"game:stone\";\n  \
+       \"#game:blocks\" -> \"#game:stones\" [style=dashed];\n}\n"
+    );
+  }
+
+  #[test]
+  fn export_dot_escapes_labels() {
+    let mut registry = Registry::<u32>::new();
+    let weird = NamespacedID::from_parts_unchecked("game", "say\"hi\"\\");
+    let handle = registry.register(1, weird).unwrap();
+    registry
+      .register_category(nsid("game:weird"), [handle])
+      .unwrap();
+
+    let mut out = Vec::new();
+    registry.export_dot(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let label = r#""game:say\"hi\"\\""#;
+    assert!(out.contains(&format!("  {};\n", label)));
+    assert!(out.contains(&format!("  \"#game:weird\" -> {};\n", label)));
+  }
+
+  #[test]
+  fn category_nsids_lists_every_category() {
+    let mut registry = Registry::<u32>::new();
+    registry.register_empty_category(nsid("game:a")).unwrap();
+    registry.register_empty_category(nsid("game:b")).unwrap();
+    let mut nsids: Vec<_> = registry.category_nsids().collect();
+    nsids.sort();
+    assert_eq!(nsids, [nsid("game:a"), nsid("game:b")]);
+  }
+
+  #[test]
+  #[should_panic]
+  fn index_with_stale_handle_panics() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.unregister(stone);
+    let _ = registry[stone];
+  }
+
+  #[test]
+  fn category_member_diff_against_a_snapshot() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    let before = registry.snapshot_category(blocks);
+
+    registry.remove_from_category(blocks, dirt);
+    registry.insert_into_category(blocks, sand);
+    let (added, removed) = registry.category_member_diff(&before, blocks);
+    assert_eq!(added, [nsid("game:sand")]);
+    assert_eq!(removed, [nsid("game:dirt")]);
+
+    let now = registry.snapshot_category(blocks);
+    assert_eq!(
+      registry.category_member_diff(&now, blocks),
+      (vec![], vec![])
+    );
+  }
+
+  #[test]
+  fn try_register_hands_the_entry_back() {
+    let mut registry = Registry::<String>::new();
+    let stone = registry
+      .try_register("stone".to_owned(), nsid("game:stone"))
+      .unwrap();
+    let (rejected, _) = registry
+      .try_register("another stone".to_owned(), nsid("game:stone"))
+      .unwrap_err();
+    assert_eq!(rejected, "another stone");
+    assert_eq!(registry[stone], "stone");
+    assert_eq!(registry.len(), 1);
+  }
+
+  #[test]
+  fn category_union_len_counts_each_member_once() {
+    let mut registry = Registry::<u32>::new();
+    let handles: Vec<_> = (0..5)
+      .map(|i| {
+        let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+        registry.register(i, nsid.unwrap()).unwrap()
+      })
+      .collect();
+    let a = registry
+      .register_category(nsid("game:a"), [handles[0], handles[1], handles[2]])
+      .unwrap();
+    let b = registry
+      .register_category(nsid("game:b"), [handles[1], handles[2], handles[3]])
+      .unwrap();
+    let c = registry
+      .register_category(nsid("game:c"), [handles[2], handles[4]])
+      .unwrap();
+
+    assert_eq!(registry.category_union_len([a, b, c]), 5);
+    assert_eq!(registry.category_union_len([a, b]), 4);
+    assert_eq!(registry.category_union_len([c]), 2);
+    assert_eq!(registry.category_union_len([]), 0);
+  }
+
+  #[test]
+  fn reserve_in_category_then_fill_it() {
+    let mut registry = Registry::<u32>::new();
+    let blocks = registry
+      .register_empty_category(nsid("game:blocks"))
+      .unwrap();
+    registry.reserve_in_category(blocks, 100);
+    for i in 0..100 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      let handle = registry.register(i, nsid.unwrap()).unwrap();
+      registry.insert_into_category(blocks, handle);
+    }
+    assert_eq!(registry.category_len(blocks), 100);
+  }
+
+  #[test]
+  fn category_values_cloned_owns_the_values() {
+    let mut registry = Registry::<String>::new();
+    let stone = registry
+      .register("stone".to_owned(), nsid("game:stone"))
+      .unwrap();
+    let dirt = registry
+      .register("dirt".to_owned(), nsid("game:dirt"))
+      .unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    let mut values = registry.category_values_cloned(blocks);
+    drop(registry);
+    values.sort();
+    assert_eq!(values, ["dirt", "stone"]);
+  }
+
+  #[test]
+  fn import_category_resolves_members_here() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+
+    let blocks = registry
+      .import_category(
+        nsid("game:blocks"),
+        &[nsid("game:stone"), nsid("game:dirt")],
+      )
+      .unwrap();
+    assert!(registry.is_in_category(stone, blocks));
+    assert!(registry.is_in_category(dirt, blocks));
+
+    match registry.import_category(
+      nsid("game:more"),
+      &[nsid("game:stone"), nsid("game:lava"), nsid("game:air")],
+    ) {
+      Err(ImportCategoryError::UnknownMembers(missing)) => {
+        assert_eq!(missing, [nsid("game:lava"), nsid("game:air")])
+      }
+      other => panic!("expected unknown members, got {:?}", other),
+    }
+    assert!(!registry.contains_category_nsid(nsid("game:more")));
+    assert!(matches!(
+      registry.import_category(nsid("game:blocks"), &[]),
+      Err(ImportCategoryError::AlreadyRegistered(_))
+    ));
+  }
+
+  #[test]
+  fn category_count_containing_an_entry() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry
+      .register_category(nsid("game:a"), [stone, dirt])
+      .unwrap();
+    registry.register_category(nsid("game:b"), [stone]).unwrap();
+    registry.register_category(nsid("game:c"), [dirt]).unwrap();
+    let lonely = registry.register(3, nsid("game:lonely")).unwrap();
+
+    assert_eq!(registry.category_count_containing(stone), 2);
+    assert_eq!(registry.category_count_containing(lonely), 0);
+  }
+
+  #[test]
+  fn category_overlap_count_of_a_selection() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let lava = registry.register(3, nsid("game:lava")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    assert_eq!(registry.category_overlap_count(blocks, [stone, lava]), 1);
+    assert_eq!(registry.category_overlap_count(blocks, [stone, dirt]), 2);
+    assert_eq!(registry.category_overlap_count(blocks, [lava]), 0);
+  }
+
+  #[test]
+  fn handle_nsid_pairs_resolve_to_their_values() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry.register(3, nsid("game:sand")).unwrap();
+    registry.unregister(dirt);
+
+    let pairs = registry.handle_nsid_pairs();
+    assert_eq!(pairs.len(), 2);
+    for (handle, nsid) in pairs {
+      assert_eq!(handle.get_nsid(), nsid);
+      assert_eq!(registry.lookup_by_nsid(nsid), Some(&registry[handle]));
+    }
+  }
+
+  #[test]
+  fn category_sample_is_sorted_by_nsid() {
+    let mut registry = Registry::<u32>::new();
+    let handles: Vec<_> = ["e", "c", "a", "d", "b"]
+      .into_iter()
+      .enumerate()
+      .map(|(i, path)| {
+        let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+        registry.register(i as u32, nsid).unwrap()
+      })
+      .collect();
+    let letters = registry
+      .register_category(nsid("game:letters"), handles)
+      .unwrap();
+
+    let sample = registry.category_sample(letters, 2);
+    let nsids: Vec<_> =
+      sample.iter().map(|(_, handle)| handle.get_nsid()).collect();
+    assert_eq!(nsids, [nsid("game:a"), nsid("game:b")]);
+    assert_eq!(sample[0].0, &2);
+    assert_eq!(registry.category_len(letters), 5);
+    assert_eq!(registry.category_sample(letters, 10).len(), 5);
+  }
+
+  #[test]
+  fn try_merge_reports_every_conflict() {
+    let mut registry = Registry::<u32>::new();
+    for path in ["a", "b", "c", "d"] {
+      let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+      registry.register(1, nsid).unwrap();
+    }
+    let mut other = Registry::<u32>::new();
+    for path in ["a", "c", "d", "e"] {
+      let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+      other.register(2, nsid).unwrap();
+    }
+
+    let Err(MergeError::Conflicts(mut conflicts)) = registry.try_merge(other)
+    else {
+      panic!("try_merge should have found conflicts");
+    };
+    conflicts.sort();
+    assert_eq!(conflicts, [nsid("game:a"), nsid("game:c"), nsid("game:d")]);
+    assert_eq!(registry.len(), 4);
+    assert!(!registry.contains_nsid(nsid("game:e")));
+
+    let mut other = Registry::<u32>::new();
+    other.register(2, nsid("game:e")).unwrap();
+    let merged = registry.try_merge(other).unwrap();
+    assert_eq!(merged.len(), 1);
+    let (nsid, handle) = merged[0];
+    assert_eq!((nsid, registry[handle]), (self::nsid("game:e"), 2));
+  }
+
+  #[test]
+  fn category_equals_needs_an_exact_match() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    assert!(registry.category_equals(blocks, [dirt, stone, dirt]));
+    assert!(!registry.category_equals(blocks, [stone]));
+    assert!(!registry.category_equals(blocks, [stone, dirt, sand]));
+  }
+
+  #[test]
+  fn filter_map_values_projects_some_entries() {
+    let mut registry = Registry::<u32>::new();
+    for (i, path) in ["a", "b", "c", "d"].into_iter().enumerate() {
+      let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+      registry.register(i as u32, nsid).unwrap();
+    }
+    let mut evens = registry.filter_map_values(|x, handle| {
+      (x % 2 == 0).then(|| handle.get_nsid().to_string())
+    });
+    evens.sort();
+    assert_eq!(evens, ["game:a", "game:c"]);
+  }
+
+  #[test]
+  fn drain_category_keeps_it_registered() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    let mut drained = registry.drain_category(blocks);
+    drained.sort_by_key(|handle| handle.get_nsid());
+    assert_eq!(drained, [dirt, stone]);
+    assert_eq!(registry.category_len(blocks), 0);
+    assert!(registry.contains_category_nsid(nsid("game:blocks")));
+    assert_eq!(registry.categories_of(stone).count(), 0);
+    assert_eq!(registry.len(), 2);
+  }
+
+  #[test]
+  fn count_matching_values() {
+    let mut registry = Registry::<u32>::new();
+    for (i, path) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+      let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+      registry.register(i as u32 * 10, nsid).unwrap();
+    }
+    assert_eq!(registry.count(|x| *x >= 20), 3);
+    assert_eq!(registry.count(|x| *x > 100), 0);
+  }
+
+  #[test]
+  fn snapshot_and_restore_a_category() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    let snapshot = registry.snapshot_category(blocks);
+    assert_eq!(
+      snapshot,
+      [nsid("game:stone"), nsid("game:dirt")]
+        .into_iter()
+        .collect()
+    );
+    registry.remove_from_category(blocks, stone);
+    registry.insert_into_category(blocks, sand);
+
+    registry
+      .set_category_members_by_nsid(blocks, snapshot)
+      .unwrap();
+    assert!(registry.category_equals(blocks, [stone, dirt]));
+  }
+
+  #[test]
+  fn set_category_members_by_nsid_is_all_or_nothing() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+
+    let err = registry
+      .set_category_members_by_nsid(
+        blocks,
+        [nsid("game:lava"), nsid("game:dirt"), nsid("game:air")],
+      )
+      .unwrap_err();
+    assert_eq!(err, [nsid("game:lava"), nsid("game:air")]);
+    assert!(registry.category_equals(blocks, [stone]));
+
+    registry
+      .set_category_members_by_nsid(blocks, [nsid("game:dirt")])
+      .unwrap();
+    assert!(registry.category_equals(blocks, [dirt]));
+    assert_eq!(registry.categories_of(stone).count(), 0);
+  }
+
+  #[test]
+  fn registries_compare_by_content() {
+    let build = |order: &[&str], stone_value: u32| {
+      let mut registry = Registry::<u32>::new();
+      for path in order {
+        let value = if *path == "stone" { stone_value } else { 2 };
+        let nsid = NamespacedID::new_from_parts("game", path).unwrap();
+        registry.register(value, nsid).unwrap();
+      }
+      let stone = registry.validate_nsid(nsid("game:stone")).unwrap();
+      let dirt = registry.validate_nsid(nsid("game:dirt")).unwrap();
+      registry
+        .register_category(nsid("game:blocks"), [stone, dirt])
+        .unwrap();
+      registry
+    };
+    let a = build(&["stone", "dirt", "sand"], 1);
+    let b = build(&["sand", "dirt", "stone"], 1);
+    assert!(a == b);
+    assert!(a != build(&["stone", "dirt", "sand"], 5));
+    assert!(a != build(&["stone", "dirt"], 1));
+
+    let mut c = build(&["stone", "dirt", "sand"], 1);
+    let sand = c.validate_nsid(nsid("game:sand")).unwrap();
+    let blocks = c.validate_category_nsid(nsid("game:blocks")).unwrap();
+    c.insert_into_category(blocks, sand);
+    assert!(a != c);
+  }
+
+  #[test]
+  fn category_intersection_all_of_three() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    let a = registry
+      .register_category(nsid("game:a"), [stone, dirt, sand])
+      .unwrap();
+    let b = registry
+      .register_category(nsid("game:b"), [stone, dirt])
+      .unwrap();
+    let c = registry
+      .register_category(nsid("game:c"), [stone, sand])
+      .unwrap();
+
+    let common = registry.category_intersection_all([a, b, c]);
+    assert_eq!(common, [stone].into_iter().collect());
+    assert_eq!(registry.category_intersection_all([a, b]).len(), 2);
+    assert!(registry.category_intersection_all([]).is_empty());
+  }
+
+  #[test]
+  fn rename_category_namespace_moves_categories() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry
+      .register_category(nsid("old:blocks"), [stone])
+      .unwrap();
+    registry
+      .register_category(nsid("old:rocks"), [stone])
+      .unwrap();
+    registry
+      .register_category(nsid("other:blocks"), [stone])
+      .unwrap();
+
+    assert_eq!(registry.rename_category_namespace("old", "new").unwrap(), 2);
+    assert!(!registry.contains_category_nsid(nsid("old:blocks")));
+    assert!(!registry.contains_category_nsid(nsid("old:rocks")));
+    for name in ["new:blocks", "new:rocks"] {
+      let category = registry.validate_category_nsid(nsid(name)).unwrap();
+      assert!(registry.is_in_category(stone, category));
+    }
+
+    assert!(matches!(
+      registry.rename_category_namespace("other", "new"),
+      Err(RenameNamespaceError::AlreadyRegistered(_))
+    ));
+    assert!(registry.contains_category_nsid(nsid("other:blocks")));
+    assert!(matches!(
+      registry.rename_category_namespace("other", "Bad!"),
+      Err(RenameNamespaceError::InvalidNamespace(_))
+    ));
+  }
+
+  #[test]
+  fn category_values_by_nsid_matches_the_full_lookup() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    let mut values: Vec<_> = registry
+      .category_values_by_nsid(nsid("game:blocks"))
+      .unwrap()
+      .collect();
+    let mut full: Vec<_> = registry
+      .lookup_category_by_nsid(nsid("game:blocks"))
+      .unwrap()
+      .map(|(x, _)| x)
+      .collect();
+    values.sort();
+    full.sort();
+    assert_eq!(values, full);
+    assert_eq!(values, [&1, &2]);
+    assert!(registry
+      .category_values_by_nsid(nsid("game:nope"))
+      .is_none());
+  }
+
+  #[test]
+  fn handle_nsid_is_current_catches_stale_handles() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    assert!(registry.handle_nsid_is_current(stone));
+
+    registry.unregister(dirt);
+    assert!(!registry.handle_nsid_is_current(dirt));
+
+    let mut other = Registry::<u32>::new();
+    let lava = other.register(3, nsid("game:lava")).unwrap();
+    assert!(!registry.handle_nsid_is_current(lava));
+  }
+
+  #[test]
+  fn tags_of_many_matches_categories_of() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let air = registry.register(3, nsid("game:air")).unwrap();
+    registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    registry
+      .register_category(nsid("game:minable"), [stone])
+      .unwrap();
+
+    let tags = registry.tags_of_many([stone, dirt, air]);
+    assert_eq!(tags.len(), 3);
+    for handle in [stone, dirt, air] {
+      let mut expected: Vec<_> = registry
+        .categories_of(handle)
+        .map(|category| category.get_nsid())
+        .collect();
+      let mut got = tags[&handle].clone();
+      expected.sort();
+      got.sort();
+      assert_eq!(got, expected);
+    }
+    assert_eq!(tags[&stone].len(), 2);
+    assert!(tags[&air].is_empty());
+  }
+
+  #[test]
+  fn unregister_leaves_stale_handles() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    assert_eq!(registry.unregister(stone), Some(1));
+    assert_eq!(registry.unregister(stone), None);
+    assert!(!registry.is_live(stone));
+    assert_eq!(registry.category_len(blocks), 1);
+    assert_eq!(registry.categories_of(stone).count(), 0);
+    assert_eq!(registry.lookup_by_nsid(nsid("game:stone")), None);
+
+    let new_stone = registry.register(3, nsid("game:stone")).unwrap();
+    assert_ne!(new_stone, stone);
+    assert!(!registry.is_live(stone));
+    assert_eq!(registry[new_stone], 3);
+    assert_eq!(registry.category_len(blocks), 1);
+  }
+
+  #[test]
+  #[should_panic]
+  fn lookup_of_unregistered_handle_panics() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.unregister(stone);
+    registry.register(2, nsid("game:dirt")).unwrap();
+    registry.lookup(stone);
+  }
+
+  #[test]
+  fn mutate_entries_after_registering() {
+    let mut registry = Registry::<Vec<NamespacedID>>::new();
+    let stone = registry.register(vec![], nsid("game:stone")).unwrap();
+    registry.register(vec![], nsid("game:cobble")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+
+    registry.lookup_mut(stone).push(nsid("game:cobble"));
+    registry
+      .lookup_by_nsid_mut(nsid("game:cobble"))
+      .unwrap()
+      .push(nsid("game:stone"));
+    registry
+      .lookup_by_nsid_mut(nsid("game:rock"))
+      .unwrap()
+      .push(nsid("game:gravel"));
+    assert!(registry.lookup_by_nsid_mut(nsid("game:dirt")).is_none());
+
+    assert_eq!(registry[stone], [nsid("game:cobble"), nsid("game:gravel")]);
+    assert_eq!(registry[nsid("game:cobble")], [nsid("game:stone")]);
+  }
+
+  #[test]
+  fn category_set_algebra() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let sand = registry.register(3, nsid("game:sand")).unwrap();
+    let solid = registry
+      .register_category(nsid("game:solid"), [stone, dirt])
+      .unwrap();
+    let diggable = registry
+      .register_category(nsid("game:diggable"), [dirt, sand])
+      .unwrap();
+
+    let values = |members: Vec<(&u32, RegistryHandle<u32>)>| {
+      let mut values: Vec<_> = members.into_iter().map(|(x, _)| *x).collect();
+      values.sort();
+      values
+    };
+    let union = registry.category_union(solid, diggable).collect();
+    assert_eq!(values(union), [1, 2, 3]);
+    let both = registry.category_intersection(solid, diggable).collect();
+    assert_eq!(values(both), [2]);
+    let only = registry.category_difference(solid, diggable).collect();
+    assert_eq!(values(only), [1]);
+
+    let all = registry
+      .register_category_union(nsid("game:all"), solid, diggable)
+      .unwrap();
+    assert_eq!(registry.category_len(all), 3);
+    let dirts = registry
+      .register_category_intersection(nsid("game:dirts"), solid, diggable)
+      .unwrap();
+    assert!(registry.is_in_category(dirt, dirts));
+    assert_eq!(registry.category_len(dirts), 1);
+    let sands = registry
+      .register_category_difference(nsid("game:sands"), diggable, solid)
+      .unwrap();
+    assert!(registry.is_in_category(sand, sands));
+    assert_eq!(registry.category_len(sands), 1);
+
+    assert!(registry
+      .register_category_union(nsid("game:solid"), solid, diggable)
+      .is_err());
+  }
+
+  #[test]
+  fn merge_refuses_to_nest_categories_in_a_loop() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let a = registry.register_category(nsid("game:a"), [stone]).unwrap();
+    let b = registry.register_empty_category(nsid("game:b")).unwrap();
+    registry.nest_category(a, b).unwrap();
+
+    for policy in [MergePolicy::ErrorOnConflict, MergePolicy::KeepExisting] {
+      let mut other = Registry::<u32>::new();
+      let dirt = other.register(2, nsid("game:dirt")).unwrap();
+      let other_b = other.register_category(nsid("game:b"), [dirt]).unwrap();
+      let other_a = other.register_empty_category(nsid("game:a")).unwrap();
+      other.nest_category(other_b, other_a).unwrap();
+      match registry.merge(other, policy) {
+        Err(MergeError::Cycle(err)) => {
+          assert_eq!(err.cycle, [nsid("game:b"), nsid("game:a")])
+        }
+        _ => panic!("merge should have found the loop"),
+      }
+    }
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry.category_len(b), 0);
+    assert_eq!(registry.subcategories(b).count(), 0);
+    assert_eq!(registry.lookup_category_recursive(a).unwrap().len(), 1);
+
+    let mut other = Registry::<u32>::new();
+    let other_a = other.register_empty_category(nsid("game:a")).unwrap();
+    let c = other.register_empty_category(nsid("game:c")).unwrap();
+    other.nest_category(c, other_a).unwrap();
+    assert!(registry.try_merge(other).is_ok());
+  }
+
+  #[test]
+  fn categories_of_follows_every_change() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    let minable = registry
+      .register_empty_category(nsid("game:minable"))
+      .unwrap();
+    let tags = |registry: &Registry<u32>, handle| {
+      let mut tags: Vec<_> = registry
+        .categories_of(handle)
+        .map(|category| category.get_nsid())
+        .collect();
+      tags.sort();
+      tags
+    };
+
+    registry.insert_into_category(minable, stone);
+    assert_eq!(
+      tags(&registry, stone),
+      [nsid("game:blocks"), nsid("game:minable")]
+    );
+    assert!(tags(&registry, dirt).is_empty());
+
+    assert!(registry.remove_from_category(blocks, stone));
+    assert_eq!(tags(&registry, stone), [nsid("game:minable")]);
+
+    registry
+      .set_category_members_by_nsid(minable, [nsid("game:dirt")])
+      .unwrap();
+    assert!(tags(&registry, stone).is_empty());
+    assert_eq!(tags(&registry, dirt), [nsid("game:minable")]);
+  }
+
+  #[test]
+  fn merge_with_each_policy() {
+    let base = || {
+      let mut registry = Registry::<u32>::new();
+      registry.register(1, nsid("game:stone")).unwrap();
+      registry
+    };
+    let plugin = || {
+      let mut registry = Registry::<u32>::new();
+      let stone = registry.register(10, nsid("game:stone")).unwrap();
+      let ore = registry.register(20, nsid("plugin:ore")).unwrap();
+      registry
+        .register_category(nsid("game:minable"), [stone, ore])
+        .unwrap();
+      (registry, stone, ore)
+    };
+
+    let mut registry = base();
+    let (other, ..) = plugin();
+    let conflicts = registry
+      .merge(other, MergePolicy::ErrorOnConflict)
+      .unwrap_err();
+    assert!(matches!(
+      conflicts,
+      MergeError::Conflicts(conflicts) if conflicts == [nsid("game:stone")]
+    ));
+    assert_eq!(registry.len(), 1);
+
+    let mut registry = base();
+    let (other, stone, ore) = plugin();
+    let remap = registry.merge(other, MergePolicy::KeepExisting).unwrap();
+    assert_eq!(registry[remap[&stone]], 1);
+    assert_eq!(registry[remap[&ore]], 20);
+    let minable = registry
+      .lookup_category_by_nsid(nsid("game:minable"))
+      .unwrap();
+    assert_eq!(minable.count(), 2);
+
+    let mut registry = base();
+    let (other, stone, _) = plugin();
+    let remap = registry.merge(other, MergePolicy::Overwrite).unwrap();
+    assert_eq!(registry[remap[&stone]], 10);
+    assert_eq!(remap[&stone].get_nsid(), nsid("game:stone"));
+  }
+
+  #[test]
+  fn merge_brings_aliases_and_migrations() {
+    let base = || {
+      let mut registry = Registry::<u32>::new();
+      registry.register(1, nsid("game:stone")).unwrap();
+      registry
+        .register_alias(nsid("game:rock"), nsid("game:stone"))
+        .unwrap();
+      let mut migrations = MigrationTable::new();
+      migrations
+        .add(nsid("game:pebble"), nsid("game:stone"))
+        .unwrap();
+      registry.set_migrations(migrations);
+      registry
+    };
+    let plugin = |old_ore| {
+      let mut registry = Registry::<u32>::new();
+      registry.register(2, nsid("game:dirt")).unwrap();
+      registry.register(3, nsid("plugin:ore")).unwrap();
+      registry
+        .register_alias(nsid("game:rock"), nsid("game:dirt"))
+        .unwrap();
+      registry
+        .register_alias(nsid("plugin:rock"), nsid("plugin:ore"))
+        .unwrap();
+      let mut migrations = MigrationTable::new();
+      migrations.add(old_ore, nsid("plugin:ore")).unwrap();
+      registry.set_migrations(migrations);
+      registry
+    };
+
+    let mut registry = base();
+    match registry.merge(plugin(nsid("old:ore")), MergePolicy::ErrorOnConflict)
+    {
+      Err(MergeError::Conflicts(conflicts)) => {
+        assert_eq!(conflicts, [nsid("game:rock")])
+      }
+      _ => panic!("the aliases should have clashed"),
+    }
+    assert_eq!(registry.len(), 1);
+
+    registry
+      .merge(plugin(nsid("old:ore")), MergePolicy::KeepExisting)
+      .unwrap();
+    assert_eq!(registry.lookup_by_nsid(nsid("game:rock")), Some(&1));
+    assert_eq!(registry.lookup_by_nsid(nsid("plugin:rock")), Some(&3));
+    assert_eq!(registry.lookup_by_nsid(nsid("game:pebble")), Some(&1));
+    assert_eq!(registry.lookup_by_nsid(nsid("old:ore")), Some(&3));
+
+    let mut registry = base();
+    registry
+      .merge(plugin(nsid("old:ore")), MergePolicy::Overwrite)
+      .unwrap();
+    assert_eq!(registry.lookup_by_nsid(nsid("game:rock")), Some(&2));
+
+    let mut registry = base();
+    match registry.merge(plugin(nsid("game:pebble")), MergePolicy::Overwrite) {
+      Err(MergeError::Migration(MigrationError::AlreadyMigrated(old))) => {
+        assert_eq!(old, nsid("game:pebble"))
+      }
+      _ => panic!("the migrations should have clashed"),
+    }
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry.aliases().count(), 1);
+  }
+
+  #[test]
+  fn hooks_see_registrations_and_category_changes() {
+    use std::sync::{Arc, Mutex};
+
+    let mut registry = Registry::<u32>::new();
+    let registered = Arc::new(Mutex::new(Vec::new()));
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let seen = registered.clone();
+    registry.on_register(move |nsid, x| seen.lock().unwrap().push((nsid, *x)));
+    let seen = changes.clone();
+    registry.on_category_change(move |category, nsid, change| {
+      seen.lock().unwrap().push((category, nsid, change))
+    });
+
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    registry.insert_into_category(blocks, stone);
+    registry.unregister(stone);
+
+    assert_eq!(*registered.lock().unwrap(), [(nsid("game:stone"), 1)]);
+    assert_eq!(
+      *changes.lock().unwrap(),
+      [
+        (
+          nsid("game:blocks"),
+          nsid("game:stone"),
+          CategoryChange::Added
+        ),
+        (
+          nsid("game:blocks"),
+          nsid("game:stone"),
+          CategoryChange::Removed
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn bare_paths_use_the_default_namespace() {
+    assert_eq!(
+      NamespacedID::parse_with_default_namespace("stone", "game").unwrap(),
+      nsid("game:stone")
+    );
+    assert_eq!(
+      NamespacedID::parse_with_default_namespace("mod:stone", "game").unwrap(),
+      nsid("mod:stone")
+    );
+
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    assert!(matches!(
+      registry.parse_nsid("stone"),
+      Err(NSIDParseError::NoSeparator)
+    ));
+    assert!(registry.set_default_namespace("Bad Space").is_err());
+    assert_eq!(registry.default_namespace(), None);
+
+    registry.set_default_namespace("game").unwrap();
+    assert_eq!(registry.default_namespace(), Some("game"));
+    assert_eq!(registry.parse_nsid("stone").unwrap(), nsid("game:stone"));
+    assert_eq!(registry.lookup_by_str("stone").unwrap(), &1);
+    assert!(registry.lookup_by_str("dirt").is_err());
+  }
+
+  #[test]
+  fn suggest_ranks_typos_closest_first() {
+    let mut registry = Registry::<u32>::new();
+    for (i, s) in ["minecraft:stone", "minecraft:stones", "minecraft:dirt"]
+      .into_iter()
+      .enumerate()
+    {
+      registry.register(i as u32, nsid(s)).unwrap();
+    }
+
+    assert_eq!(
+      registry.suggest("mincraft:stone", 5),
+      [nsid("minecraft:stone"), nsid("minecraft:stones")]
+    );
+    assert_eq!(
+      registry.suggest("mincraft:stone", 1),
+      [nsid("minecraft:stone")]
+    );
+    assert_eq!(
+      registry.suggest("Minecraft:Dirt!", 5),
+      [nsid("minecraft:dirt")]
+    );
+    assert!(registry.suggest("other:thing", 5).is_empty());
+  }
+
+  #[test]
+  fn lookup_by_str_tells_bad_nsids_from_missing_ones() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+
+    assert_eq!(registry.lookup_by_str("game:stone").unwrap(), &1);
+    assert!(matches!(
+      registry.lookup_by_str("game:dirt"),
+      Err(LookupError::NotRegistered(missing)) if missing == nsid("game:dirt")
+    ));
+    assert!(matches!(
+      registry.lookup_by_str("game:Stone"),
+      Err(LookupError::InvalidNsid(NSIDParseError::InvalidPath(_)))
+    ));
+  }
+
+  #[test]
+  #[should_panic(expected = "couldn't find the NSID `game:dirt`")]
+  fn index_by_missing_nsid_names_it() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(7, nsid("game:stone")).unwrap();
+    let _ = registry[nsid("game:dirt")];
+  }
+
+  #[test]
+  fn sizes_and_membership() {
+    let mut registry = Registry::<u32>::new();
+    assert!(registry.is_empty());
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:dirt")).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+
+    assert_eq!(registry.len(), 2);
+    assert!(!registry.is_empty());
+    assert_eq!(registry.category_count(), 1);
+    assert_eq!(registry.category_len(blocks), 1);
+    assert!(registry.contains_nsid(nsid("game:stone")));
+    assert!(!registry.contains_nsid(nsid("game:rock")));
+    assert!(registry.contains_category_nsid(nsid("game:blocks")));
+    assert!(!registry.contains_category_nsid(nsid("game:stone")));
+  }
+
+  #[test]
+  fn preallocated_registries_work_like_any_other() {
+    let mut registry = Registry::<u32>::with_capacity(100, 10);
+    registry.reserve(1000, 100);
+    for i in 0..200 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      registry.register(i, nsid.unwrap()).unwrap();
+    }
+    assert_eq!(registry.len(), 200);
+    assert_eq!(registry[nsid("game:e150")], 150);
+    assert!(
+      registry == {
+        let mut plain = Registry::<u32>::new();
+        for i in 0..200 {
+          let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+          plain.register(i, nsid.unwrap()).unwrap();
+        }
+        plain
+      }
+    );
+  }
+
+  #[test]
+  fn iter_categories_counts_members() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+    let empty = registry
+      .register_empty_category(nsid("game:empty"))
+      .unwrap();
+
+    let mut found: Vec<_> = registry.iter_categories().collect();
+    found.sort_by_key(|(_, nsid, _)| *nsid);
+    assert_eq!(
+      found,
+      [
+        (blocks, nsid("game:blocks"), 2),
+        (empty, nsid("game:empty"), 0)
+      ]
+    );
+  }
+
+  #[test]
+  fn iter_namespace_only_sees_that_namespace() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:dirt")).unwrap();
+    let gem = registry.register(3, nsid("mod:gem")).unwrap();
+    registry.register(4, nsid("game:air")).unwrap();
+    registry.unregister(gem);
+
+    let game: Vec<_> = registry
+      .iter_namespace("game")
+      .map(|(_, handle)| handle.get_nsid())
+      .collect();
+    assert_eq!(
+      game,
+      [nsid("game:air"), nsid("game:dirt"), nsid("game:stone")]
+    );
+    assert_eq!(registry.iter_namespace("mod").count(), 0);
+    assert_eq!(registry.iter_namespace("nope").count(), 0);
+    assert_eq!(registry.namespaces().collect::<Vec<_>>(), ["game"]);
+  }
+
+  #[test]
+  fn iter_path_prefix_finds_a_subtree() {
+    let mut registry = Registry::<u32>::new();
+    for (i, s) in [
+      "game:ores/iron",
+      "game:ores/gold",
+      "game:ores_deepslate/iron",
+      "game:oresome",
+      "game:stone",
+      "mod:ores/tin",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+      registry.register(i as u32, nsid(s)).unwrap();
+    }
+
+    let paths = |prefix| {
+      registry
+        .iter_path_prefix("game", prefix)
+        .map(|(_, handle)| handle.get_nsid().resolve().1)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(paths("ores/"), ["ores/gold", "ores/iron"]);
+    assert_eq!(
+      paths("ores"),
+      ["ores/gold", "ores/iron", "ores_deepslate/iron", "oresome"]
+    );
+    assert!(paths("zzz").is_empty());
+    assert_eq!(paths("").len(), 5);
+  }
+
+  #[test]
+  fn get_or_register_is_idempotent() {
+    let mut registry = Registry::<u32>::new();
+    let first = registry.get_or_register(nsid("game:stone"), || 1);
+    let second = registry.get_or_register(nsid("game:stone"), || 2);
+    assert_eq!(first, second);
+    assert_eq!(registry[first], 1);
+    assert_eq!(registry.len(), 1);
+  }
+
+  #[test]
+  fn register_many_is_all_or_nothing() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+
+    let err = registry
+      .register_many([
+        (nsid("game:dirt"), 2),
+        (nsid("game:stone"), 3),
+        (nsid("game:sand"), 4),
+        (nsid("game:dirt"), 5),
+        (nsid("game:dirt"), 6),
+      ])
+      .unwrap_err();
+    assert_eq!(err.conflicts, [nsid("game:stone"), nsid("game:dirt")]);
+    assert_eq!(registry.len(), 1);
+
+    let handles = registry
+      .register_many([(nsid("game:dirt"), 2), (nsid("game:sand"), 4)])
+      .unwrap();
+    assert_eq!(handles.len(), 2);
+    assert_eq!(registry[handles[0]], 2);
+    assert_eq!(registry[handles[1]], 4);
+  }
+
+  #[test]
+  fn retain_and_drain_fix_up_categories() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let gem = registry.register(2, nsid("plugin:gem")).unwrap();
+    let ore = registry.register(3, nsid("plugin:ore")).unwrap();
+    let shiny = registry
+      .register_category(nsid("game:shiny"), [stone, gem, ore])
+      .unwrap();
+
+    let mut removed = registry.retain(|nsid, _| nsid.resolve().0 != "plugin");
+    removed.sort();
+    assert_eq!(removed, [(nsid("plugin:gem"), 2), (nsid("plugin:ore"), 3)]);
+    assert_eq!(registry.len(), 1);
+    assert_eq!(registry.category_len(shiny), 1);
+    assert!(registry.validate_nsid(nsid("plugin:gem")).is_none());
+
+    let drained: Vec<_> = registry.drain().collect();
+    assert_eq!(drained, [(nsid("game:stone"), 1)]);
+    assert!(registry.is_empty());
+    assert_eq!(registry.category_len(shiny), 0);
+    assert!(registry.contains_category_nsid(nsid("game:shiny")));
+  }
+
+  #[test]
+  fn replace_and_update_keep_identity() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+
+    assert_eq!(registry.replace(stone, 10), 1);
+    let doubled = registry.update(stone, |x| {
+      *x *= 2;
+      *x
+    });
+    assert_eq!(doubled, 20);
+    assert_eq!(registry[nsid("game:stone")], 20);
+    assert_eq!(registry.validate_nsid(nsid("game:stone")), Some(stone));
+    assert!(registry.is_in_category(stone, blocks));
+  }
+
+  #[test]
+  fn clones_are_independent_and_consistent() {
+    let mut registry = Registry::<u32>::new();
+    let air = registry.register(0, nsid("game:air")).unwrap();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    let all = registry.register_empty_category(nsid("game:all")).unwrap();
+    registry.nest_category(all, blocks).unwrap();
+    registry
+      .register_alias(nsid("game:rock"), nsid("game:stone"))
+      .unwrap();
+    registry.unregister(air);
+
+    let mut copy = registry.clone();
+    assert!(copy == registry);
+    let copy_stone = copy.validate_nsid(nsid("game:rock")).unwrap();
+    let copy_all = copy.validate_category_nsid(nsid("game:all")).unwrap();
+    assert_eq!(copy.lookup_category_recursive(copy_all).unwrap().len(), 1);
+
+    copy.replace(copy_stone, 100);
+    copy.register(2, nsid("game:dirt")).unwrap();
+    assert_eq!(registry[stone], 1);
+    assert_eq!(registry.len(), 1);
+    assert_eq!(copy[nsid("game:stone")], 100);
+    assert!(copy != registry);
+  }
+
+  #[test]
+  fn handles_round_trip_through_raw_indices() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    assert_ne!(stone.to_raw(), dirt.to_raw());
+
+    let packed: Vec<u32> =
+      [dirt, stone, dirt].iter().map(|h| h.to_raw()).collect();
+    let unpacked: Vec<_> = packed
+      .iter()
+      .map(|raw| registry.handle_from_raw(*raw).unwrap())
+      .collect();
+    assert_eq!(unpacked, [dirt, stone, dirt]);
+
+    registry.unregister(dirt);
+    assert_eq!(registry.handle_from_raw(dirt.to_raw()), None);
+    assert_eq!(registry.handle_from_raw(1000), None);
+  }
+
+  #[test]
+  fn any_hasher_works() {
+    use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+    type Deterministic = BuildHasherDefault<DefaultHasher>;
+    let mut registry = Registry::<u32, NamespacedID, Deterministic>::default();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:dirt")).unwrap();
+    let blocks = registry
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    assert_eq!(registry[nsid("game:dirt")], 2);
+    assert_eq!(registry.lookup_category(blocks).len(), 1);
+
+    let mut plain = Registry::<u32>::new();
+    let stone = plain.register(1, nsid("game:stone")).unwrap();
+    plain.register(2, nsid("game:dirt")).unwrap();
+    plain
+      .register_category(nsid("game:blocks"), [stone])
+      .unwrap();
+    assert_eq!(registry.checksum(), plain.checksum());
+  }
+}
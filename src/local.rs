@@ -0,0 +1,422 @@
+use std::{
+  fmt::{Debug, Display},
+  hash::BuildHasher,
+  sync::atomic::{AtomicU32, Ordering},
+};
+
+use lasso::{Rodeo, Spur};
+
+use crate::{NSIDParseError, NamespacedID, NsidRules, Registry, RegistryKey};
+
+/// An interner for NSIDs that you own, instead of the global one every [`NamespacedID`] goes through.
+///
+/// Strings put in the global interner stay there until the program exits,
+/// which adds up for throwaway registries in tests or editor sessions.
+/// Give one to a `Registry<T, LocalNamespacedID>` with [`Registry::with_interner`] instead,
+/// and when the registry is dropped, the strings go too.
+///
+/// An interner can also have its own [`NsidRules`] for what counts as a valid NSID.
+pub struct NsidInterner {
+  rodeo: Rodeo,
+  id: u32,
+  rules: NsidRules,
+}
+
+/// An NSID made by a particular [`NsidInterner`].
+///
+/// It's just as cheap as a [`NamespacedID`], but turning it back into text means asking the interner that made it.
+/// Its `Debug` output only shows the raw IDs for that reason.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalNamespacedID {
+  interner: u32,
+  namespace: Spur,
+  path: Spur,
+}
+
+impl RegistryKey for LocalNamespacedID {
+  fn rebase_interner(self, from: &NsidInterner, to: &NsidInterner) -> Self {
+    if self.interner == from.id {
+      // Clones hand out the same keys, so only the interner ID changes
+      Self {
+        interner: to.id,
+        ..self
+      }
+    } else {
+      self
+    }
+  }
+}
+
+static NEXT_INTERNER_ID: AtomicU32 = AtomicU32::new(0);
+
+impl NsidInterner {
+  pub fn new() -> Self {
+    Self::with_rules(NsidRules::default())
+  }
+
+  /// Make an interner that checks NSIDs against these rules instead of the usual ones.
+  pub fn with_rules(rules: NsidRules) -> Self {
+    Self {
+      rodeo: Rodeo::new(),
+      id: NEXT_INTERNER_ID.fetch_add(1, Ordering::Relaxed),
+      rules,
+    }
+  }
+
+  /// Get the rules this interner checks NSIDs against.
+  pub fn rules(&self) -> &NsidRules {
+    &self.rules
+  }
+
+  /// Parse and intern a `namespace:path` string, validating it against this interner's rules.
+  pub fn nsid(&mut self, s: &str) -> Result<LocalNamespacedID, NSIDParseError> {
+    let (namespace, path) = self.rules.split_checked(s)?;
+    Ok(LocalNamespacedID {
+      interner: self.id,
+      namespace: self.rodeo.get_or_intern(namespace),
+      path: self.rodeo.get_or_intern(path),
+    })
+  }
+
+  /// Find the NSID for this string if it's already been interned here, without interning anything new.
+  pub fn get(&self, s: &str) -> Option<LocalNamespacedID> {
+    let (namespace, path) = s.split_once(':')?;
+    Some(LocalNamespacedID {
+      interner: self.id,
+      namespace: self.rodeo.get(namespace)?,
+      path: self.rodeo.get(path)?,
+    })
+  }
+
+  /// Get the namespace and path of an NSID from this interner.
+  ///
+  /// Returns `None` if it came from a different interner.
+  pub fn resolve(&self, nsid: LocalNamespacedID) -> Option<(&str, &str)> {
+    if nsid.interner != self.id {
+      return None;
+    }
+    Some((
+      self.rodeo.resolve(&nsid.namespace),
+      self.rodeo.resolve(&nsid.path),
+    ))
+  }
+
+  /// Get something that prints an NSID from this interner as `namespace:path`.
+  ///
+  /// Panics if it came from a different interner.
+  pub fn display(&self, nsid: LocalNamespacedID) -> impl Display + '_ {
+    let (namespace, path) = self
+      .resolve(nsid)
+      .expect("tried to display an NSID from a different interner");
+    DisplayLocal { namespace, path }
+  }
+
+  /// Turn an NSID from this interner into one from the global interner.
+  ///
+  /// It isn't checked again against the default rules, so an NSID that's only valid under this interner's
+  /// [`NsidRules`] comes across as it is, just like one made with `NamespacedID::new_with_rules`.
+  ///
+  /// Returns `None` if it came from a different interner.
+  pub fn to_global(&self, nsid: LocalNamespacedID) -> Option<NamespacedID> {
+    let (namespace, path) = self.resolve(nsid)?;
+    Some(NamespacedID::from_parts_unchecked(namespace, path))
+  }
+
+  /// Turn a global NSID into one from this interner.
+  pub fn from_global(&mut self, nsid: NamespacedID) -> LocalNamespacedID {
+    let (namespace, path) = nsid.resolve();
+    LocalNamespacedID {
+      interner: self.id,
+      namespace: self.rodeo.get_or_intern(namespace),
+      path: self.rodeo.get_or_intern(path),
+    }
+  }
+
+  /// How many distinct strings this has interned.
+  pub fn len(&self) -> usize {
+    self.rodeo.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.rodeo.is_empty()
+  }
+}
+
+impl Default for NsidInterner {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// The copy gets its own ID, so NSIDs interned on one side after cloning can't be mixed up with the other's.
+/// Cloning a `Registry` moves its keys over to the cloned interner.
+impl Clone for NsidInterner {
+  fn clone(&self) -> Self {
+    // Interning in key order hands out the same keys again
+    let mut rodeo =
+      Rodeo::with_capacity(lasso::Capacity::for_strings(self.rodeo.len()));
+    for (_, s) in self.rodeo.iter() {
+      rodeo.get_or_intern(s);
+    }
+    Self {
+      rodeo,
+      id: NEXT_INTERNER_ID.fetch_add(1, Ordering::Relaxed),
+      rules: self.rules.clone(),
+    }
+  }
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, LocalNamespacedID, S> {
+  /// Make an empty registry that owns this interner.
+  ///
+  /// Make NSIDs for it with `intern`, and they're all freed when the registry is dropped.
+  pub fn with_interner(interner: NsidInterner) -> Self {
+    Self {
+      interner: Some(interner),
+      ..Self::default()
+    }
+  }
+
+  /// Get the interner this registry's NSIDs come from, if it has one yet.
+  pub fn interner(&self) -> Option<&NsidInterner> {
+    self.interner.as_ref()
+  }
+
+  /// Get the interner this registry's NSIDs come from mutably,
+  /// making one with the default rules if there isn't one yet.
+  pub fn interner_mut(&mut self) -> &mut NsidInterner {
+    self.interner.get_or_insert_with(NsidInterner::new)
+  }
+
+  /// Parse and intern a `namespace:path` string with this registry's interner.
+  ///
+  /// If the registry doesn't have an interner yet, one with the default rules is made.
+  pub fn intern(
+    &mut self,
+    s: &str,
+  ) -> Result<LocalNamespacedID, NSIDParseError> {
+    self.interner_mut().nsid(s)
+  }
+
+  /// Get the namespace and path of one of this registry's NSIDs.
+  ///
+  /// Returns `None` if it came from some other interner.
+  pub fn resolve_local(&self, nsid: LocalNamespacedID) -> Option<(&str, &str)> {
+    self.interner.as_ref()?.resolve(nsid)
+  }
+
+  /// Rebuild this registry's interner with only the strings its NSIDs still use,
+  /// freeing the ones left behind by unregistered entries.
+  ///
+  /// Every entry, category, alias and migration keeps its NSID, but the strings get new keys
+  /// and the interner gets a new ID. So any `LocalNamespacedID` from before (including the ones
+  /// inside existing handles) stops resolving; get them again with `intern` or from `iter`.
+  /// The handles themselves still look things up fine.
+  ///
+  /// Does nothing if the registry doesn't have an interner.
+  pub fn compact_interner(&mut self) {
+    let old = match self.interner.take() {
+      Some(it) => it,
+      None => return,
+    };
+    let mut new = NsidInterner::with_rules(old.rules.clone());
+    let mut remap = |nsid: LocalNamespacedID| match old.resolve(nsid) {
+      Some((namespace, path)) => LocalNamespacedID {
+        interner: new.id,
+        namespace: new.rodeo.get_or_intern(namespace),
+        path: new.rodeo.get_or_intern(path),
+      },
+      // Not ours to move
+      None => nsid,
+    };
+
+    for (_, slot) in self.arena.iter_mut() {
+      if let Some((_, nsid)) = slot {
+        *nsid = remap(*nsid);
+      }
+    }
+    self.nsid_map = self
+      .nsid_map
+      .drain()
+      .map(|(nsid, id)| (remap(nsid), id))
+      .collect();
+    for (_, (_, nsid)) in self.category_arena.iter_mut() {
+      *nsid = remap(*nsid);
+    }
+    self.category_nsid_map = self
+      .category_nsid_map
+      .drain()
+      .map(|(nsid, id)| (remap(nsid), id))
+      .collect();
+    self.aliases = self
+      .aliases
+      .drain()
+      .map(|(alias, target)| (remap(alias), remap(target)))
+      .collect();
+    if let Some(migrations) = &mut self.migrations {
+      migrations.map_nsids(&mut remap);
+    }
+
+    self.interner = Some(new);
+  }
+}
+
+struct DisplayLocal<'a> {
+  namespace: &'a str,
+  path: &'a str,
+}
+
+impl<'a> Display for DisplayLocal<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.namespace, self.path)
+  }
+}
+
+impl Debug for LocalNamespacedID {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("LocalNamespacedID")
+      .field(&self.interner)
+      .field(&self.namespace)
+      .field(&self.path)
+      .finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn to_global_keeps_nsids_only_valid_under_custom_rules() {
+    let mut interner = NsidInterner::with_rules(NsidRules {
+      allow_uppercase: true,
+      extra_path_chars: "+".to_owned(),
+      ..Default::default()
+    });
+    let local = interner.nsid("Game:C++").unwrap();
+    let global = interner.to_global(local).unwrap();
+    assert_eq!(global.resolve(), ("Game", "C++"));
+    assert_eq!(interner.from_global(global), local);
+  }
+
+  #[test]
+  fn registry_owns_its_interner() {
+    let mut registry = Registry::<u32, LocalNamespacedID>::with_interner(
+      NsidInterner::with_rules(NsidRules {
+        allow_uppercase: true,
+        ..Default::default()
+      }),
+    );
+    let stone = registry.intern("Game:Stone").unwrap();
+    registry.register(1, stone).unwrap();
+    assert_eq!(registry.resolve_local(stone), Some(("Game", "Stone")));
+    assert_eq!(registry.interner().unwrap().len(), 2);
+
+    let copy = registry.clone();
+    assert_eq!(copy.lookup_by_nsid(stone), None);
+    let copied_stone = copy.interner().unwrap().get("Game:Stone").unwrap();
+    assert_eq!(copy.lookup_by_nsid(copied_stone), Some(&1));
+    assert_eq!(copy.resolve_local(copied_stone), Some(("Game", "Stone")));
+  }
+
+  #[test]
+  fn clones_keep_their_nsids_apart() {
+    let mut interner = NsidInterner::new();
+    let stone = interner.nsid("game:stone").unwrap();
+    let mut copy = interner.clone();
+    assert_eq!(copy.resolve(stone), None);
+    assert_eq!(
+      copy.display(copy.get("game:stone").unwrap()).to_string(),
+      "game:stone"
+    );
+
+    let dirt = interner.nsid("game:dirt").unwrap();
+    let sand = copy.nsid("game:sand").unwrap();
+    assert_eq!(copy.resolve(dirt), None);
+    assert_eq!(interner.resolve(sand), None);
+    assert_eq!(copy.to_global(dirt), None);
+    assert_eq!(interner.to_global(sand), None);
+
+    let mut registry =
+      Registry::<u32, LocalNamespacedID>::with_interner(interner);
+    let grass = registry.intern("game:grass").unwrap();
+    registry.register(1, grass).unwrap();
+    let turf = registry.intern("game:turf").unwrap();
+    registry.register_alias(turf, grass).unwrap();
+    let mut copy = registry.clone();
+    let copied_turf = copy.interner().unwrap().get("game:turf").unwrap();
+    assert_eq!(copy.lookup_by_nsid(copied_turf), Some(&1));
+    let clay = copy.intern("game:clay").unwrap();
+    let gravel = registry.intern("game:gravel").unwrap();
+    assert_eq!(registry.resolve_local(clay), None);
+    assert_eq!(copy.resolve_local(gravel), None);
+    assert_eq!(copy.lookup_by_nsid(grass), None);
+  }
+
+  #[test]
+  fn intern_makes_an_interner_if_needed() {
+    let mut registry = Registry::<u32, LocalNamespacedID>::new();
+    assert!(registry.interner().is_none());
+    let stone = registry.intern("game:stone").unwrap();
+    assert_eq!(registry.resolve_local(stone), Some(("game", "stone")));
+    assert!(registry.intern("Game:stone").is_err());
+  }
+
+  #[test]
+  fn compact_interner_drops_unused_strings() {
+    let mut registry =
+      Registry::<u32, LocalNamespacedID>::with_interner(NsidInterner::new());
+    let stone = registry.intern("game:stone").unwrap();
+    let dirt = registry.intern("game:dirt").unwrap();
+    let tools = registry.intern("game:tools/pickaxe").unwrap();
+    let blocks = registry.intern("game:blocks").unwrap();
+    let stone = registry.register(1, stone).unwrap();
+    registry.register(2, dirt).unwrap();
+    let tools = registry.register(3, tools).unwrap();
+    registry.register_category(blocks, [stone]).unwrap();
+
+    registry.unregister(tools);
+    let before = registry.interner().unwrap().len();
+    registry.compact_interner();
+    let after = registry.interner().unwrap().len();
+    assert_eq!(before, 5);
+    assert_eq!(after, 4);
+
+    let names: Vec<_> = registry
+      .iter()
+      .map(|(_, handle)| registry.resolve_local(handle.get_nsid()).unwrap())
+      .collect();
+    assert_eq!(names, [("game", "stone"), ("game", "dirt")]);
+
+    let stone_nsid = registry.intern("game:stone").unwrap();
+    assert_eq!(registry.lookup_by_nsid(stone_nsid), Some(&1));
+    assert_eq!(registry[stone], 1);
+    let blocks = registry.intern("game:blocks").unwrap();
+    let category = registry.validate_category_nsid(blocks).unwrap();
+    assert!(registry.is_in_category(stone, category));
+    assert_eq!(registry.interner().unwrap().len(), after);
+  }
+
+  #[test]
+  fn compact_interner_keeps_aliases() {
+    let mut registry =
+      Registry::<u32, LocalNamespacedID>::with_interner(NsidInterner::new());
+    let stone = registry.intern("game:stone").unwrap();
+    let rock = registry.intern("game:rock").unwrap();
+    registry.register(1, stone).unwrap();
+    registry.register_alias(rock, stone).unwrap();
+
+    registry.compact_interner();
+    let rock = registry.intern("game:rock").unwrap();
+    assert_eq!(registry.lookup_by_nsid(rock), Some(&1));
+  }
+
+  #[test]
+  fn to_global_rejects_other_interners() {
+    let mut a = NsidInterner::new();
+    let b = NsidInterner::new();
+    let nsid = a.nsid("game:stone").unwrap();
+    assert!(b.to_global(nsid).is_none());
+    assert!(b.resolve(nsid).is_none());
+  }
+}
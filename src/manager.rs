@@ -0,0 +1,217 @@
+use std::{any::Any, fmt::Debug, hash::Hash, marker::PhantomData};
+
+use ahash::AHashMap;
+
+use crate::{ErrAlreadyRegistered, NamespacedID, Registry};
+
+/// The NSID of one of the registries in a [`RegistryManager`], along with what's in it.
+///
+/// It's not called `RegistryKey` because that's what the keys *in* a registry are.
+pub struct RegistryName<T> {
+  nsid: NamespacedID,
+  phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> RegistryName<T> {
+  pub fn new(nsid: NamespacedID) -> Self {
+    Self {
+      nsid,
+      phantom: PhantomData,
+    }
+  }
+
+  pub fn get_nsid(&self) -> NamespacedID {
+    self.nsid
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T> Clone for RegistryName<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for RegistryName<T> {}
+
+impl<T> Hash for RegistryName<T> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.nsid.hash(state);
+  }
+}
+
+impl<T> PartialEq for RegistryName<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.nsid == other.nsid
+  }
+}
+
+impl<T> Eq for RegistryName<T> {}
+
+impl<T> Debug for RegistryName<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("RegistryName").field(&self.nsid).finish()
+  }
+}
+
+/// A bunch of registries of different types, each one under its own NSID like `game:blocks`.
+///
+/// Instead of a big struct with a field for every registry, make a `RegistryName` for each one
+/// and keep them all in here.
+#[derive(Default)]
+pub struct RegistryManager {
+  registries: AHashMap<NamespacedID, Box<dyn Any + Send + Sync>>,
+}
+
+impl RegistryManager {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a registry under this name.
+  ///
+  /// Fails if there's already a registry by that NSID, even if it's of a different type.
+  pub fn register<T: Send + Sync + 'static>(
+    &mut self,
+    name: RegistryName<T>,
+    registry: Registry<T>,
+  ) -> Result<(), ErrAlreadyRegistered> {
+    if self.registries.contains_key(&name.nsid) {
+      return Err(ErrAlreadyRegistered);
+    }
+    self.registries.insert(name.nsid, Box::new(registry));
+    Ok(())
+  }
+
+  /// Get the registry by this name.
+  ///
+  /// Returns `None` if there isn't one, or if the one by that NSID has a different type.
+  pub fn get<T: Send + Sync + 'static>(
+    &self,
+    name: RegistryName<T>,
+  ) -> Option<&Registry<T>> {
+    self.registries.get(&name.nsid)?.downcast_ref()
+  }
+
+  /// Get the registry by this name mutably.
+  pub fn get_mut<T: Send + Sync + 'static>(
+    &mut self,
+    name: RegistryName<T>,
+  ) -> Option<&mut Registry<T>> {
+    self.registries.get_mut(&name.nsid)?.downcast_mut()
+  }
+
+  /// Get the registry by this name, making an empty one if it isn't there yet.
+  ///
+  /// Panics if there's already a registry by that NSID of a different type.
+  pub fn get_or_default<T: Send + Sync + 'static>(
+    &mut self,
+    name: RegistryName<T>,
+  ) -> &mut Registry<T> {
+    self
+      .registries
+      .entry(name.nsid)
+      .or_insert_with(|| Box::new(Registry::<T>::new()))
+      .downcast_mut()
+      .unwrap_or_else(|| {
+        panic!("the registry {:?} has a different type", name.nsid)
+      })
+  }
+
+  /// Take the registry by this name out of the manager.
+  ///
+  /// If the one by that NSID has a different type, it's left alone and this returns `None`.
+  pub fn remove<T: Send + Sync + 'static>(
+    &mut self,
+    name: RegistryName<T>,
+  ) -> Option<Registry<T>> {
+    if !self.contains::<T>(name) {
+      return None;
+    }
+    let registry = self.registries.remove(&name.nsid)?;
+    registry.downcast().ok().map(|it| *it)
+  }
+
+  /// Return if there's a registry by this name, of this type.
+  pub fn contains<T: 'static>(&self, name: RegistryName<T>) -> bool {
+    self
+      .registries
+      .get(&name.nsid)
+      .is_some_and(|registry| registry.is::<Registry<T>>())
+  }
+
+  /// Return if there's a registry by this NSID of any type.
+  pub fn contains_nsid(&self, nsid: NamespacedID) -> bool {
+    self.registries.contains_key(&nsid)
+  }
+
+  /// Iterate over the NSIDs of every registry in here, in no particular order.
+  pub fn nsids(&self) -> impl Iterator<Item = NamespacedID> + '_ {
+    self.registries.keys().copied()
+  }
+
+  /// How many registries there are.
+  pub fn len(&self) -> usize {
+    self.registries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.registries.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[derive(Debug, PartialEq)]
+  struct Block(u32);
+
+  #[derive(Debug, PartialEq)]
+  struct Item(&'static str);
+
+  #[test]
+  fn registries_of_different_types_live_together() {
+    let blocks = RegistryName::<Block>::new(nsid("game:blocks"));
+    let items = RegistryName::<Item>::new(nsid("game:items"));
+    let mut manager = RegistryManager::new();
+
+    let mut block_registry = Registry::new();
+    block_registry
+      .register(Block(1), nsid("game:stone"))
+      .unwrap();
+    manager.register(blocks, block_registry).unwrap();
+    manager
+      .get_or_default(items)
+      .register(Item("pick"), nsid("game:pick"))
+      .unwrap();
+
+    assert_eq!(manager.len(), 2);
+    assert_eq!(manager.get(blocks).unwrap()[nsid("game:stone")], Block(1));
+    assert_eq!(manager.get(items).unwrap()[nsid("game:pick")], Item("pick"));
+
+    // Same NSID, wrong type
+    let wrong = RegistryName::<Item>::new(nsid("game:blocks"));
+    assert!(manager.get(wrong).is_none());
+    assert!(!manager.contains(wrong));
+    assert!(manager.contains_nsid(nsid("game:blocks")));
+    assert!(manager.remove(wrong).is_none());
+    assert!(manager.register(wrong, Registry::new()).is_err());
+
+    let removed = manager.remove(blocks).unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(manager.nsids().collect::<Vec<_>>(), [nsid("game:items")]);
+  }
+
+  #[test]
+  #[should_panic(expected = "has a different type")]
+  fn get_or_default_with_the_wrong_type_panics() {
+    let mut manager = RegistryManager::new();
+    manager.get_or_default(RegistryName::<Block>::new(nsid("game:things")));
+    manager.get_or_default(RegistryName::<Item>::new(nsid("game:things")));
+  }
+}
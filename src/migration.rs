@@ -0,0 +1,260 @@
+use std::{fmt::Debug, hash::BuildHasher, io::BufRead, sync::Arc};
+
+use ahash::AHashMap;
+
+use crate::{
+  LoadMigrationsError, MigrationError, NamespacedID, Registry, RegistryKey,
+};
+
+/// `Arc` so tables (and the registries they're in) can be cloned
+type MigrationHook<K> = Arc<dyn Fn(MigrationNotice<K>) + Send + Sync>;
+
+/// A list of NSIDs that were renamed, for finding things under their new names when something asks for the old ones.
+///
+/// Renames can chain: if `a` became `b` and later `b` became `c`, looking up `a` finds `c`.
+/// Install one in a registry with [`Registry::set_migrations`] and `lookup_by_nsid` and `validate_nsid`
+/// fall back on it for NSIDs that aren't registered, telling your [`MigrationTable::on_migrate`] callback every time.
+///
+/// Unlike aliases, migrations are only for names that are on their way out, so you can warn about them.
+#[derive(Clone)]
+pub struct MigrationTable<K = NamespacedID> {
+  renames: AHashMap<K, K>,
+  on_migrate: Option<MigrationHook<K>>,
+}
+
+/// An old NSID got used and was migrated to its new name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationNotice<K = NamespacedID> {
+  /// What was asked for
+  pub old: K,
+  /// What it was migrated to, at the end of any chain of renames
+  pub new: K,
+}
+
+impl<K: RegistryKey> MigrationTable<K> {
+  pub fn new() -> Self {
+    Self {
+      renames: AHashMap::new(),
+      on_migrate: None,
+    }
+  }
+
+  /// Record that `old` was renamed to `new`.
+  ///
+  /// Fails if `old` was already renamed to something, or if this would make renames go around in a loop.
+  pub fn add(&mut self, old: K, new: K) -> Result<(), MigrationError<K>> {
+    if self.renames.contains_key(&old) {
+      return Err(MigrationError::AlreadyMigrated(old));
+    }
+    let mut cycle = vec![old];
+    let mut current = new;
+    loop {
+      if current == old {
+        return Err(MigrationError::Cycle(cycle));
+      }
+      cycle.push(current);
+      match self.renames.get(&current) {
+        Some(next) => current = *next,
+        None => break,
+      }
+    }
+    self.renames.insert(old, new);
+    Ok(())
+  }
+
+  /// Follow the renames from `old` all the way to its newest name.
+  ///
+  /// Returns `None` if `old` was never renamed. This doesn't call the `on_migrate` callback.
+  pub fn migrate(&self, old: K) -> Option<K> {
+    let mut current = *self.renames.get(&old)?;
+    while let Some(next) = self.renames.get(&current) {
+      current = *next;
+    }
+    Some(current)
+  }
+
+  /// Call `hook` every time a registry uses this table to find something under an old name.
+  ///
+  /// There's only one callback; setting another replaces it.
+  pub fn on_migrate(
+    &mut self,
+    hook: impl Fn(MigrationNotice<K>) + Send + Sync + 'static,
+  ) {
+    self.on_migrate = Some(Arc::new(hook));
+  }
+
+  /// Iterate over every rename, as it was added (not following chains).
+  pub fn iter(&self) -> impl Iterator<Item = (K, K)> + '_ {
+    self.renames.iter().map(|(old, new)| (*old, *new))
+  }
+
+  /// How many renames there are.
+  pub fn len(&self) -> usize {
+    self.renames.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.renames.is_empty()
+  }
+
+  /// Change every NSID in the table, like when moving them all to a new interner.
+  pub(crate) fn map_nsids(&mut self, mut f: impl FnMut(K) -> K) {
+    self.renames = self
+      .renames
+      .drain()
+      .map(|(old, new)| (f(old), f(new)))
+      .collect();
+  }
+
+  fn notify(&self, old: K, new: K) {
+    if let Some(hook) = &self.on_migrate {
+      hook(MigrationNotice { old, new });
+    }
+  }
+}
+
+impl MigrationTable {
+  /// Read renames from `reader`, one per line, written `old:nsid -> new:nsid`.
+  ///
+  /// Blank lines and lines starting with `#` are skipped.
+  /// Line numbers in errors start from 1.
+  pub fn from_reader<R: BufRead>(
+    reader: R,
+  ) -> Result<Self, LoadMigrationsError> {
+    let mut table = Self::new();
+    for (idx, line) in reader.lines().enumerate() {
+      let line_no = idx + 1;
+      let line = line?;
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let (old, new) = line.split_once("->").ok_or_else(|| {
+        LoadMigrationsError::BadLine(line_no, line.to_owned())
+      })?;
+      let parse = |s: &str| {
+        NamespacedID::new(s.trim())
+          .map_err(|err| LoadMigrationsError::InvalidNsid(line_no, err))
+      };
+      let (old, new) = (parse(old)?, parse(new)?);
+      table
+        .add(old, new)
+        .map_err(|err| LoadMigrationsError::Migration(line_no, err))?;
+    }
+    Ok(table)
+  }
+}
+
+impl<K: RegistryKey> Default for MigrationTable<K> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<K: Debug> Debug for MigrationTable<K> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("MigrationTable")
+      .field("renames", &self.renames)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Registry<T, K, S> {
+  /// Use this table to find things under their new names when an NSID isn't registered.
+  ///
+  /// Returns the table that was installed before, if there was one.
+  pub fn set_migrations(
+    &mut self,
+    table: MigrationTable<K>,
+  ) -> Option<MigrationTable<K>> {
+    self.migrations.replace(table)
+  }
+
+  /// Get the migration table this registry falls back on, if there is one.
+  pub fn migrations(&self) -> Option<&MigrationTable<K>> {
+    self.migrations.as_ref()
+  }
+
+  /// Stop using a migration table, and hand it back.
+  pub fn take_migrations(&mut self) -> Option<MigrationTable<K>> {
+    self.migrations.take()
+  }
+
+  /// Find what an unregistered NSID was renamed to, if it was, and tell the table's callback about it.
+  pub(crate) fn migrate_nsid(&self, old: K) -> Option<K> {
+    let table = self.migrations.as_ref()?;
+    let new = table.migrate(old)?;
+    table.notify(old, new);
+    Some(new)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn chained_renames_resolve_and_notify() {
+    let mut table = MigrationTable::from_reader(
+      "# renamed in 1.2\ngame:rock -> game:stone\n\ngame:pebble -> game:rock\n"
+        .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.migrate(nsid("game:pebble")), Some(nsid("game:stone")));
+    assert_eq!(table.migrate(nsid("game:stone")), None);
+
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let seen = notices.clone();
+    table.on_migrate(move |notice| seen.lock().unwrap().push(notice));
+
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    registry.set_migrations(table);
+    assert_eq!(registry.validate_nsid(nsid("game:pebble")), Some(stone));
+    assert_eq!(registry.lookup_by_nsid(nsid("game:rock")), Some(&1));
+    assert_eq!(registry.lookup_by_nsid(nsid("game:stone")), Some(&1));
+    assert_eq!(
+      *notices.lock().unwrap(),
+      [
+        MigrationNotice {
+          old: nsid("game:pebble"),
+          new: nsid("game:stone")
+        },
+        MigrationNotice {
+          old: nsid("game:rock"),
+          new: nsid("game:stone")
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn bad_renames_are_refused() {
+    let mut table = MigrationTable::new();
+    table.add(nsid("game:a"), nsid("game:b")).unwrap();
+    table.add(nsid("game:b"), nsid("game:c")).unwrap();
+    assert!(matches!(
+      table.add(nsid("game:a"), nsid("game:d")),
+      Err(MigrationError::AlreadyMigrated(old)) if old == nsid("game:a")
+    ));
+    match table.add(nsid("game:c"), nsid("game:a")) {
+      Err(MigrationError::Cycle(cycle)) => {
+        assert_eq!(cycle, [nsid("game:c"), nsid("game:a"), nsid("game:b")])
+      }
+      other => panic!("expected a cycle, got {:?}", other),
+    }
+    assert_eq!(table.len(), 2);
+
+    let err =
+      MigrationTable::from_reader("game:a -> game:b\noops\n".as_bytes())
+        .unwrap_err();
+    assert!(matches!(err, LoadMigrationsError::BadLine(2, _)));
+  }
+}
@@ -1,13 +1,15 @@
 use std::{
   fmt::{Debug, Display},
+  io::BufRead,
   str::FromStr,
-  sync::RwLock,
 };
 
-use lasso::{Rodeo, Spur};
-use once_cell::sync::Lazy;
+use lasso::{Spur, ThreadedRodeo};
+use once_cell::sync::{Lazy, OnceCell};
 
-use crate::{InvalidNamespace, InvalidPath, NSIDParseError};
+use crate::{
+  InvalidNamespace, InvalidPath, NSIDParseError, NsidRules, PreinternError,
+};
 
 /// Light-weight friendly-printable handle to an entry in a registry.
 ///
@@ -18,20 +20,64 @@ pub struct NamespacedID {
   path: Spur,
 }
 
-static NSID_INTERNER: Lazy<RwLock<Rodeo>> =
-  Lazy::new(|| RwLock::new(Rodeo::new()));
+/// What `NamespacedID::parse_lenient` had to do to a string to make it into a valid NSID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NsidNormalization {
+  /// How many uppercase letters were lowercased
+  pub lowercased: usize,
+  /// How many spaces were turned into underscores
+  pub spaces_replaced: usize,
+  /// Every char that was thrown out, in order
+  pub removed: Vec<char>,
+}
+
+impl NsidNormalization {
+  /// Return if the string was already a valid NSID and nothing had to change.
+  pub fn is_unchanged(&self) -> bool {
+    self.lowercased == 0 && self.spaces_replaced == 0 && self.removed.is_empty()
+  }
+
+  fn normalize(&mut self, s: &str, is_valid: fn(char) -> bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+      if c == ' ' {
+        self.spaces_replaced += 1;
+        out.push('_');
+      } else if c.is_ascii_uppercase() {
+        self.lowercased += 1;
+        out.push(c.to_ascii_lowercase());
+      } else if is_valid(c) {
+        out.push(c);
+      } else {
+        self.removed.push(c);
+      }
+    }
+    out
+  }
+}
+
+/// Safe to intern into from lots of threads at once, so making NSIDs never waits on a lock.
+static NSID_INTERNER: Lazy<ThreadedRodeo> = Lazy::new(ThreadedRodeo::new);
+
+/// Intern a string into `NSID_INTERNER`, and don't hand back the key until it resolves.
+///
+/// `ThreadedRodeo` lets other threads see a new key a moment before the string behind it is stored,
+/// so a thread interning the same string at the same time could get a key that panics in `resolve`.
+fn intern(s: &str) -> Result<Spur, lasso::LassoError> {
+  let key = NSID_INTERNER.try_get_or_intern(s)?;
+  while NSID_INTERNER.try_resolve(&key).is_none() {
+    std::thread::yield_now();
+  }
+  Ok(key)
+}
 
 impl NamespacedID {
   pub fn is_valid_namespace_char(chr: char) -> bool {
-    ('a'..='z').contains(&chr)
-      || ('0'..='9').contains(&chr)
-      || "-_".contains(chr)
+    chr.is_ascii_lowercase() || chr.is_ascii_digit() || "-_".contains(chr)
   }
 
   pub fn is_valid_path_char(chr: char) -> bool {
-    ('a'..='z').contains(&chr)
-      || ('0'..='9').contains(&chr)
-      || "./-_".contains(chr)
+    chr.is_ascii_lowercase() || chr.is_ascii_digit() || "./-_".contains(chr)
   }
 
   pub fn check_namespace<S: AsRef<str>>(s: S) -> Result<(), InvalidNamespace> {
@@ -60,6 +106,32 @@ impl NamespacedID {
     Ok(())
   }
 
+  /// Check if this is a valid `namespace:path` string, without interning anything.
+  ///
+  /// This is a `const fn`, which is how [`nsid!`](crate::nsid!) checks its NSIDs at compile time.
+  pub const fn is_valid(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() && bytes[idx] != b':' {
+      if !is_namespace_byte(bytes[idx]) {
+        return false;
+      }
+      idx += 1;
+    }
+    if idx == 0 || idx == bytes.len() || idx + 1 == bytes.len() {
+      // Empty namespace, no colon, or empty path
+      return false;
+    }
+    idx += 1;
+    while idx < bytes.len() {
+      if !is_path_byte(bytes[idx]) {
+        return false;
+      }
+      idx += 1;
+    }
+    true
+  }
+
   /// Make a new NSID.
   ///
   /// This is just a wrapper around FromStr.
@@ -78,48 +150,272 @@ impl NamespacedID {
     let namespace = namespace.as_ref();
     let path = path.as_ref();
 
+    // Validate both halves before touching the interner,
+    // so a bad path doesn't leave the namespace interned forever.
     NamespacedID::check_namespace(namespace)?;
-    NamespacedID::check_path(namespace)?;
+    NamespacedID::check_path(path)?;
 
-    let mut interner = NSID_INTERNER.try_write()?;
-    let ns = interner.get_or_intern(namespace);
-    let p = interner.get_or_intern(path);
+    let ns = intern(namespace)?;
+    let p = intern(path)?;
     Ok(Self {
       namespace: ns,
       path: p,
     })
   }
 
+  /// Make a new NSID, checking it against custom rules instead of the usual ones.
+  pub fn new_with_rules(
+    s: &str,
+    rules: &NsidRules,
+  ) -> Result<Self, NSIDParseError> {
+    let (namespace, path) = rules.split_checked(s)?;
+
+    let ns = intern(namespace)?;
+    let p = intern(path)?;
+    Ok(Self {
+      namespace: ns,
+      path: p,
+    })
+  }
+
+  /// Make a new NSID without checking that it's valid, for strings you already know are fine
+  /// (like your own hard-coded constants).
+  ///
+  /// The string is split at the first `:`; if there isn't one, the whole thing becomes the path
+  /// and the namespace is empty.
+  ///
+  /// # Safety
+  ///
+  /// This can't corrupt memory, but the rest of the crate assumes every NSID is valid.
+  /// Passing a string that `NamespacedID::new` would reject gets you a weird NSID that
+  /// won't round-trip through `Display` and `FromStr`, so only use this on input you trust.
+  pub unsafe fn new_unchecked(s: &str) -> NamespacedID {
+    let (namespace, path) = s.split_once(':').unwrap_or(("", s));
+    NamespacedID::from_parts_unchecked(namespace, path)
+  }
+
+  /// Intern a namespace and path as they are, without checking them against any rules.
+  pub(crate) fn from_parts_unchecked(namespace: &str, path: &str) -> Self {
+    Self {
+      namespace: intern(namespace).expect("the NSID interner is full"),
+      path: intern(path).expect("the NSID interner is full"),
+    }
+  }
+
+  /// Make a new NSID, putting it in `default_namespace` if it doesn't say which namespace it's in.
+  ///
+  /// So `stone` with the default namespace `minecraft` is `minecraft:stone`, but `mymod:stone` stays as it is.
+  pub fn parse_with_default_namespace(
+    s: &str,
+    default_namespace: &str,
+  ) -> Result<NamespacedID, NSIDParseError> {
+    if s.contains(':') {
+      s.parse()
+    } else {
+      NamespacedID::new_from_parts(default_namespace, s)
+    }
+  }
+
+  /// Make a new NSID out of a messy string, fixing it up instead of failing wherever possible.
+  ///
+  /// Uppercase letters are lowercased, spaces become underscores, and any other char that isn't allowed
+  /// (including any `:` after the first) is dropped. This still fails if there's no `:`,
+  /// or if nothing is left of the namespace or path afterwards.
+  ///
+  /// Along with the NSID, you get a report of what had to change to make it.
+  pub fn parse_lenient(
+    s: &str,
+  ) -> Result<(NamespacedID, NsidNormalization), NSIDParseError> {
+    let (namespace, path) =
+      s.split_once(':').ok_or(NSIDParseError::NoSeparator)?;
+    let mut report = NsidNormalization::default();
+    let namespace =
+      report.normalize(namespace, NamespacedID::is_valid_namespace_char);
+    let path = report.normalize(path, NamespacedID::is_valid_path_char);
+    let nsid = NamespacedID::new_from_parts(namespace, path)?;
+    Ok((nsid, report))
+  }
+
+  /// Make a new NSID, cleaning up redundant slashes in the path first.
+  ///
+  /// Repeated `/`s are collapsed into one and leading/trailing `/`s are stripped,
+  /// so `mod:a//b/` and `mod:a/b` make the same NSID.
+  /// Everything else is validated just like `FromStr`; byte indices in errors refer to the cleaned string.
+  pub fn canonicalize(s: &str) -> Result<NamespacedID, NSIDParseError> {
+    let (namespace, path) =
+      s.split_once(':').ok_or(NSIDParseError::NoSeparator)?;
+    let path = path
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect::<Vec<_>>()
+      .join("/");
+    format!("{}:{}", namespace, path).parse()
+  }
+
+  /// Read NSIDs from `reader`, one per line, and intern all of them up front.
+  ///
+  /// Everything is validated before anything is interned,
+  /// so later calls to `new` with these strings don't have to add anything to the interner.
+  /// Blank lines are skipped. Returns how many NSIDs were read.
+  pub fn preintern_from_reader<R: BufRead>(
+    reader: R,
+  ) -> Result<usize, PreinternError> {
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+      let line = line?;
+      if !line.trim().is_empty() {
+        lines.push(line);
+      }
+    }
+    let parts = lines
+      .iter()
+      .map(|line| NamespacedID::split_checked(line.trim()))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    for (namespace, path) in &parts {
+      intern(namespace).map_err(NSIDParseError::from)?;
+      intern(path).map_err(NSIDParseError::from)?;
+    }
+    Ok(parts.len())
+  }
+
+  /// Split a string into its namespace and path, checking that both are valid.
+  pub(crate) fn split_checked(s: &str) -> Result<(&str, &str), NSIDParseError> {
+    let (namespace, path) =
+      s.split_once(':').ok_or(NSIDParseError::NoSeparator)?;
+    NamespacedID::check_namespace(namespace)?;
+    NamespacedID::check_path(path).map_err(|e| {
+      if let InvalidPath::BadChar(idx, c) = e {
+        // add the namespace and the colon
+        InvalidPath::BadChar(namespace.len() + 1 + idx, c)
+      } else {
+        e
+      }
+    })?;
+    Ok((namespace, path))
+  }
+
   /// Get this NSID's namespace
   pub fn namespace(&self) -> String {
-    let interner = NSID_INTERNER.try_read().unwrap();
-    interner.resolve(&self.namespace).to_owned()
+    self.resolve().0.to_owned()
   }
 
   /// Get this NSID's path
   pub fn path(&self) -> String {
-    let interner = NSID_INTERNER.try_read().unwrap();
-    interner.resolve(&self.path).to_owned()
+    self.resolve().1.to_owned()
+  }
+
+  /// Borrow this NSID's namespace and path straight out of the interner, without allocating.
+  ///
+  /// Interned strings live as long as the program does, so these are `'static`.
+  pub fn resolve(&self) -> (&'static str, &'static str) {
+    let interner: &'static ThreadedRodeo = &NSID_INTERNER;
+    (
+      interner.resolve(&self.namespace),
+      interner.resolve(&self.path),
+    )
+  }
+
+  /// Call `f` with this NSID's namespace and path, without allocating.
+  pub fn with_str<R>(&self, f: impl FnOnce(&str, &str) -> R) -> R {
+    let (namespace, path) = self.resolve();
+    f(namespace, path)
   }
 
   /// Decompose this into a namespace and path
   pub fn dissolve(&self) -> (String, String) {
     (self.namespace(), self.path())
   }
+
+  /// Check if this NSID has the given namespace and path,
+  /// without interning (or even validating) the strings you're comparing against.
+  pub fn components_eq(&self, namespace: &str, path: &str) -> bool {
+    self.resolve() == (namespace, path)
+  }
+
+  /// Iterate over the `/`-separated pieces of this NSID's path.
+  pub fn segments(&self) -> impl Iterator<Item = &'static str> {
+    self.resolve().1.split('/')
+  }
+
+  /// Get the last `/`-separated piece of this NSID's path, like `stone` in `mod:block/stone`.
+  pub fn file_name(&self) -> &'static str {
+    let path = self.resolve().1;
+    path.rsplit('/').next().unwrap_or(path)
+  }
+
+  /// Chop the last piece off this NSID's path, so `mod:block/stone` becomes `mod:block`.
+  ///
+  /// Returns `None` if the path is only one piece.
+  pub fn parent(&self) -> Option<NamespacedID> {
+    let (namespace, path) = self.resolve();
+    let (parent, _) = path.rsplit_once('/')?;
+    NamespacedID::new_from_parts(namespace, parent).ok()
+  }
+
+  /// Add another piece to the end of this NSID's path, so `mod:block` joined with `stone` is `mod:block/stone`.
+  ///
+  /// Slashes on either end of `segment` are dropped. If that leaves nothing, it's an `InvalidPath::Empty` error.
+  pub fn join(&self, segment: &str) -> Result<NamespacedID, NSIDParseError> {
+    let (namespace, path) = self.resolve();
+    let segment = segment.trim_matches('/');
+    if segment.is_empty() {
+      Err(InvalidPath::Empty)?;
+    }
+    NamespacedID::new_from_parts(namespace, format!("{}/{}", path, segment))
+  }
+
+  /// Put something in front of this NSID's path, so `mod:stone` with the prefix `textures/block`
+  /// is `mod:textures/block/stone`.
+  ///
+  /// Like with `join`, an empty prefix (or one that's only slashes) is an `InvalidPath::Empty` error.
+  pub fn with_prefix(
+    &self,
+    prefix: &str,
+  ) -> Result<NamespacedID, NSIDParseError> {
+    let (namespace, path) = self.resolve();
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+      Err(InvalidPath::Empty)?;
+    }
+    NamespacedID::new_from_parts(namespace, format!("{}/{}", prefix, path))
+  }
+
+  /// How many chars (not bytes) this takes up when printed as `namespace:path`.
+  ///
+  /// Handy for padding columns of NSIDs.
+  pub fn display_width(&self) -> usize {
+    let (n, p) = self.resolve();
+    n.chars().count() + 1 + p.chars().count()
+  }
 }
 
 impl Display for NamespacedID {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    let interner = NSID_INTERNER.try_read().unwrap();
-    let n = interner.resolve(&self.namespace);
-    let p = interner.resolve(&self.path);
+    let (n, p) = self.resolve();
     write!(f, "{}:{}", n, p)
   }
 }
 
 impl Debug for NamespacedID {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    <NamespacedID as Display>::fmt(&self, f)
+    <NamespacedID as Display>::fmt(self, f)
+  }
+}
+
+/// NSIDs sort by namespace, then by path, comparing the actual strings.
+impl PartialOrd for NamespacedID {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for NamespacedID {
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    if self == other {
+      return std::cmp::Ordering::Equal;
+    }
+    self.resolve().cmp(&other.resolve())
   }
 }
 
@@ -127,26 +423,10 @@ impl FromStr for NamespacedID {
   type Err = NSIDParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let (namespace, maybe_path) = match s.split_once(':') {
-      None => return Err(NSIDParseError::NoSeparator),
-      Some((ns, path)) => {
-        NamespacedID::check_namespace(ns)?;
-        (String::from(ns), path)
-      }
-    };
-    let ns_char_len = namespace.chars().count();
-    NamespacedID::check_path(maybe_path).map_err(|e| {
-      if let InvalidPath::BadChar(idx, c) = e {
-        // add the namespace, the colon, and the char
-        InvalidPath::BadChar(ns_char_len + 1 + idx, c)
-      } else {
-        e
-      }
-    })?;
+    let (namespace, path) = NamespacedID::split_checked(s)?;
 
-    let mut interner = NSID_INTERNER.try_write()?;
-    let ns = interner.get_or_intern(namespace);
-    let p = interner.get_or_intern(maybe_path);
+    let ns = intern(namespace)?;
+    let p = intern(path)?;
     Ok(Self {
       namespace: ns,
       path: p,
@@ -154,7 +434,531 @@ impl FromStr for NamespacedID {
   }
 }
 
+/// An NSID from a tag file or other data, where a leading `#` means it names a category instead of an entry.
+///
+/// ```
+/// use regigigas::{nsid, EntryOrTag};
+///
+/// let tag = EntryOrTag::parse("#game:ores").unwrap();
+/// assert_eq!(tag, EntryOrTag::Tag(nsid("game:ores")));
+/// assert_eq!(tag.to_string(), "#game:ores");
+///
+/// let entry = EntryOrTag::parse("game:stone").unwrap();
+/// assert_eq!(entry, EntryOrTag::Entry(nsid("game:stone")));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryOrTag {
+  Entry(NamespacedID),
+  Tag(NamespacedID),
+}
+
+impl EntryOrTag {
+  pub fn parse(s: &str) -> Result<Self, NSIDParseError> {
+    s.parse()
+  }
+
+  /// Get the NSID, whichever kind it is.
+  pub fn nsid(&self) -> NamespacedID {
+    match self {
+      EntryOrTag::Entry(nsid) | EntryOrTag::Tag(nsid) => *nsid,
+    }
+  }
+
+  pub fn is_tag(&self) -> bool {
+    matches!(self, EntryOrTag::Tag(_))
+  }
+}
+
+impl FromStr for EntryOrTag {
+  type Err = NSIDParseError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.strip_prefix('#') {
+      Some(tag) => tag.parse().map(EntryOrTag::Tag),
+      None => s.parse().map(EntryOrTag::Entry),
+    }
+  }
+}
+
+/// Writes tags with their `#`, so it parses back the same.
+impl Display for EntryOrTag {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      EntryOrTag::Entry(nsid) => write!(f, "{}", nsid),
+      EntryOrTag::Tag(nsid) => write!(f, "#{}", nsid),
+    }
+  }
+}
+
+// Every valid char is ASCII, so checking byte by byte is fine
+const fn is_namespace_byte(b: u8) -> bool {
+  b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_'
+}
+
+const fn is_path_byte(b: u8) -> bool {
+  is_namespace_byte(b) || b == b'.' || b == b'/'
+}
+
 /// Convenience function to create and unwrap an NSID
 pub fn nsid(s: impl AsRef<str>) -> NamespacedID {
   NamespacedID::new(s).unwrap()
 }
+
+/// Make an NSID out of a string literal, checking that it's valid at compile time.
+///
+/// ```
+/// use regigigas::nsid;
+///
+/// let stone = nsid!("minecraft:stone");
+/// assert_eq!(stone, nsid("minecraft:stone"));
+/// ```
+///
+/// A typo is a build error instead of a panic:
+///
+/// ```compile_fail
+/// let stone = regigigas::nsid!("minecraft:Stone");
+/// ```
+///
+/// The NSID is only interned the first time each `nsid!` runs; after that it's just a copy.
+#[macro_export]
+macro_rules! nsid {
+  ($nsid:literal) => {{
+    const _: () = ::core::assert!(
+      $crate::NamespacedID::is_valid($nsid),
+      "invalid NSID literal"
+    );
+    static NSID: $crate::__private::Lazy<$crate::NamespacedID> =
+      $crate::__private::Lazy::new(|| {
+        // SAFETY: checked at compile time above
+        unsafe { $crate::NamespacedID::new_unchecked($nsid) }
+      });
+    *NSID
+  }};
+}
+
+/// An NSID that's only parsed and interned the first time it's used, for putting in `static`s.
+///
+/// ```
+/// use regigigas::{lazy_nsid, nsid, LazyNsid};
+///
+/// pub static STONE: LazyNsid = lazy_nsid!("game:stone");
+///
+/// assert_eq!(*STONE, nsid("game:stone"));
+/// ```
+///
+/// The string is checked when the `LazyNsid` is made, so in a `static` an invalid one is a build error.
+pub struct LazyNsid {
+  s: &'static str,
+  cell: OnceCell<NamespacedID>,
+}
+
+impl LazyNsid {
+  /// Panics if `s` isn't a valid NSID, which is a build error if this is in a `static` or `const`.
+  pub const fn new(s: &'static str) -> Self {
+    assert!(NamespacedID::is_valid(s), "invalid NSID");
+    Self {
+      s,
+      cell: OnceCell::new(),
+    }
+  }
+
+  /// Get the NSID, interning it if this is the first time.
+  pub fn get(&self) -> NamespacedID {
+    **self
+  }
+
+  /// Get the string this was made from, without interning anything.
+  pub fn as_str(&self) -> &'static str {
+    self.s
+  }
+}
+
+impl std::ops::Deref for LazyNsid {
+  type Target = NamespacedID;
+
+  fn deref(&self) -> &Self::Target {
+    self.cell.get_or_init(|| {
+      // SAFETY: checked in `new`
+      unsafe { NamespacedID::new_unchecked(self.s) }
+    })
+  }
+}
+
+impl Display for LazyNsid {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.s)
+  }
+}
+
+impl Debug for LazyNsid {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.s)
+  }
+}
+
+/// Make a [`LazyNsid`] out of a string literal.
+#[macro_export]
+macro_rules! lazy_nsid {
+  ($nsid:literal) => {
+    $crate::LazyNsid::new($nsid)
+  };
+}
+
+/// Declare a module full of lazily-created NSIDs that all share a namespace.
+///
+/// ```
+/// regigigas::nsid_consts! {
+///   pub mod blocks {
+///     STONE = "stone";
+///     DIRT = "dirt";
+///   } under "minecraft"
+/// }
+///
+/// assert_eq!(*blocks::STONE, regigigas::nsid("minecraft:stone"));
+/// ```
+///
+/// Each constant is a `Lazy<NamespacedID>`, so the string is only validated and interned once,
+/// the first time it's used. An invalid namespace or path panics at that point.
+#[macro_export]
+macro_rules! nsid_consts {
+  (
+    $(#[$meta:meta])*
+    $vis:vis mod $module:ident {
+      $( $(#[$item_meta:meta])* $name:ident = $path:literal; )*
+    } under $namespace:literal
+  ) => {
+    $(#[$meta])*
+    $vis mod $module {
+      $(
+        $(#[$item_meta])*
+        pub static $name: $crate::__private::Lazy<$crate::NamespacedID> =
+          $crate::__private::Lazy::new(|| {
+            $crate::NamespacedID::new_from_parts($namespace, $path).unwrap()
+          });
+      )*
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn bad_path_char(s: &str) -> (usize, char) {
+    match NamespacedID::new(s) {
+      Err(NSIDParseError::InvalidPath(InvalidPath::BadChar(idx, c))) => {
+        (idx, c)
+      }
+      other => panic!("expected a bad path char, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn errors_point_at_bytes() {
+    assert_eq!(bad_path_char("game:sto!ne"), (8, '!'));
+    let s = "game:stoné";
+    let (idx, c) = bad_path_char(s);
+    assert_eq!((idx, c), (9, 'é'));
+    assert!(s[idx..].starts_with(c));
+    assert!(matches!(
+      NamespacedID::new("gamé:stone"),
+      Err(NSIDParseError::InvalidNamespace(InvalidNamespace::BadChar(
+        3, 'é'
+      )))
+    ));
+  }
+
+  #[test]
+  fn parts_round_trip() {
+    let nsid = NamespacedID::new("game:blocks/stone").unwrap();
+    assert_eq!(nsid.resolve(), ("game", "blocks/stone"));
+    assert_eq!(nsid.to_string(), "game:blocks/stone");
+    assert_eq!(
+      NamespacedID::new_from_parts("game", "blocks/stone").unwrap(),
+      nsid
+    );
+    assert!(matches!(
+      NamespacedID::new("stone"),
+      Err(NSIDParseError::NoSeparator)
+    ));
+  }
+
+  #[test]
+  fn canonicalize_cleans_slashes() {
+    let clean = NamespacedID::new("game:blocks/stone").unwrap();
+    for messy in [
+      "game:blocks/stone",
+      "game:blocks//stone",
+      "game:/blocks/stone",
+      "game:blocks/stone/",
+      "game://blocks///stone//",
+    ] {
+      assert_eq!(NamespacedID::canonicalize(messy).unwrap(), clean);
+    }
+    assert!(NamespacedID::new("game:blocks//stone").is_ok_and(|it| it != clean));
+    assert!(matches!(
+      NamespacedID::canonicalize("game://"),
+      Err(NSIDParseError::InvalidPath(InvalidPath::Empty))
+    ));
+  }
+
+  #[test]
+  fn failed_new_from_parts_interns_nothing() {
+    let namespace = "never_interned_namespace";
+    assert!(NamespacedID::new_from_parts(namespace, "bad path!").is_err());
+    assert!(NSID_INTERNER.get(namespace).is_none());
+
+    let path = "never_interned_path";
+    assert!(NamespacedID::new_from_parts("Bad", path).is_err());
+    assert!(NSID_INTERNER.get(path).is_none());
+  }
+
+  #[test]
+  fn display_width_counts_chars() {
+    let plain = NamespacedID::new("game:stone").unwrap();
+    assert_eq!(plain.display_width(), plain.to_string().chars().count());
+
+    let rules = NsidRules {
+      extra_namespace_chars: "é".to_owned(),
+      extra_path_chars: "ü".to_owned(),
+      ..Default::default()
+    };
+    let fancy = NamespacedID::new_with_rules("café:müsli", &rules).unwrap();
+    assert_eq!(fancy.display_width(), fancy.to_string().chars().count());
+    assert_eq!(fancy.display_width(), 10);
+    assert_eq!(fancy.to_string().len(), 12);
+  }
+
+  #[test]
+  fn new_unchecked_matches_new_for_valid_input() {
+    for s in ["game:stone", "game:blocks/stone", "a-b_c:d.e"] {
+      // SAFETY: these are all valid
+      let unchecked = unsafe { NamespacedID::new_unchecked(s) };
+      assert_eq!(unchecked, NamespacedID::new(s).unwrap());
+    }
+  }
+
+  crate::nsid_consts! {
+    mod blocks {
+      STONE = "stone";
+      /// With docs
+      DIRT = "dirt/coarse";
+    } under "game"
+  }
+
+  #[test]
+  fn nsid_consts_share_a_namespace() {
+    assert_eq!(*blocks::STONE, NamespacedID::new("game:stone").unwrap());
+    assert_eq!(blocks::DIRT.resolve(), ("game", "dirt/coarse"));
+  }
+
+  #[test]
+  fn formatting_works_while_other_threads_intern() {
+    let nsid = NamespacedID::new("game:stone").unwrap();
+    std::thread::scope(|scope| {
+      for thread in 0..4 {
+        scope.spawn(move || {
+          for i in 0..500 {
+            NamespacedID::new_from_parts("busy", format!("t{}_{}", thread, i))
+              .unwrap();
+          }
+        });
+      }
+      for _ in 0..500 {
+        assert_eq!(nsid.to_string(), "game:stone");
+        assert_eq!(format!("{:?}", nsid), "game:stone");
+      }
+    });
+  }
+
+  #[test]
+  fn preintern_from_a_buffer() {
+    let text = "preintern:first\n\n  preintern:second/path  \n";
+    let count = NamespacedID::preintern_from_reader(text.as_bytes()).unwrap();
+    assert_eq!(count, 2);
+    for part in ["preintern", "first", "second/path"] {
+      assert!(
+        NSID_INTERNER.get(part).is_some(),
+        "{} wasn't interned",
+        part
+      );
+    }
+    let nsid = NamespacedID::new("preintern:second/path").unwrap();
+    assert_eq!(NSID_INTERNER.get("second/path"), Some(nsid.path));
+
+    let bad = "preintern_bad:ok\npreintern_bad:NOPE\n";
+    assert!(matches!(
+      NamespacedID::preintern_from_reader(bad.as_bytes()),
+      Err(PreinternError::InvalidNsid(_))
+    ));
+    assert!(NSID_INTERNER.get("preintern_bad").is_none());
+  }
+
+  #[test]
+  fn components_eq_compares_both_parts() {
+    let nsid = NamespacedID::new("game:blocks/stone").unwrap();
+    assert!(nsid.components_eq("game", "blocks/stone"));
+    assert!(!nsid.components_eq("game", "blocks/dirt"));
+    assert!(!nsid.components_eq("game:blocks", "stone"));
+    assert!(NSID_INTERNER.get("never_interned_query").is_none());
+    assert!(!nsid.components_eq("never_interned_query", "stone"));
+    assert!(NSID_INTERNER.get("never_interned_query").is_none());
+  }
+
+  #[test]
+  fn threads_interning_the_same_strings_agree() {
+    let parsed: Vec<Vec<NamespacedID>> = std::thread::scope(|scope| {
+      let threads: Vec<_> = (0..4)
+        .map(|_| {
+          scope.spawn(|| {
+            (0..200)
+              .map(|i| {
+                NamespacedID::new_from_parts("shared", format!("e{}", i))
+                  .unwrap()
+              })
+              .collect()
+          })
+        })
+        .collect();
+      threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    for other in &parsed[1..] {
+      assert_eq!(other, &parsed[0]);
+    }
+    assert_eq!(parsed[0][7].to_string(), "shared:e7");
+  }
+
+  #[test]
+  fn parsing_from_many_threads_never_fails() {
+    std::thread::scope(|scope| {
+      for thread in 0..8 {
+        scope.spawn(move || {
+          for i in 0..500 {
+            let text = format!("contended{}:path/{}", thread, i);
+            let nsid: NamespacedID = text.parse().unwrap();
+            assert_eq!(nsid.to_string(), text);
+          }
+        });
+      }
+    });
+  }
+
+  #[test]
+  fn nsid_macro_and_const_validation() {
+    const _: () = assert!(NamespacedID::is_valid("game:blocks/stone.png"));
+    assert!(!NamespacedID::is_valid("game"));
+    assert!(!NamespacedID::is_valid("Game:stone"));
+    assert!(!NamespacedID::is_valid("game:sto ne"));
+    assert!(!NamespacedID::is_valid(":stone"));
+
+    let make = || crate::nsid!("game:stone");
+    assert_eq!(make(), NamespacedID::new("game:stone").unwrap());
+    assert_eq!(make(), make());
+  }
+
+  #[test]
+  fn lazy_nsids_intern_on_first_use() {
+    static ORE: LazyNsid = crate::lazy_nsid!("lazy:never_touched_ore");
+
+    assert_eq!(ORE.as_str(), "lazy:never_touched_ore");
+    assert_eq!(ORE.to_string(), "lazy:never_touched_ore");
+    assert!(NSID_INTERNER.get("never_touched_ore").is_none());
+
+    assert_eq!(
+      ORE.get(),
+      NamespacedID::new("lazy:never_touched_ore").unwrap()
+    );
+    assert_eq!(*ORE, ORE.get());
+    assert!(NSID_INTERNER.get("never_touched_ore").is_some());
+  }
+
+  #[test]
+  fn nsids_sort_by_their_strings() {
+    // Interned backwards, so sorting by interner key would get this wrong
+    let order = ["ordz:a", "orda:z", "orda:b/c", "orda:b"];
+    let nsids: Vec<_> = order
+      .iter()
+      .map(|s| NamespacedID::new(s).unwrap())
+      .collect();
+    let mut sorted = nsids.clone();
+    sorted.sort();
+    let sorted: Vec<_> = sorted.iter().map(|nsid| nsid.to_string()).collect();
+    assert_eq!(sorted, ["orda:b", "orda:b/c", "orda:z", "ordz:a"]);
+
+    let map: std::collections::BTreeMap<_, _> =
+      nsids.iter().map(|nsid| (*nsid, ())).collect();
+    assert_eq!(map.keys().next().unwrap().to_string(), "orda:b");
+    assert_eq!(nsids[0].cmp(&nsids[0]), std::cmp::Ordering::Equal);
+  }
+
+  #[test]
+  fn borrowed_strings_match_the_owned_ones() {
+    let nsid = NamespacedID::new("game:blocks/stone").unwrap();
+    assert_eq!(nsid.resolve(), ("game", "blocks/stone"));
+    assert_eq!(
+      nsid.with_str(|namespace, path| namespace.len() + path.len()),
+      16
+    );
+    assert_eq!(nsid.resolve().0, nsid.namespace());
+    assert_eq!(nsid.resolve().1, nsid.path());
+  }
+
+  #[test]
+  fn path_helpers() {
+    let stone = NamespacedID::new("game:block/stone").unwrap();
+    assert_eq!(stone.segments().collect::<Vec<_>>(), ["block", "stone"]);
+    assert_eq!(stone.file_name(), "stone");
+    assert_eq!(stone.parent(), NamespacedID::new("game:block").ok());
+    assert_eq!(NamespacedID::new("game:block").unwrap().parent(), None);
+
+    let joined = stone.parent().unwrap().join("/dirt/").unwrap();
+    assert_eq!(joined.to_string(), "game:block/dirt");
+    let texture = stone.with_prefix("textures/").unwrap();
+    assert_eq!(texture.to_string(), "game:textures/block/stone");
+
+    assert!(matches!(
+      stone.join("//"),
+      Err(NSIDParseError::InvalidPath(InvalidPath::Empty))
+    ));
+    assert!(matches!(
+      stone.with_prefix(""),
+      Err(NSIDParseError::InvalidPath(InvalidPath::Empty))
+    ));
+    assert!(stone.join("Bad").is_err());
+  }
+
+  #[test]
+  fn lenient_parsing_reports_its_fixes() {
+    let (nsid, report) =
+      NamespacedID::parse_lenient("My Mod:Cool Stone!:v2").unwrap();
+    assert_eq!(nsid.to_string(), "my_mod:cool_stonev2");
+    assert_eq!(report.lowercased, 4);
+    assert_eq!(report.spaces_replaced, 2);
+    assert_eq!(report.removed, ['!', ':']);
+    assert!(!report.is_unchanged());
+
+    let (nsid, report) = NamespacedID::parse_lenient("game:stone").unwrap();
+    assert_eq!(nsid, NamespacedID::new("game:stone").unwrap());
+    assert!(report.is_unchanged());
+
+    assert!(matches!(
+      NamespacedID::parse_lenient("stone"),
+      Err(NSIDParseError::NoSeparator)
+    ));
+    assert!(NamespacedID::parse_lenient("game:!!!").is_err());
+  }
+
+  #[test]
+  fn entry_or_tag_reads_the_hash() {
+    let tag = EntryOrTag::parse("#game:ores").unwrap();
+    assert!(tag.is_tag());
+    assert_eq!(tag.nsid(), NamespacedID::new("game:ores").unwrap());
+    let entry: EntryOrTag = "game:stone".parse().unwrap();
+    assert!(!entry.is_tag());
+    assert_eq!(entry.to_string(), "game:stone");
+
+    assert!(EntryOrTag::parse("##game:ores").is_err());
+    assert!(EntryOrTag::parse("#").is_err());
+    assert!(EntryOrTag::parse("game:#ores").is_err());
+  }
+}
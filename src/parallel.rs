@@ -0,0 +1,88 @@
+//! Iterating over registries on lots of threads at once, behind the `rayon` feature.
+
+use std::hash::BuildHasher;
+
+use rayon::prelude::*;
+
+use crate::{CategoryHandle, Registry, RegistryHandle, RegistryKey};
+
+impl<T: Sync, K: RegistryKey + Send + Sync, S: BuildHasher + Default + Sync>
+  Registry<T, K, S>
+{
+  /// Iterate over everything in this registry in parallel.
+  pub fn par_iter(
+    &self,
+  ) -> impl ParallelIterator<Item = (&T, RegistryHandle<T, K>)> {
+    // The arena can only be iterated in order, so go by index and rebuild each ID
+    (0..self.arena.len())
+      .into_par_iter()
+      .filter_map(move |idx| {
+        let id = self.id_at(idx);
+        let (x, nsid) = self.arena.get(id)?.as_ref()?;
+        Some((x, RegistryHandle::new(id, *nsid)))
+      })
+  }
+
+  /// Look up all the elements in the given category in parallel.
+  pub fn par_lookup_category(
+    &self,
+    category: CategoryHandle<T, K>,
+  ) -> impl ParallelIterator<Item = (&T, RegistryHandle<T, K>)> {
+    let arena = &self.arena;
+    let set = &self.category_arena.get(category.id).unwrap().0;
+    set.par_iter().map(move |id| {
+      let (x, nsid) = arena.get(*id).unwrap().as_ref().unwrap();
+      (x, RegistryHandle::new(*id, *nsid))
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::NamespacedID;
+
+  use super::*;
+
+  #[test]
+  fn parallel_iteration_matches_serial() {
+    let mut registry = Registry::<u32>::new();
+    let mut handles = Vec::new();
+    for i in 0..1000 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      handles.push(registry.register(i, nsid.unwrap()).unwrap());
+    }
+    for handle in handles.iter().step_by(3) {
+      registry.unregister(*handle);
+    }
+    let evens = registry
+      .register_category(
+        NamespacedID::new("game:evens").unwrap(),
+        registry
+          .iter()
+          .filter(|(x, _)| *x % 2 == 0)
+          .map(|(_, handle)| handle)
+          .collect::<Vec<_>>(),
+      )
+      .unwrap();
+
+    let mut serial: Vec<_> = registry.iter().collect();
+    let mut parallel: Vec<_> = registry.par_iter().collect();
+    serial.sort_by_key(|(x, _)| **x);
+    parallel.sort_by_key(|(x, _)| **x);
+    assert_eq!(parallel, serial);
+
+    let mut parallel: Vec<_> = registry
+      .par_lookup_category(evens)
+      .map(|(x, _)| *x)
+      .collect();
+    parallel.sort();
+    let mut serial: Vec<_> =
+      registry.lookup_category(evens).map(|(x, _)| *x).collect();
+    serial.sort();
+    assert_eq!(parallel, serial);
+    assert_eq!(
+      registry.par_iter().map(|(x, _)| *x as u64).sum::<u64>(),
+      registry.iter().map(|(x, _)| *x as u64).sum::<u64>()
+    );
+  }
+}
@@ -0,0 +1,230 @@
+use std::{fmt::Display, hash::BuildHasher, str::FromStr};
+
+use crate::{
+  InvalidNamespace, InvalidPath, NSIDParseError, NamespacedID, NsidRules,
+  Registry, RegistryHandle,
+};
+
+/// A wildcard pattern that matches NSIDs, like `minecraft:ores/*` or `*:stone`.
+///
+/// - `*` matches anything within one `/`-separated piece of the path (or anything in the namespace).
+/// - `**` matches anything at all, across any number of pieces. `mod:**/stone` also matches `mod:stone`.
+/// - `?` matches any one character, except `/`.
+///
+/// The namespace and path are matched separately, so `*` never crosses the `:`.
+/// Patterns go by the default [`NsidRules`]; use [`NsidPattern::with_rules`] for NSIDs made under looser ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NsidPattern {
+  namespace: String,
+  path: String,
+}
+
+impl NsidPattern {
+  pub fn new(pattern: &str) -> Result<Self, NSIDParseError> {
+    pattern.parse()
+  }
+
+  /// Make a pattern that can have any char these rules allow in an NSID, besides the wildcards.
+  pub fn with_rules(
+    pattern: &str,
+    rules: &NsidRules,
+  ) -> Result<Self, NSIDParseError> {
+    let (namespace, path) =
+      pattern.split_once(':').ok_or(NSIDParseError::NoSeparator)?;
+    if namespace.is_empty() {
+      Err(InvalidNamespace::Empty)?;
+    }
+    for (idx, c) in namespace.char_indices() {
+      if !rules.is_valid_namespace_char(c) && !is_wildcard(c) {
+        Err(InvalidNamespace::BadChar(idx, c))?;
+      }
+    }
+    if path.is_empty() {
+      Err(InvalidPath::Empty)?;
+    }
+    for (idx, c) in path.char_indices() {
+      if !rules.is_valid_path_char(c) && !is_wildcard(c) {
+        Err(InvalidPath::BadChar(namespace.len() + 1 + idx, c))?;
+      }
+    }
+    Ok(Self {
+      namespace: namespace.to_owned(),
+      path: path.to_owned(),
+    })
+  }
+
+  /// Return if this NSID matches the pattern.
+  pub fn matches(&self, nsid: NamespacedID) -> bool {
+    let (namespace, path) = nsid.resolve();
+    glob(&self.namespace, namespace) && glob(&self.path, path)
+  }
+}
+
+impl FromStr for NsidPattern {
+  type Err = NSIDParseError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::with_rules(s, &NsidRules::default())
+  }
+}
+
+impl Display for NsidPattern {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.namespace, self.path)
+  }
+}
+
+fn is_wildcard(c: char) -> bool {
+  c == '*' || c == '?'
+}
+
+/// Match a glob pattern against a string, a char at a time.
+fn glob(pattern: &str, s: &str) -> bool {
+  let pattern: Vec<_> = pattern.chars().collect();
+  let s: Vec<_> = s.chars().collect();
+  Glob {
+    memo: vec![None; (pattern.len() + 1) * (s.len() + 1)],
+    pattern: &pattern,
+    s: &s,
+  }
+  .matches(0, 0)
+}
+
+/// Each wildcard can try every place in the rest of the string, which blows up on patterns
+/// like `a*a*a*a*b`. So remember whether each (pattern index, string index) pair matched,
+/// and never work one out twice.
+struct Glob<'a> {
+  pattern: &'a [char],
+  s: &'a [char],
+  memo: Vec<Option<bool>>,
+}
+
+impl Glob<'_> {
+  fn matches(&mut self, p: usize, i: usize) -> bool {
+    let key = p * (self.s.len() + 1) + i;
+    if let Some(done) = self.memo[key] {
+      return done;
+    }
+    let (pattern, text) = (self.pattern, self.s);
+    let out = match &pattern[p..] {
+      [] => i == text.len(),
+      ['*', '*', '/', ..] => {
+        // Matching no pieces at all, or anything ending in a slash
+        self.matches(p + 3, i)
+          || (i..text.len())
+            .filter(|&j| text[j] == '/')
+            .any(|j| self.matches(p + 3, j + 1))
+      }
+      ['*', '*', ..] => (i..=text.len()).any(|j| self.matches(p + 2, j)),
+      ['*', ..] => {
+        let segment_end = text[i..]
+          .iter()
+          .position(|&c| c == '/')
+          .map_or(text.len(), |len| i + len);
+        (i..=segment_end).any(|j| self.matches(p + 1, j))
+      }
+      ['?', ..] => match text.get(i) {
+        Some(&c) if c != '/' => self.matches(p + 1, i + 1),
+        _ => false,
+      },
+      [literal, ..] => match text.get(i) {
+        Some(c) if c == literal => self.matches(p + 1, i + 1),
+        _ => false,
+      },
+    };
+    self.memo[key] = Some(out);
+    out
+  }
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Iterate over everything whose NSID matches this pattern.
+  pub fn iter_matching<'a>(
+    &'a self,
+    pattern: &'a NsidPattern,
+  ) -> impl Iterator<Item = (&'a T, RegistryHandle<T>)> + 'a {
+    self
+      .iter()
+      .filter(move |(_, handle)| pattern.matches(handle.get_nsid()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn matches(pattern: &str, nsid: &str) -> bool {
+    NsidPattern::new(pattern)
+      .unwrap()
+      .matches(NamespacedID::new(nsid).unwrap())
+  }
+
+  #[test]
+  fn wildcards() {
+    assert!(matches("game:ores/*", "game:ores/iron"));
+    assert!(!matches("game:ores/*", "game:ores/deep/iron"));
+    assert!(matches("*:stone", "other:stone"));
+    assert!(!matches("*:stone", "game:stones"));
+    assert!(matches("game:**/stone", "game:stone"));
+    assert!(matches("game:**/stone", "game:a/b/stone"));
+    assert!(!matches("game:**/stone", "game:a/bstone"));
+    assert!(matches("game:a/**", "game:a/b/c"));
+    assert!(matches("game:st?ne", "game:stone"));
+    assert!(!matches("game:a?b", "game:a/b"));
+  }
+
+  #[test]
+  fn many_stars_stay_fast() {
+    let pattern = format!("game:{}b", "a*".repeat(30));
+    let path = "a".repeat(200);
+    assert!(!matches(&pattern, &format!("game:{}", path)));
+    let pattern = format!("game:{}", "**a".repeat(30));
+    assert!(matches(&pattern, &format!("game:{}", path)));
+  }
+
+  #[test]
+  fn errors_point_at_bytes() {
+    match NsidPattern::new("game:ores/!*") {
+      Err(NSIDParseError::InvalidPath(InvalidPath::BadChar(idx, c))) => {
+        assert_eq!((idx, c), (10, '!'))
+      }
+      other => panic!("expected a bad path char, got {:?}", other),
+    }
+    assert_eq!(
+      NsidPattern::new("*:ores/**").unwrap().to_string(),
+      "*:ores/**"
+    );
+  }
+
+  #[test]
+  fn wildcards_match_whole_chars() {
+    let rules = NsidRules {
+      extra_path_chars: "é".to_owned(),
+      ..Default::default()
+    };
+    let cafe = NamespacedID::new_with_rules("game:café", &rules).unwrap();
+    assert!(NsidPattern::new("game:caf?").unwrap().matches(cafe));
+    assert!(!NsidPattern::new("game:caf??").unwrap().matches(cafe));
+    assert!(NsidPattern::new("game:c*").unwrap().matches(cafe));
+
+    assert!(NsidPattern::new("game:café").is_err());
+    let pattern = NsidPattern::with_rules("game:?afé", &rules).unwrap();
+    assert!(pattern.matches(cafe));
+    assert!(!pattern.matches(NamespacedID::new("game:cafe").unwrap()));
+  }
+
+  #[test]
+  fn iter_matching_filters_the_registry() {
+    let mut registry = Registry::<u32>::new();
+    registry
+      .register(1, NamespacedID::new("game:ores/iron").unwrap())
+      .unwrap();
+    registry
+      .register(2, NamespacedID::new("game:stone").unwrap())
+      .unwrap();
+    let pattern = NsidPattern::new("game:ores/*").unwrap();
+    let found: Vec<_> =
+      registry.iter_matching(&pattern).map(|(x, _)| *x).collect();
+    assert_eq!(found, [1]);
+  }
+}
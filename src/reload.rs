@@ -0,0 +1,199 @@
+//! Rebuilding registries from data files while the program runs, behind the `hot-reload` feature.
+
+use std::{
+  fs,
+  hash::BuildHasher,
+  ops::Deref,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+use ahash::RandomState;
+
+use crate::{NamespacedID, Registry, RegistryKey, ReloadError};
+
+/// A registry that gets rebuilt from a directory of data files whenever they change.
+///
+/// There's no background thread: call [`ReloadableRegistry::poll`] every so often (like once a frame)
+/// and it checks whether anything in the directory was added, removed, or modified since last time.
+/// If so, the whole registry is rebuilt with your loader function, and you get told which NSIDs changed
+/// so you can throw out anything you cached about them.
+///
+/// Rebuilding makes a brand new registry, so handles from before a reload don't work after it.
+/// Hang on to NSIDs instead.
+pub struct ReloadableRegistry<T, F, K = NamespacedID, S = RandomState> {
+  dir: PathBuf,
+  loader: F,
+  registry: Registry<T, K, S>,
+  stamp: Vec<(PathBuf, SystemTime, u64)>,
+}
+
+/// What changed in a registry when it got reloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryChanges<K = NamespacedID> {
+  /// Things that are registered now but weren't before
+  pub added: Vec<K>,
+  /// Things that were registered before but aren't now
+  pub removed: Vec<K>,
+  /// Things that are registered both times, but with a different value
+  pub modified: Vec<K>,
+}
+
+impl<K> RegistryChanges<K> {
+  /// Return if nothing changed at all.
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+  }
+}
+
+impl<T, E, F, K, S> ReloadableRegistry<T, F, K, S>
+where
+  T: PartialEq,
+  F: FnMut(&Path) -> Result<Registry<T, K, S>, E>,
+  K: RegistryKey,
+  S: BuildHasher + Default,
+{
+  /// Load a registry out of `dir` with `loader`, and keep an eye on `dir` for changes.
+  pub fn new(
+    dir: impl Into<PathBuf>,
+    mut loader: F,
+  ) -> Result<Self, ReloadError<E>> {
+    let dir = dir.into();
+    let stamp = stamp_dir(&dir)?;
+    let registry = loader(&dir).map_err(ReloadError::Load)?;
+    Ok(Self {
+      dir,
+      loader,
+      registry,
+      stamp,
+    })
+  }
+
+  /// Check if anything in the directory changed since the last load, and reload if it did.
+  ///
+  /// Returns `None` if nothing on disk changed.
+  /// If the loader fails, the old registry is kept and the error is returned;
+  /// it won't be tried again until the files change again.
+  pub fn poll(&mut self) -> Result<Option<RegistryChanges<K>>, ReloadError<E>> {
+    let stamp = stamp_dir(&self.dir)?;
+    if stamp == self.stamp {
+      return Ok(None);
+    }
+    self.stamp = stamp;
+    self.reload().map(Some)
+  }
+
+  /// Reload right now, whether or not anything changed.
+  pub fn reload(&mut self) -> Result<RegistryChanges<K>, ReloadError<E>> {
+    let fresh = (self.loader)(&self.dir).map_err(ReloadError::Load)?;
+    let diff = self.registry.diff(&fresh);
+    let changes = RegistryChanges {
+      added: diff.added,
+      removed: diff.removed,
+      modified: diff.changed,
+    };
+    self.registry = fresh;
+    Ok(changes)
+  }
+
+  /// Get the registry as of the last successful load.
+  pub fn registry(&self) -> &Registry<T, K, S> {
+    &self.registry
+  }
+
+  /// Stop watching and keep the registry as it is.
+  pub fn into_inner(self) -> Registry<T, K, S> {
+    self.registry
+  }
+}
+
+impl<T, F, K, S> Deref for ReloadableRegistry<T, F, K, S> {
+  type Target = Registry<T, K, S>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.registry
+  }
+}
+
+/// Get the path, modification time, and size of every file under `dir`, in a consistent order.
+fn stamp_dir(dir: &Path) -> std::io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+  fn walk(
+    dir: &Path,
+    out: &mut Vec<(PathBuf, SystemTime, u64)>,
+  ) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+      let entry = entry?;
+      let meta = entry.metadata()?;
+      if meta.is_dir() {
+        walk(&entry.path(), out)?;
+      } else {
+        out.push((entry.path(), meta.modified()?, meta.len()));
+      }
+    }
+    Ok(())
+  }
+
+  let mut stamp = Vec::new();
+  walk(dir, &mut stamp)?;
+  stamp.sort();
+  Ok(stamp)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  /// Every file is an entry in the `game` namespace, named after the file, holding the number written in it.
+  fn load_numbers(dir: &Path) -> Result<Registry<u32>, String> {
+    let mut registry = Registry::new();
+    for entry in fs::read_dir(dir).map_err(|err| err.to_string())? {
+      let path = entry.map_err(|err| err.to_string())?.path();
+      let name = path.file_stem().unwrap().to_str().unwrap();
+      let text = fs::read_to_string(&path).map_err(|err| err.to_string())?;
+      let value = text.trim().parse().map_err(|_| format!("bad {}", name))?;
+      let nsid = NamespacedID::new_from_parts("game", name).unwrap();
+      registry.register(value, nsid).unwrap();
+    }
+    Ok(registry)
+  }
+
+  #[test]
+  fn poll_reports_what_changed() {
+    let dir = std::env::temp_dir()
+      .join(format!("regigigas-reload-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stone.txt"), "1").unwrap();
+    fs::write(dir.join("dirt.txt"), "2").unwrap();
+
+    let mut reloadable = ReloadableRegistry::new(&dir, load_numbers).unwrap();
+    assert_eq!(reloadable.len(), 2);
+    assert_eq!(reloadable.poll().unwrap(), None);
+
+    // Different lengths, so the change shows up even if the mtime doesn't
+    fs::write(dir.join("stone.txt"), "100").unwrap();
+    fs::remove_file(dir.join("dirt.txt")).unwrap();
+    fs::write(dir.join("sand.txt"), "3").unwrap();
+    let changes = reloadable.poll().unwrap().unwrap();
+    assert_eq!(changes.added, [nsid("game:sand")]);
+    assert_eq!(changes.removed, [nsid("game:dirt")]);
+    assert_eq!(changes.modified, [nsid("game:stone")]);
+    assert_eq!(reloadable[nsid("game:stone")], 100);
+
+    fs::write(dir.join("sand.txt"), "oops").unwrap();
+    match reloadable.poll() {
+      Err(ReloadError::Load(err)) => assert_eq!(err, "bad sand"),
+      other => panic!("expected a load error, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(reloadable[nsid("game:sand")], 3);
+    assert_eq!(reloadable.poll().unwrap(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(reloadable.reload().is_err());
+    assert_eq!(reloadable.into_inner().len(), 2);
+  }
+}
@@ -0,0 +1,144 @@
+use crate::{InvalidNamespace, InvalidPath, NSIDParseError};
+
+/// Which strings count as valid NSIDs, for projects whose identifiers don't quite follow Minecraft's rules.
+///
+/// The default is the usual rules: lowercase letters, digits, `_` and `-`, plus `.` and `/` in paths,
+/// with no length limit. Start from that and loosen what you need:
+///
+/// ```
+/// use regigigas::{NamespacedID, NsidRules};
+///
+/// let rules = NsidRules {
+///   allow_uppercase: true,
+///   extra_path_chars: "+".to_owned(),
+///   ..Default::default()
+/// };
+/// assert!(NamespacedID::new_with_rules("Game:C++", &rules).is_ok());
+/// assert!(NamespacedID::new("Game:C++").is_err());
+/// ```
+///
+/// Use these with `NamespacedID::new_with_rules`, or give them to an [`NsidInterner`](crate::NsidInterner).
+/// Everything else (`NamespacedID::new`, `FromStr`, serde, [`nsid!`](crate::nsid!)) still goes by the default rules,
+/// so an NSID that's only valid under looser rules won't parse back from its `Display` form that way.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NsidRules {
+  /// Allow `A-Z` in both the namespace and path
+  pub allow_uppercase: bool,
+  /// Any more chars to allow in the namespace
+  pub extra_namespace_chars: String,
+  /// Any more chars to allow in the path
+  pub extra_path_chars: String,
+  /// The longest a namespace can be, in chars
+  pub max_namespace_len: Option<usize>,
+  /// The longest a path can be, in chars
+  pub max_path_len: Option<usize>,
+}
+
+impl NsidRules {
+  pub fn is_valid_namespace_char(&self, chr: char) -> bool {
+    chr.is_ascii_lowercase()
+      || chr.is_ascii_digit()
+      || "-_".contains(chr)
+      || (self.allow_uppercase && chr.is_ascii_uppercase())
+      || self.extra_namespace_chars.contains(chr)
+  }
+
+  pub fn is_valid_path_char(&self, chr: char) -> bool {
+    chr.is_ascii_lowercase()
+      || chr.is_ascii_digit()
+      || "./-_".contains(chr)
+      || (self.allow_uppercase && chr.is_ascii_uppercase())
+      || self.extra_path_chars.contains(chr)
+  }
+
+  pub fn check_namespace(&self, s: &str) -> Result<(), InvalidNamespace> {
+    if s.is_empty() {
+      Err(InvalidNamespace::Empty)?;
+    }
+    // Chars for the length limit, but bytes for where a bad char is, like everywhere else
+    for (count, (idx, c)) in s.char_indices().enumerate() {
+      if !self.is_valid_namespace_char(c) {
+        Err(InvalidNamespace::BadChar(idx, c))?;
+      }
+      if let Some(max) = self.max_namespace_len {
+        if count >= max {
+          Err(InvalidNamespace::TooLong(max))?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  pub fn check_path(&self, s: &str) -> Result<(), InvalidPath> {
+    if s.is_empty() {
+      Err(InvalidPath::Empty)?;
+    }
+    // Chars for the length limit, but bytes for where a bad char is, like everywhere else
+    for (count, (idx, c)) in s.char_indices().enumerate() {
+      if !self.is_valid_path_char(c) {
+        Err(InvalidPath::BadChar(idx, c))?;
+      }
+      if let Some(max) = self.max_path_len {
+        if count >= max {
+          Err(InvalidPath::TooLong(max))?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Split a string into its namespace and path, checking both against these rules.
+  pub(crate) fn split_checked<'a>(
+    &self,
+    s: &'a str,
+  ) -> Result<(&'a str, &'a str), NSIDParseError> {
+    let (namespace, path) =
+      s.split_once(':').ok_or(NSIDParseError::NoSeparator)?;
+    self.check_namespace(namespace)?;
+    self.check_path(path).map_err(|e| {
+      if let InvalidPath::BadChar(idx, c) = e {
+        // add the namespace and the colon
+        InvalidPath::BadChar(namespace.len() + 1 + idx, c)
+      } else {
+        e
+      }
+    })?;
+    Ok((namespace, path))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bad_chars_are_byte_indexed_and_lengths_are_chars() {
+    let rules = NsidRules {
+      extra_path_chars: "é".to_owned(),
+      max_path_len: Some(4),
+      ..Default::default()
+    };
+    let s = "game:éé!";
+    match rules.split_checked(s) {
+      Err(NSIDParseError::InvalidPath(InvalidPath::BadChar(idx, c))) => {
+        assert_eq!((idx, c), (9, '!'));
+        assert!(s[idx..].starts_with(c));
+      }
+      other => panic!("expected a bad path char, got {:?}", other),
+    }
+
+    assert!(rules.check_path("éééé").is_ok());
+    assert!(matches!(
+      rules.check_path("ééééé"),
+      Err(InvalidPath::TooLong(4))
+    ));
+  }
+
+  #[test]
+  fn default_rules_match_nsids() {
+    let rules = NsidRules::default();
+    assert!(rules.split_checked("game:blocks/stone").is_ok());
+    assert!(rules.split_checked("Game:stone").is_err());
+    assert!(rules.split_checked("game:sto ne").is_err());
+  }
+}
@@ -0,0 +1,385 @@
+use std::{
+  collections::BTreeMap,
+  ops::{Index, IndexMut},
+};
+
+use crate::{NamespacedID, RegistryHandle, RegistryKey};
+
+/// Extra data for entries of a registry, kept next to the registry instead of inside it.
+///
+/// It's laid out by handle index like the registry is, so looking something up is indexing into a `Vec`,
+/// and it iterates in the same order the entries were registered.
+/// For data that only a few entries have, [`SparseSecondaryMap`] takes up less room.
+///
+/// Only use handles from one registry with each map. Entries that get unregistered aren't removed from here
+/// on their own, but their handles are never given out again, so they won't get mixed up with anything else.
+pub struct SecondaryMap<T, V, K = NamespacedID> {
+  slots: Vec<Option<(RegistryHandle<T, K>, V)>>,
+  len: usize,
+}
+
+impl<T, V, K: RegistryKey> SecondaryMap<T, V, K> {
+  pub fn new() -> Self {
+    Self {
+      slots: Vec::new(),
+      len: 0,
+    }
+  }
+
+  /// Make an empty map with room for entries with handle indices up to this many.
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self {
+      slots: Vec::with_capacity(capacity),
+      len: 0,
+    }
+  }
+
+  /// Set the data for this entry, handing back what was there before.
+  pub fn insert(
+    &mut self,
+    handle: RegistryHandle<T, K>,
+    value: V,
+  ) -> Option<V> {
+    let idx = handle.to_raw() as usize;
+    if idx >= self.slots.len() {
+      self.slots.resize_with(idx + 1, || None);
+    }
+    let old = self.slots[idx].replace((handle, value));
+    if old.is_none() {
+      self.len += 1;
+    }
+    old.map(|(_, value)| value)
+  }
+
+  /// Take away the data for this entry.
+  pub fn remove(&mut self, handle: RegistryHandle<T, K>) -> Option<V> {
+    let (_, value) = self.slots.get_mut(handle.to_raw() as usize)?.take()?;
+    self.len -= 1;
+    Some(value)
+  }
+
+  pub fn get(&self, handle: RegistryHandle<T, K>) -> Option<&V> {
+    let (_, value) = self.slots.get(handle.to_raw() as usize)?.as_ref()?;
+    Some(value)
+  }
+
+  pub fn get_mut(&mut self, handle: RegistryHandle<T, K>) -> Option<&mut V> {
+    let (_, value) = self.slots.get_mut(handle.to_raw() as usize)?.as_mut()?;
+    Some(value)
+  }
+
+  pub fn contains_key(&self, handle: RegistryHandle<T, K>) -> bool {
+    self.get(handle).is_some()
+  }
+
+  /// Iterate over every entry that has data, in the order they were registered.
+  pub fn iter(&self) -> impl Iterator<Item = (RegistryHandle<T, K>, &V)> {
+    self
+      .slots
+      .iter()
+      .filter_map(|slot| slot.as_ref().map(|(handle, value)| (*handle, value)))
+  }
+
+  /// Iterate over every entry that has data mutably, in the order they were registered.
+  pub fn iter_mut(
+    &mut self,
+  ) -> impl Iterator<Item = (RegistryHandle<T, K>, &mut V)> {
+    self
+      .slots
+      .iter_mut()
+      .filter_map(|slot| slot.as_mut().map(|(handle, value)| (*handle, value)))
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &V> {
+    self.iter().map(|(_, value)| value)
+  }
+
+  /// How many entries have data.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn clear(&mut self) {
+    self.slots.clear();
+    self.len = 0;
+  }
+}
+
+impl<T, V, K: RegistryKey> Default for SecondaryMap<T, V, K> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, V: Clone, K: RegistryKey> Clone for SecondaryMap<T, V, K> {
+  fn clone(&self) -> Self {
+    Self {
+      slots: self.slots.clone(),
+      len: self.len,
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> Extend<(RegistryHandle<T, K>, V)>
+  for SecondaryMap<T, V, K>
+{
+  fn extend<I: IntoIterator<Item = (RegistryHandle<T, K>, V)>>(
+    &mut self,
+    iter: I,
+  ) {
+    for (handle, value) in iter {
+      self.insert(handle, value);
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> FromIterator<(RegistryHandle<T, K>, V)>
+  for SecondaryMap<T, V, K>
+{
+  fn from_iter<I: IntoIterator<Item = (RegistryHandle<T, K>, V)>>(
+    iter: I,
+  ) -> Self {
+    let mut map = Self::new();
+    map.extend(iter);
+    map
+  }
+}
+
+/// Convenience for `get`. Panics if there's no data for the entry.
+impl<T, V, K: RegistryKey> Index<RegistryHandle<T, K>>
+  for SecondaryMap<T, V, K>
+{
+  type Output = V;
+
+  fn index(&self, index: RegistryHandle<T, K>) -> &Self::Output {
+    match self.get(index) {
+      Some(it) => it,
+      None => panic!("there's no data for {:?}", index),
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> IndexMut<RegistryHandle<T, K>>
+  for SecondaryMap<T, V, K>
+{
+  fn index_mut(&mut self, index: RegistryHandle<T, K>) -> &mut Self::Output {
+    match self.get_mut(index) {
+      Some(it) => it,
+      None => panic!("there's no data for {:?}", index),
+    }
+  }
+}
+
+/// Like [`SecondaryMap`], but for data that only a few entries have.
+///
+/// It only takes up room for the entries that have data, instead of one slot for every handle up to the biggest one.
+/// It has all the same methods, so you can switch between the two without changing anything else.
+pub struct SparseSecondaryMap<T, V, K = NamespacedID> {
+  /// By handle index, so it still iterates in registration order
+  entries: BTreeMap<u32, (RegistryHandle<T, K>, V)>,
+}
+
+impl<T, V, K: RegistryKey> SparseSecondaryMap<T, V, K> {
+  pub fn new() -> Self {
+    Self {
+      entries: BTreeMap::new(),
+    }
+  }
+
+  /// There's nothing to reserve, so this is the same as `new`. It's just here to match `SecondaryMap`.
+  pub fn with_capacity(_capacity: usize) -> Self {
+    Self::new()
+  }
+
+  /// Set the data for this entry, handing back what was there before.
+  pub fn insert(
+    &mut self,
+    handle: RegistryHandle<T, K>,
+    value: V,
+  ) -> Option<V> {
+    let old = self.entries.insert(handle.to_raw(), (handle, value));
+    old.map(|(_, value)| value)
+  }
+
+  /// Take away the data for this entry.
+  pub fn remove(&mut self, handle: RegistryHandle<T, K>) -> Option<V> {
+    let (_, value) = self.entries.remove(&handle.to_raw())?;
+    Some(value)
+  }
+
+  pub fn get(&self, handle: RegistryHandle<T, K>) -> Option<&V> {
+    let (_, value) = self.entries.get(&handle.to_raw())?;
+    Some(value)
+  }
+
+  pub fn get_mut(&mut self, handle: RegistryHandle<T, K>) -> Option<&mut V> {
+    let (_, value) = self.entries.get_mut(&handle.to_raw())?;
+    Some(value)
+  }
+
+  pub fn contains_key(&self, handle: RegistryHandle<T, K>) -> bool {
+    self.entries.contains_key(&handle.to_raw())
+  }
+
+  /// Iterate over every entry that has data, in the order they were registered.
+  pub fn iter(&self) -> impl Iterator<Item = (RegistryHandle<T, K>, &V)> {
+    self
+      .entries
+      .values()
+      .map(|(handle, value)| (*handle, value))
+  }
+
+  /// Iterate over every entry that has data mutably, in the order they were registered.
+  pub fn iter_mut(
+    &mut self,
+  ) -> impl Iterator<Item = (RegistryHandle<T, K>, &mut V)> {
+    self
+      .entries
+      .values_mut()
+      .map(|(handle, value)| (*handle, value))
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &V> {
+    self.iter().map(|(_, value)| value)
+  }
+
+  /// How many entries have data.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+  }
+}
+
+impl<T, V, K: RegistryKey> Default for SparseSecondaryMap<T, V, K> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, V: Clone, K: RegistryKey> Clone for SparseSecondaryMap<T, V, K> {
+  fn clone(&self) -> Self {
+    Self {
+      entries: self.entries.clone(),
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> Extend<(RegistryHandle<T, K>, V)>
+  for SparseSecondaryMap<T, V, K>
+{
+  fn extend<I: IntoIterator<Item = (RegistryHandle<T, K>, V)>>(
+    &mut self,
+    iter: I,
+  ) {
+    for (handle, value) in iter {
+      self.insert(handle, value);
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> FromIterator<(RegistryHandle<T, K>, V)>
+  for SparseSecondaryMap<T, V, K>
+{
+  fn from_iter<I: IntoIterator<Item = (RegistryHandle<T, K>, V)>>(
+    iter: I,
+  ) -> Self {
+    let mut map = Self::new();
+    map.extend(iter);
+    map
+  }
+}
+
+/// Convenience for `get`. Panics if there's no data for the entry.
+impl<T, V, K: RegistryKey> Index<RegistryHandle<T, K>>
+  for SparseSecondaryMap<T, V, K>
+{
+  type Output = V;
+
+  fn index(&self, index: RegistryHandle<T, K>) -> &Self::Output {
+    match self.get(index) {
+      Some(it) => it,
+      None => panic!("there's no data for {:?}", index),
+    }
+  }
+}
+
+impl<T, V, K: RegistryKey> IndexMut<RegistryHandle<T, K>>
+  for SparseSecondaryMap<T, V, K>
+{
+  fn index_mut(&mut self, index: RegistryHandle<T, K>) -> &mut Self::Output {
+    match self.get_mut(index) {
+      Some(it) => it,
+      None => panic!("there's no data for {:?}", index),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Registry;
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  fn registry() -> (Registry<u32>, Vec<RegistryHandle<u32>>) {
+    let mut registry = Registry::new();
+    let handles = ["game:stone", "game:dirt", "game:sand"]
+      .into_iter()
+      .enumerate()
+      .map(|(i, s)| registry.register(i as u32, nsid(s)).unwrap())
+      .collect();
+    (registry, handles)
+  }
+
+  #[test]
+  fn dense_map_iterates_in_registry_order() {
+    let (_, handles) = registry();
+    let mut colors = SecondaryMap::new();
+    assert_eq!(colors.insert(handles[2], "yellow"), None);
+    assert_eq!(colors.insert(handles[0], "grey"), None);
+    assert_eq!(colors.insert(handles[0], "gray"), Some("grey"));
+    assert_eq!(colors.len(), 2);
+    assert_eq!(colors.get(handles[1]), None);
+    assert_eq!(colors[handles[2]], "yellow");
+
+    let order: Vec<_> = colors.iter().map(|(handle, _)| handle).collect();
+    assert_eq!(order, [handles[0], handles[2]]);
+
+    colors[handles[2]] = "gold";
+    assert_eq!(colors.remove(handles[0]), Some("gray"));
+    assert!(!colors.contains_key(handles[0]));
+    assert_eq!(colors.values().collect::<Vec<_>>(), [&"gold"]);
+  }
+
+  #[test]
+  fn sparse_map_has_the_same_api() {
+    let (_, handles) = registry();
+    let mut special: SparseSecondaryMap<_, _> =
+      [(handles[2], 30), (handles[0], 10)].into_iter().collect();
+    assert_eq!(special.len(), 2);
+    assert_eq!(special.get(handles[1]), None);
+    *special.get_mut(handles[0]).unwrap() += 1;
+    special[handles[2]] += 1;
+
+    let all: Vec<_> = special.iter().map(|(h, v)| (h, *v)).collect();
+    assert_eq!(all, [(handles[0], 11), (handles[2], 31)]);
+
+    assert_eq!(special.remove(handles[2]), Some(31));
+    special.clear();
+    assert!(special.is_empty());
+  }
+}
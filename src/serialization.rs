@@ -0,0 +1,614 @@
+//! Serde support, behind the `serde` feature.
+//!
+//! NSIDs serialize as their `namespace:path` string, and an `EntryOrTag` has a `#` in front if it's a tag.
+//! Handles serialize as the key they were registered under, and because a handle is meaningless without
+//! the registry it came from, deserializing one goes through a seed borrowing that registry.
+//!
+//! A whole registry serializes as a map of keys to values, a map of category keys to member keys,
+//! and a map of category keys to the keys of the categories nested in them,
+//! and gets rebuilt from scratch when deserialized.
+//!
+//! A `Holder` serializes as its NSID too, but doesn't need a seed because it deserializes unresolved.
+//! A `HolderSet` is either `"#namespace:path"` for a category or a list of NSIDs.
+//!
+//! A `SyncMap` serializes as its NSIDs in order of their numbers.
+
+use std::{fmt, hash::BuildHasher, marker::PhantomData};
+
+use ahash::RandomState;
+
+use serde::{
+  de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+  ser::{SerializeMap, SerializeStruct},
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+  CategoryHandle, EntryOrTag, Holder, HolderSet, NamespacedID, Registry,
+  RegistryHandle, RegistryKey, SyncMap,
+};
+
+impl Serialize for NamespacedID {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for NamespacedID {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_str(NamespacedIDVisitor)
+  }
+}
+
+struct NamespacedIDVisitor;
+
+impl<'de> Visitor<'de> for NamespacedIDVisitor {
+  type Value = NamespacedID;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a `namespace:path` string")
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    v.parse().map_err(E::custom)
+  }
+}
+
+impl Serialize for EntryOrTag {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for EntryOrTag {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_str(EntryOrTagVisitor)
+  }
+}
+
+struct EntryOrTagVisitor;
+
+impl<'de> Visitor<'de> for EntryOrTagVisitor {
+  type Value = EntryOrTag;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a `namespace:path` or `#namespace:path` string")
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    v.parse().map_err(E::custom)
+  }
+}
+
+impl<T, K: RegistryKey + Serialize> Serialize for RegistryHandle<T, K> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.get_nsid().serialize(serializer)
+  }
+}
+
+impl<T, K: RegistryKey + Serialize> Serialize for CategoryHandle<T, K> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.get_nsid().serialize(serializer)
+  }
+}
+
+impl<T, K: RegistryKey + Serialize> Serialize for Holder<T, K> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.get_nsid().serialize(serializer)
+  }
+}
+
+impl<'de, T, K: RegistryKey + Deserialize<'de>> Deserialize<'de>
+  for Holder<T, K>
+{
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    K::deserialize(deserializer).map(Holder::Unresolved)
+  }
+}
+
+impl<T> Serialize for HolderSet<T> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      HolderSet::List(holders) => holders.serialize(serializer),
+      HolderSet::Category(nsid) => {
+        serializer.collect_str(&EntryOrTag::Tag(*nsid))
+      }
+    }
+  }
+}
+
+impl<'de, T> Deserialize<'de> for HolderSet<T> {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(HolderSetVisitor(PhantomData))
+  }
+}
+
+struct HolderSetVisitor<T>(PhantomData<fn() -> T>);
+
+impl<'de, T> Visitor<'de> for HolderSetVisitor<T> {
+  type Value = HolderSet<T>;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a `#namespace:path` category, or a list of NSIDs")
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    match EntryOrTag::parse(v).map_err(E::custom)? {
+      EntryOrTag::Tag(category) => Ok(HolderSet::Category(category)),
+      EntryOrTag::Entry(nsid) => {
+        Ok(HolderSet::List(vec![Holder::Unresolved(nsid)]))
+      }
+    }
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(
+    self,
+    mut seq: A,
+  ) -> Result<Self::Value, A::Error> {
+    let mut holders = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(holder) = seq.next_element()? {
+      holders.push(holder);
+    }
+    Ok(HolderSet::List(holders))
+  }
+}
+
+/// Deserializes a [`RegistryHandle`] from its key, checking that the key is registered in the registry.
+///
+/// Get one with [`Registry::handle_seed`].
+pub struct HandleSeed<'a, T, K = NamespacedID, H = RandomState> {
+  registry: &'a Registry<T, K, H>,
+}
+
+impl<'de, 'a, T, K, H> DeserializeSeed<'de> for HandleSeed<'a, T, K, H>
+where
+  K: RegistryKey + Deserialize<'de>,
+  H: BuildHasher + Default,
+{
+  type Value = RegistryHandle<T, K>;
+
+  fn deserialize<D: Deserializer<'de>>(
+    self,
+    deserializer: D,
+  ) -> Result<Self::Value, D::Error> {
+    let key = K::deserialize(deserializer)?;
+    self.registry.validate_nsid(key).ok_or_else(|| {
+      de::Error::custom(format_args!("{:?} is not registered", key))
+    })
+  }
+}
+
+/// Deserializes a [`CategoryHandle`] from its key, checking that the key is a category in the registry.
+///
+/// Get one with [`Registry::category_seed`].
+pub struct CategorySeed<'a, T, K = NamespacedID, H = RandomState> {
+  registry: &'a Registry<T, K, H>,
+}
+
+impl<'de, 'a, T, K, H> DeserializeSeed<'de> for CategorySeed<'a, T, K, H>
+where
+  K: RegistryKey + Deserialize<'de>,
+  H: BuildHasher + Default,
+{
+  type Value = CategoryHandle<T, K>;
+
+  fn deserialize<D: Deserializer<'de>>(
+    self,
+    deserializer: D,
+  ) -> Result<Self::Value, D::Error> {
+    let key = K::deserialize(deserializer)?;
+    self.registry.validate_category_nsid(key).ok_or_else(|| {
+      de::Error::custom(format_args!("{:?} is not a registered category", key))
+    })
+  }
+}
+
+// Manual impls cause they only hold a reference
+impl<'a, T, K, H> Clone for HandleSeed<'a, T, K, H> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T, K, H> Copy for HandleSeed<'a, T, K, H> {}
+
+impl<'a, T, K, H> Clone for CategorySeed<'a, T, K, H> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<'a, T, K, H> Copy for CategorySeed<'a, T, K, H> {}
+
+impl<T, K: RegistryKey, H: BuildHasher + Default> Registry<T, K, H> {
+  /// Get a seed to deserialize a [`RegistryHandle`] against this registry.
+  ///
+  /// Deserialization fails if the key isn't registered.
+  pub fn handle_seed(&self) -> HandleSeed<'_, T, K, H> {
+    HandleSeed { registry: self }
+  }
+
+  /// Get a seed to deserialize a [`CategoryHandle`] against this registry.
+  ///
+  /// Deserialization fails if the key isn't a registered category.
+  pub fn category_seed(&self) -> CategorySeed<'_, T, K, H> {
+    CategorySeed { registry: self }
+  }
+}
+
+/// Only the entries, categories, and nested categories are written out.
+/// Aliases, migrations, hooks, the default namespace, and numbers from `apply_id_map` aren't,
+/// so set those up again after deserializing.
+impl<T: Serialize, K: RegistryKey + Serialize, H: BuildHasher + Default>
+  Serialize for Registry<T, K, H>
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut registry = serializer.serialize_struct("Registry", 3)?;
+    registry.serialize_field("entries", &EntriesRepr(self))?;
+    registry.serialize_field("categories", &CategoriesRepr(self))?;
+    registry.serialize_field("subcategories", &SubcategoriesRepr(self))?;
+    registry.end()
+  }
+}
+
+struct EntriesRepr<'a, T, K, H>(&'a Registry<T, K, H>);
+
+impl<'a, T: Serialize, K: RegistryKey + Serialize, H: BuildHasher + Default>
+  Serialize for EntriesRepr<'a, T, K, H>
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(self.0.nsid_map.len()))?;
+    for (_, (x, nsid)) in self.0.entries() {
+      map.serialize_entry(nsid, x)?;
+    }
+    map.end()
+  }
+}
+
+struct CategoriesRepr<'a, T, K, H>(&'a Registry<T, K, H>);
+
+impl<'a, T, K: RegistryKey + Serialize, H: BuildHasher + Default> Serialize
+  for CategoriesRepr<'a, T, K, H>
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let registry = self.0;
+    let mut map =
+      serializer.serialize_map(Some(registry.category_arena.len()))?;
+    for (_, (set, nsid)) in registry.category_arena.iter() {
+      // Sort by registration order so the output doesn't depend on hash order
+      let mut ids: Vec<_> = set.iter().copied().collect();
+      ids.sort();
+      let members: Vec<_> =
+        ids.into_iter().map(|id| registry.slot(id).1).collect();
+      map.serialize_entry(nsid, &members)?;
+    }
+    map.end()
+  }
+}
+
+struct SubcategoriesRepr<'a, T, K, H>(&'a Registry<T, K, H>);
+
+impl<'a, T, K: RegistryKey + Serialize, H: BuildHasher + Default> Serialize
+  for SubcategoriesRepr<'a, T, K, H>
+{
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let registry = self.0;
+    let mut parents: Vec<_> = registry
+      .category_children
+      .iter()
+      .filter(|(_, children)| !children.is_empty())
+      .collect();
+    parents.sort_by_key(|(id, _)| **id);
+    let mut map = serializer.serialize_map(Some(parents.len()))?;
+    for (parent, children) in parents {
+      let mut ids: Vec<_> = children.iter().copied().collect();
+      ids.sort();
+      let nsid = |id| registry.category_arena.get(id).unwrap().1;
+      let children: Vec<_> = ids.into_iter().map(nsid).collect();
+      map.serialize_entry(&nsid(*parent), &children)?;
+    }
+    map.end()
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>, K: Deserialize<'de>"))]
+struct RegistryRepr<T, K> {
+  #[serde(deserialize_with = "ordered_map")]
+  entries: Vec<(K, T)>,
+  #[serde(default, deserialize_with = "ordered_map")]
+  categories: Vec<(K, Vec<K>)>,
+  #[serde(default, deserialize_with = "ordered_map")]
+  subcategories: Vec<(K, Vec<K>)>,
+}
+
+/// Deserialize a map into a list of pairs in the order they were written.
+fn ordered_map<'de, D, A, B>(deserializer: D) -> Result<Vec<(A, B)>, D::Error>
+where
+  D: Deserializer<'de>,
+  A: Deserialize<'de>,
+  B: Deserialize<'de>,
+{
+  struct OrderedMapVisitor<A, B>(PhantomData<(A, B)>);
+
+  impl<'de, A: Deserialize<'de>, B: Deserialize<'de>> Visitor<'de>
+    for OrderedMapVisitor<A, B>
+  {
+    type Value = Vec<(A, B)>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      write!(f, "a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(
+      self,
+      mut access: M,
+    ) -> Result<Self::Value, M::Error> {
+      let mut out = Vec::with_capacity(access.size_hint().unwrap_or(0));
+      while let Some(pair) = access.next_entry()? {
+        out.push(pair);
+      }
+      Ok(out)
+    }
+  }
+
+  deserializer.deserialize_map(OrderedMapVisitor(PhantomData))
+}
+
+impl<'de, T, K, H> Deserialize<'de> for Registry<T, K, H>
+where
+  T: Deserialize<'de>,
+  K: RegistryKey + Deserialize<'de>,
+  H: BuildHasher + Default,
+{
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    let repr = RegistryRepr::<T, K>::deserialize(deserializer)?;
+
+    let mut registry = Registry::default();
+    for (nsid, x) in repr.entries {
+      registry.register(x, nsid).map_err(|_| {
+        de::Error::custom(format_args!("{:?} was registered twice", nsid))
+      })?;
+    }
+    for (nsid, members) in repr.categories {
+      let mut handles = Vec::with_capacity(members.len());
+      for member in members {
+        let handle = registry.validate_nsid(member).ok_or_else(|| {
+          de::Error::custom(format_args!(
+            "category {:?} contains {:?}, which is not registered",
+            nsid, member
+          ))
+        })?;
+        handles.push(handle);
+      }
+      registry.register_category(nsid, handles).map_err(|_| {
+        de::Error::custom(format_args!(
+          "category {:?} was registered twice",
+          nsid
+        ))
+      })?;
+    }
+    for (nsid, children) in repr.subcategories {
+      let find = |category| {
+        registry.validate_category_nsid(category).ok_or_else(|| {
+          de::Error::custom(format_args!(
+            "{:?} is nested but is not a registered category",
+            category
+          ))
+        })
+      };
+      let parent = find(nsid)?;
+      let children = children
+        .into_iter()
+        .map(find)
+        .collect::<Result<Vec<_>, _>>()?;
+      for child in children {
+        registry
+          .nest_category(parent, child)
+          .map_err(de::Error::custom)?;
+      }
+    }
+    Ok(registry)
+  }
+}
+
+impl Serialize for SyncMap {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(self.nsids())
+  }
+}
+
+impl<'de> Deserialize<'de> for SyncMap {
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    let nsids = Vec::<NamespacedID>::deserialize(deserializer)?;
+    SyncMap::from_nsids(nsids).map_err(|nsid| {
+      de::Error::custom(format_args!("{} has more than one ID", nsid))
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[derive(Serialize)]
+  struct Recipe {
+    input: CategoryHandle<u32>,
+    count: u32,
+  }
+
+  #[test]
+  fn category_handles_round_trip_through_a_seed() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let stones = registry
+      .register_category(nsid("game:stones"), [stone])
+      .unwrap();
+
+    let recipe = Recipe {
+      input: stones,
+      count: 4,
+    };
+    let written = serde_json::to_value(&recipe).unwrap();
+    assert_eq!(written, json!({"input": "game:stones", "count": 4}));
+
+    let read = Recipe {
+      input: registry
+        .category_seed()
+        .deserialize(written["input"].clone())
+        .unwrap(),
+      count: serde_json::from_value(written["count"].clone()).unwrap(),
+    };
+    assert_eq!(read.input, stones);
+    assert_eq!(read.count, 4);
+
+    let err = registry
+      .category_seed()
+      .deserialize(Value::from("game:stone"))
+      .unwrap_err();
+    assert!(err.to_string().contains("not a registered category"));
+  }
+
+  #[test]
+  fn registry_round_trips() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+    registry.register(3, nsid("game:air")).unwrap();
+    registry
+      .register_category(nsid("game:blocks"), [stone, dirt])
+      .unwrap();
+
+    let written = serde_json::to_value(&registry).unwrap();
+    assert_eq!(
+      written,
+      json!({
+        "entries": {"game:stone": 1, "game:dirt": 2, "game:air": 3},
+        "categories": {"game:blocks": ["game:stone", "game:dirt"]},
+        "subcategories": {},
+      })
+    );
+    let read: Registry<u32> = serde_json::from_value(written).unwrap();
+    assert!(read == registry);
+    let blocks = read.lookup_category_by_nsid(nsid("game:blocks")).unwrap();
+    assert_eq!(blocks.count(), 2);
+  }
+
+  #[test]
+  fn registry_with_unknown_member_fails() {
+    let written = json!({
+      "entries": {"game:stone": 1},
+      "categories": {"game:blocks": ["game:stone", "game:dirt"]},
+    });
+    let err = match serde_json::from_value::<Registry<u32>>(written) {
+      Ok(_) => panic!("deserialized a category with a missing member"),
+      Err(err) => err,
+    };
+    assert!(err.to_string().contains("not registered"));
+  }
+
+  #[test]
+  fn nsids_are_strings() {
+    let written = serde_json::to_value(nsid("game:blocks/stone")).unwrap();
+    assert_eq!(written, json!("game:blocks/stone"));
+    let read: NamespacedID = serde_json::from_value(written).unwrap();
+    assert_eq!(read, nsid("game:blocks/stone"));
+
+    assert!(serde_json::from_value::<NamespacedID>(json!("no colon")).is_err());
+    assert!(serde_json::from_value::<NamespacedID>(json!(4)).is_err());
+  }
+
+  #[test]
+  fn handles_round_trip_through_a_seed() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+
+    let written = serde_json::to_value(stone).unwrap();
+    assert_eq!(written, json!("game:stone"));
+    let read = registry.handle_seed().deserialize(written).unwrap();
+    assert_eq!(read, stone);
+
+    let err = registry
+      .handle_seed()
+      .deserialize(Value::from("game:dirt"))
+      .unwrap_err();
+    assert!(err.to_string().contains("is not registered"));
+  }
+
+  #[test]
+  fn sync_maps_are_lists_of_nsids() {
+    let mut registry = Registry::<u32>::new();
+    registry.register(1, nsid("game:stone")).unwrap();
+    registry.register(2, nsid("game:air")).unwrap();
+    let map = registry.export_id_map();
+
+    let written = serde_json::to_value(&map).unwrap();
+    assert_eq!(written, json!(["game:air", "game:stone"]));
+    let read: SyncMap = serde_json::from_value(written).unwrap();
+    assert_eq!(read, map);
+
+    let err = serde_json::from_value::<SyncMap>(json!(["game:a", "game:a"]))
+      .unwrap_err();
+    assert!(err.to_string().contains("more than one ID"));
+  }
+
+  #[test]
+  fn holders_read_back_unresolved() {
+    let mut registry = Registry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+
+    let written = serde_json::to_value(Holder::from(stone)).unwrap();
+    assert_eq!(written, json!("game:stone"));
+    let mut read: Holder<u32> = serde_json::from_value(written).unwrap();
+    assert!(!read.is_resolved());
+    assert_eq!(read.resolve(&registry), Some(stone));
+  }
+
+  #[test]
+  fn holder_sets_are_a_tag_or_a_list() {
+    let read: HolderSet<u32> =
+      serde_json::from_value(json!("#game:blocks")).unwrap();
+    assert!(matches!(read, HolderSet::Category(c) if c == nsid("game:blocks")));
+    assert_eq!(serde_json::to_value(&read).unwrap(), json!("#game:blocks"));
+
+    let read: HolderSet<u32> =
+      serde_json::from_value(json!(["game:stone", "game:dirt"])).unwrap();
+    match &read {
+      HolderSet::List(holders) => assert_eq!(
+        holders,
+        &[
+          Holder::Unresolved(nsid("game:stone")),
+          Holder::Unresolved(nsid("game:dirt"))
+        ]
+      ),
+      HolderSet::Category(_) => panic!("expected a list"),
+    }
+    assert_eq!(
+      serde_json::to_value(&read).unwrap(),
+      json!(["game:stone", "game:dirt"])
+    );
+
+    let read: HolderSet<u32> =
+      serde_json::from_value(json!("game:stone")).unwrap();
+    assert!(matches!(read, HolderSet::List(holders) if holders.len() == 1));
+    assert!(serde_json::from_value::<HolderSet<u32>>(json!(3)).is_err());
+  }
+}
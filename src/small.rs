@@ -0,0 +1,251 @@
+use std::{
+  fmt::Debug,
+  hash::{BuildHasher, Hash},
+  marker::PhantomData,
+  ops::Deref,
+};
+
+use ahash::RandomState;
+
+use crate::{
+  CategoryHandle, ErrCategoryAlreadyRegistered, NamespacedID, Registry,
+  RegistryHandle, RegistryKey, SmallRegisterError,
+};
+
+/// A registry for at most 65536 things, handing out 2-byte [`SmallHandle`]s.
+///
+/// A `RegistryHandle` carries its NSID around, which is handy but big.
+/// When you're storing a lot of them, like in a chunk palette, use one of these instead.
+///
+/// Every registration uses up a handle for good, even if it's unregistered later.
+///
+/// Reading from the registry works through `Deref`. `small_handle` and `handle` go between the two kinds of handle.
+pub struct SmallRegistry<T, K = NamespacedID, S = RandomState> {
+  registry: Registry<T, K, S>,
+}
+
+impl<T, K: RegistryKey> SmallRegistry<T, K> {
+  pub fn new() -> Self {
+    Self {
+      registry: Registry::new(),
+    }
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> SmallRegistry<T, K, S> {
+  /// Register something new.
+  pub fn register(
+    &mut self,
+    x: T,
+    nsid: K,
+  ) -> Result<SmallHandle<T>, SmallRegisterError> {
+    if self.registry.arena.len() > u16::MAX as usize {
+      return Err(SmallRegisterError::Full);
+    }
+    let handle = self.registry.register(x, nsid)?;
+    Ok(SmallHandle::new(handle.to_raw() as u16))
+  }
+
+  /// Unregister something, handing back its value. See `Registry::unregister`.
+  ///
+  /// Its handle isn't given out again.
+  pub fn unregister(&mut self, handle: SmallHandle<T>) -> Option<T> {
+    let handle = self.handle(handle)?;
+    self.registry.unregister(handle)
+  }
+
+  /// Register a category out of some things in here.
+  pub fn register_category(
+    &mut self,
+    nsid: K,
+    entries: impl IntoIterator<Item = SmallHandle<T>>,
+  ) -> Result<CategoryHandle<T, K>, ErrCategoryAlreadyRegistered> {
+    let entries: Vec<_> = entries
+      .into_iter()
+      .map(|handle| self.full_handle(handle))
+      .collect();
+    self.registry.register_category(nsid, entries)
+  }
+
+  /// Look up something by its handle.
+  ///
+  /// Panics if it's been unregistered, just like `Registry::lookup`.
+  pub fn lookup(&self, handle: SmallHandle<T>) -> &T {
+    self.registry.lookup(self.full_handle(handle))
+  }
+
+  /// Look up something by its handle, mutably.
+  pub fn lookup_mut(&mut self, handle: SmallHandle<T>) -> &mut T {
+    let handle = self.full_handle(handle);
+    self.registry.lookup_mut(handle)
+  }
+
+  /// Get the handle of whatever is registered under this NSID.
+  pub fn validate_nsid(&self, nsid: K) -> Option<SmallHandle<T>> {
+    self
+      .registry
+      .validate_nsid(nsid)
+      .map(|handle| self.small_handle(handle))
+  }
+
+  /// Get the NSID something was registered under.
+  ///
+  /// Panics if it's been unregistered.
+  pub fn nsid_of(&self, handle: SmallHandle<T>) -> K {
+    self.full_handle(handle).get_nsid()
+  }
+
+  /// Shrink a handle from this registry down to a small one.
+  pub fn small_handle(&self, handle: RegistryHandle<T, K>) -> SmallHandle<T> {
+    SmallHandle::new(handle.to_raw() as u16)
+  }
+
+  /// Get the full handle for a small one, or `None` if it's been unregistered.
+  pub fn handle(&self, handle: SmallHandle<T>) -> Option<RegistryHandle<T, K>> {
+    self.registry.handle_from_raw(handle.idx as u32)
+  }
+
+  /// Turn a number from `SmallHandle::to_raw` back into a handle.
+  ///
+  /// Returns `None` if nothing was ever registered there, or if it's been unregistered.
+  pub fn handle_from_raw(&self, raw: u16) -> Option<SmallHandle<T>> {
+    self
+      .handle(SmallHandle::new(raw))
+      .map(|_| SmallHandle::new(raw))
+  }
+
+  /// Stop limiting the size, and go back to a normal registry.
+  pub fn into_inner(self) -> Registry<T, K, S> {
+    self.registry
+  }
+
+  fn full_handle(&self, handle: SmallHandle<T>) -> RegistryHandle<T, K> {
+    self
+      .handle(handle)
+      .expect("used a handle to an entry that was unregistered")
+  }
+}
+
+impl<T, K: RegistryKey, S: BuildHasher + Default> Default
+  for SmallRegistry<T, K, S>
+{
+  fn default() -> Self {
+    Self {
+      registry: Registry::default(),
+    }
+  }
+}
+
+impl<T, K, S> Deref for SmallRegistry<T, K, S> {
+  type Target = Registry<T, K, S>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.registry
+  }
+}
+
+/// Handle to an entry in a [`SmallRegistry`], in only 2 bytes.
+pub struct SmallHandle<T> {
+  idx: u16,
+  phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> SmallHandle<T> {
+  fn new(idx: u16) -> Self {
+    Self {
+      idx,
+      phantom: PhantomData,
+    }
+  }
+
+  /// Get the number inside, for packing into network packets or chunk data.
+  pub fn to_raw(&self) -> u16 {
+    self.idx
+  }
+}
+
+// Manual impls cause it doesn't believe me it doesn't actually own a T
+impl<T> Clone for SmallHandle<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for SmallHandle<T> {}
+
+impl<T> Hash for SmallHandle<T> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.idx.hash(state);
+  }
+}
+
+impl<T> PartialEq for SmallHandle<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.idx == other.idx
+  }
+}
+
+impl<T> Eq for SmallHandle<T> {}
+
+impl<T> Debug for SmallHandle<T> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_tuple("SmallHandle").field(&self.idx).finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn small_handles_round_trip() {
+    assert_eq!(std::mem::size_of::<SmallHandle<u32>>(), 2);
+    let mut registry = SmallRegistry::<u32>::new();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    let dirt = registry.register(2, nsid("game:dirt")).unwrap();
+
+    assert_eq!(registry.lookup(dirt), &2);
+    assert_eq!(registry.nsid_of(stone), nsid("game:stone"));
+    assert_eq!(registry.validate_nsid(nsid("game:dirt")), Some(dirt));
+    let full = registry.handle(stone).unwrap();
+    assert_eq!(registry.small_handle(full), stone);
+    assert_eq!(registry.handle_from_raw(dirt.to_raw()), Some(dirt));
+    assert_eq!(registry.lookup_by_nsid(nsid("game:stone")), Some(&1));
+  }
+
+  #[test]
+  fn unregistered_handles_are_not_reused() {
+    let mut registry = SmallRegistry::<
+      u32,
+      NamespacedID,
+      BuildHasherDefault<DefaultHasher>,
+    >::default();
+    let stone = registry.register(1, nsid("game:stone")).unwrap();
+    assert_eq!(registry.unregister(stone), Some(1));
+    assert_eq!(registry.unregister(stone), None);
+    assert_eq!(registry.handle(stone), None);
+
+    let again = registry.register(1, nsid("game:stone")).unwrap();
+    assert_ne!(again, stone);
+    assert_eq!(registry.into_inner().len(), 1);
+  }
+
+  #[test]
+  fn fills_up_at_u16_max() {
+    let mut registry = SmallRegistry::<()>::new();
+    for i in 0..=u16::MAX as u32 {
+      let nsid = NamespacedID::new_from_parts("game", format!("e{}", i));
+      registry.register((), nsid.unwrap()).unwrap();
+    }
+    assert!(matches!(
+      registry.register((), nsid("game:one_too_many")),
+      Err(SmallRegisterError::Full)
+    ));
+  }
+}
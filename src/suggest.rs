@@ -0,0 +1,43 @@
+//! Helpers for working out which NSIDs someone probably meant.
+
+/// Levenshtein distance between two strings, counted in chars.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut prev: Vec<usize> = (0..=b.len()).collect();
+  let mut cur = vec![0; b.len() + 1];
+  for (i, ca) in a.chars().enumerate() {
+    cur[0] = i + 1;
+    for (j, cb) in b.iter().enumerate() {
+      let substitute = prev[j] + usize::from(ca != *cb);
+      cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+    }
+    std::mem::swap(&mut prev, &mut cur);
+  }
+  prev[b.len()]
+}
+
+/// The most edits a candidate can be away from `target` and still count as a typo of it.
+pub(crate) fn max_typo_distance(target: &str) -> usize {
+  (target.chars().count() / 3).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn edit_distance_counts_chars() {
+    assert_eq!(edit_distance("stone", "stone"), 0);
+    assert_eq!(edit_distance("stone", "stine"), 1);
+    assert_eq!(edit_distance("stone", "stones"), 1);
+    assert_eq!(edit_distance("stone", "tone"), 1);
+    assert_eq!(edit_distance("", "dirt"), 4);
+    assert_eq!(edit_distance("café", "cafe"), 1);
+  }
+
+  #[test]
+  fn short_targets_still_allow_one_typo() {
+    assert_eq!(max_typo_distance("ab"), 1);
+    assert_eq!(max_typo_distance("game:stone"), 3);
+  }
+}
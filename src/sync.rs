@@ -0,0 +1,192 @@
+use std::hash::BuildHasher;
+
+use ahash::AHashMap;
+
+use crate::{
+  ApplySyncMapError, ArenaID, NamespacedID, Registry, RegistryHandle,
+};
+
+/// A numbering of every NSID in a registry, for sending entries over the network as small numbers
+/// instead of strings.
+///
+/// The server makes one with [`Registry::export_id_map`] and sends it to the client once
+/// (it serializes as a list of NSIDs with the `serde` feature). Then both sides `apply_id_map` it,
+/// and can turn handles into numbers with `sync_id` and back with `handle_from_sync_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncMap {
+  /// Each NSID is at the index of its number
+  nsids: Vec<NamespacedID>,
+  ids: AHashMap<NamespacedID, u32>,
+}
+
+impl SyncMap {
+  /// Number these NSIDs in order, or return the first one that shows up twice.
+  pub(crate) fn from_nsids(
+    nsids: Vec<NamespacedID>,
+  ) -> Result<Self, NamespacedID> {
+    let mut ids = AHashMap::with_capacity(nsids.len());
+    for (idx, nsid) in nsids.iter().enumerate() {
+      if ids.insert(*nsid, idx as u32).is_some() {
+        return Err(*nsid);
+      }
+    }
+    Ok(Self { nsids, ids })
+  }
+
+  /// Get the number for this NSID.
+  pub fn id_of(&self, nsid: NamespacedID) -> Option<u32> {
+    self.ids.get(&nsid).copied()
+  }
+
+  /// Get the NSID with this number.
+  pub fn nsid_of(&self, id: u32) -> Option<NamespacedID> {
+    self.nsids.get(id as usize).copied()
+  }
+
+  /// Iterate over every NSID in order of its number.
+  pub fn nsids(&self) -> impl Iterator<Item = NamespacedID> + '_ {
+    self.nsids.iter().copied()
+  }
+
+  pub fn len(&self) -> usize {
+    self.nsids.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nsids.is_empty()
+  }
+}
+
+/// A `SyncMap` after it's been applied to a particular registry.
+pub(crate) struct SyncIds<T> {
+  /// The entry for each number. `None` if the entry was unregistered since
+  by_id: Vec<Option<ArenaID<T>>>,
+  by_entry: AHashMap<ArenaID<T>, u32>,
+}
+
+impl<T> SyncIds<T> {
+  /// Move these over to another registry with the same entries under different IDs, like a clone.
+  pub(crate) fn remap(&self, remap: &AHashMap<ArenaID<T>, ArenaID<T>>) -> Self {
+    let by_id: Vec<_> = self
+      .by_id
+      .iter()
+      .map(|id| id.and_then(|id| remap.get(&id).copied()))
+      .collect();
+    let by_entry = by_id
+      .iter()
+      .enumerate()
+      .filter_map(|(idx, id)| Some(((*id)?, idx as u32)))
+      .collect();
+    Self { by_id, by_entry }
+  }
+
+  pub(crate) fn forget(&mut self, id: ArenaID<T>) {
+    if let Some(idx) = self.by_entry.remove(&id) {
+      self.by_id[idx as usize] = None;
+    }
+  }
+}
+
+impl<T, S: BuildHasher + Default> Registry<T, NamespacedID, S> {
+  /// Number everything in this registry, in order of NSID so it's the same for any registry with the same NSIDs.
+  pub fn export_id_map(&self) -> SyncMap {
+    let mut nsids: Vec<_> = self.nsid_map.keys().copied().collect();
+    nsids.sort();
+    SyncMap::from_nsids(nsids).unwrap()
+  }
+
+  /// Use the numbering from a `SyncMap`, probably one from the server, for `sync_id` and `handle_from_sync_id`.
+  ///
+  /// Every NSID in the map has to be registered here; if any aren't, nothing changes and they're all reported.
+  /// It's fine for this registry to have extra entries, but they don't get numbers.
+  /// Entries registered afterwards don't get numbers either.
+  pub fn apply_id_map(
+    &mut self,
+    map: &SyncMap,
+  ) -> Result<(), ApplySyncMapError> {
+    let missing: Vec<_> = map
+      .nsids()
+      .filter(|nsid| !self.nsid_map.contains_key(nsid))
+      .collect();
+    if !missing.is_empty() {
+      return Err(ApplySyncMapError { missing });
+    }
+    let by_id: Vec<_> =
+      map.nsids().map(|nsid| Some(self.nsid_map[&nsid])).collect();
+    let by_entry = map
+      .nsids()
+      .enumerate()
+      .map(|(idx, nsid)| (self.nsid_map[&nsid], idx as u32))
+      .collect();
+    self.sync_ids = Some(SyncIds { by_id, by_entry });
+    Ok(())
+  }
+
+  /// Get the number for this entry from the applied `SyncMap`.
+  ///
+  /// Returns `None` if no map was applied or this entry doesn't have a number.
+  pub fn sync_id(&self, handle: RegistryHandle<T>) -> Option<u32> {
+    self.sync_ids.as_ref()?.by_entry.get(&handle.id).copied()
+  }
+
+  /// Get the handle for this number from the applied `SyncMap`.
+  ///
+  /// Returns `None` if no map was applied, or if nothing has that number (or it was unregistered).
+  pub fn handle_from_sync_id(&self, id: u32) -> Option<RegistryHandle<T>> {
+    let id = (*self.sync_ids.as_ref()?.by_id.get(id as usize)?)?;
+    Some(RegistryHandle::new(id, self.slot(id).1))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nsid(s: &str) -> NamespacedID {
+    NamespacedID::new(s).unwrap()
+  }
+
+  #[test]
+  fn client_adopts_the_server_numbering() {
+    let mut server = Registry::<u32>::new();
+    for s in ["game:stone", "game:air", "game:dirt"] {
+      server.register(0, nsid(s)).unwrap();
+    }
+    // Registered in a different order, with an extra entry
+    let mut client = Registry::<u32>::new();
+    for s in ["game:dirt", "client:debug", "game:stone", "game:air"] {
+      client.register(0, nsid(s)).unwrap();
+    }
+
+    let map = server.export_id_map();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.id_of(nsid("game:air")), Some(0));
+    assert_eq!(map.nsid_of(2), Some(nsid("game:stone")));
+    server.apply_id_map(&map).unwrap();
+    client.apply_id_map(&map).unwrap();
+
+    let stone = server.validate_nsid(nsid("game:stone")).unwrap();
+    let id = server.sync_id(stone).unwrap();
+    let on_client = client.handle_from_sync_id(id).unwrap();
+    assert_eq!(on_client.get_nsid(), nsid("game:stone"));
+
+    let debug = client.validate_nsid(nsid("client:debug")).unwrap();
+    assert_eq!(client.sync_id(debug), None);
+    client.unregister(on_client);
+    assert_eq!(client.handle_from_sync_id(id), None);
+    assert_eq!(client.handle_from_sync_id(99), None);
+  }
+
+  #[test]
+  fn applying_a_map_with_unknown_nsids_changes_nothing() {
+    let mut server = Registry::<u32>::new();
+    server.register(0, nsid("game:stone")).unwrap();
+    server.register(0, nsid("game:lava")).unwrap();
+    let mut client = Registry::<u32>::new();
+    let stone = client.register(0, nsid("game:stone")).unwrap();
+
+    let err = client.apply_id_map(&server.export_id_map()).unwrap_err();
+    assert_eq!(err.missing, [nsid("game:lava")]);
+    assert_eq!(client.sync_id(stone), None);
+  }
+}